@@ -0,0 +1,124 @@
+//! Fuzzy search over a user-maintained JSON catalog
+//!
+//! A catalog is a JSON array of entries such as bookmarks or snippets:
+//!
+//! ```json
+//! [{"title": "Rust docs", "subtitle": "std", "arg": "https://doc.rust-lang.org/std/",
+//!   "icon": "icons/rust.png", "match": "rust std library"}]
+//! ```
+//!
+//! `title` and `arg` are required; unknown keys are rejected so typos surface
+//! instead of being silently ignored.
+
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+use crate::{fuzzy_filter_sort, Error, Icon, Item};
+
+/// One searchable catalog entry
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CatalogEntry {
+    pub title: String,
+    #[serde(default)]
+    pub subtitle: Option<String>,
+    /// Value passed on when the item is actioned
+    pub arg: String,
+    /// Icon file path
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// Text matched against the query instead of the title
+    #[serde(default, rename = "match")]
+    pub match_field: Option<String>,
+}
+
+impl CatalogEntry {
+    /// Text the query is matched against
+    pub fn match_text(&self) -> &str {
+        self.match_field.as_deref().unwrap_or(&self.title)
+    }
+
+    /// Alfred item for this entry (uid is the `arg`, for Alfred's learning)
+    pub fn to_item(&self) -> Item {
+        let mut item = Item::title_only(&self.title)
+            .uid(&self.arg)
+            .arg(&self.arg)
+            .subtitle_opt(self.subtitle.as_deref());
+        if let Some(icon) = &self.icon {
+            item = item.icon(Icon::path(icon));
+        }
+        if let Some(match_field) = &self.match_field {
+            item = item.match_field(match_field);
+        }
+        item
+    }
+}
+
+/// Read and validate a catalog file
+pub fn load_catalog(path: &Path) -> Result<Vec<CatalogEntry>, Error> {
+    if !path.exists() {
+        return Err(Error::NotFound(path.to_path_buf()));
+    }
+    parse_catalog(&fs::read_to_string(path)?)
+}
+
+/// Parse catalog JSON, rejecting entries with an empty `title` or `arg`
+pub fn parse_catalog(json: &str) -> Result<Vec<CatalogEntry>, Error> {
+    let entries: Vec<CatalogEntry> =
+        serde_json::from_str(json).map_err(|e| Error::Parse(e.to_string()))?;
+    for (i, entry) in entries.iter().enumerate() {
+        if entry.title.trim().is_empty() {
+            return Err(Error::Parse(format!("entry {}: title is empty", i)));
+        }
+        if entry.arg.trim().is_empty() {
+            return Err(Error::Parse(format!("entry {}: arg is empty", i)));
+        }
+    }
+    Ok(entries)
+}
+
+/// Items for entries fuzzy-matching `query`, best first (all entries, in
+/// file order, for an empty query)
+pub fn search_catalog(entries: Vec<CatalogEntry>, query: &str) -> Vec<Item> {
+    fuzzy_filter_sort(entries, query, |entry| entry.match_text())
+        .into_iter()
+        .map(|(entry, _)| entry.to_item())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOOKMARKS: &str = include_str!("../tests/fixtures/catalog/bookmarks.json");
+
+    #[test]
+    fn test_search_catalog_fixture() {
+        let entries = parse_catalog(BOOKMARKS).unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[2].icon.as_deref(), Some("icons/flow.png"));
+
+        let items = search_catalog(entries.clone(), "json");
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].title, "Alfred Script Filter JSON");
+        assert_eq!(
+            items[0].match_field.as_deref(),
+            Some("alfred script filter json format")
+        );
+
+        let items = search_catalog(entries.clone(), "flow");
+        assert_eq!(items[0].arg.as_deref(), Some("~/code/flow"));
+        assert_eq!(items[0].subtitle.as_deref(), Some("~/code/flow"));
+
+        assert_eq!(search_catalog(entries, "").len(), 3);
+    }
+
+    #[test]
+    fn test_parse_catalog_rejects_invalid_entries() {
+        assert!(parse_catalog(r#"[{"title": "x", "arg": "y", "url": "z"}]"#).is_err());
+        assert!(parse_catalog(r#"[{"title": "x"}]"#).is_err());
+        let err = parse_catalog(r#"[{"title": " ", "arg": "y"}]"#).unwrap_err();
+        assert_eq!(err.to_string(), "Parse error: entry 0: title is empty");
+    }
+}
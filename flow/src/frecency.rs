@@ -0,0 +1,191 @@
+//! Workflow-managed frecency ranking
+//!
+//! Each key keeps a decayed pick count: every [`Frecency::record`] adds 1,
+//! and the accumulated value halves every `half_life_days`. Scores are plain
+//! numbers in a JSON file, so ranking can be inspected and reset by hand.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{data_dir, write_atomic, Error, Item};
+
+const SECONDS_PER_DAY: f64 = 86_400.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Entry {
+    /// Decayed score as of `updated`
+    score: f64,
+    /// Unix seconds of the last record
+    updated: i64,
+}
+
+/// Recency-weighted frequency store backed by a JSON file
+#[derive(Debug)]
+pub struct Frecency {
+    path: PathBuf,
+    half_life_days: f64,
+    entries: HashMap<String, Entry>,
+}
+
+impl Frecency {
+    /// Open `data_dir()/frecency.json` (None outside Alfred)
+    pub fn load() -> Option<Self> {
+        data_dir().map(|dir| Self::open(dir.join("frecency.json")))
+    }
+
+    /// Open a store at a specific path, starting empty if it doesn't exist
+    pub fn open(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let entries = fs::read_to_string(&path)
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default();
+        Self {
+            path,
+            half_life_days: 14.0,
+            entries,
+        }
+    }
+
+    /// Days for a score to decay to half its value (default 14)
+    ///
+    /// Zero, negative and non-finite values are ignored, since they would
+    /// turn every decayed score into NaN or infinity.
+    pub fn half_life(mut self, days: f64) -> Self {
+        if days.is_finite() && days > 0.0 {
+            self.half_life_days = days;
+        }
+        self
+    }
+
+    /// Path of the backing JSON file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Record a pick of `key` now and persist the store
+    pub fn record(&mut self, key: &str) -> Result<(), Error> {
+        self.record_at(key, chrono::Utc::now().timestamp());
+        self.save()
+    }
+
+    /// Record a pick of `key` at a given unix time (without saving)
+    pub fn record_at(&mut self, key: &str, now: i64) {
+        let score = self.score_at(key, now) + 1.0;
+        self.entries.insert(
+            key.to_string(),
+            Entry {
+                score,
+                updated: now,
+            },
+        );
+    }
+
+    /// Current score for `key` (0.0 if never recorded)
+    pub fn score(&self, key: &str) -> f64 {
+        self.score_at(key, chrono::Utc::now().timestamp())
+    }
+
+    /// Score for `key` as of a given unix time
+    pub fn score_at(&self, key: &str, now: i64) -> f64 {
+        match self.entries.get(key) {
+            Some(entry) => entry.score * self.decay(now - entry.updated),
+            None => 0.0,
+        }
+    }
+
+    /// Multiplier for a score that is `elapsed` seconds old
+    fn decay(&self, elapsed: i64) -> f64 {
+        let days = elapsed.max(0) as f64 / SECONDS_PER_DAY;
+        0.5_f64.powf(days / self.half_life_days)
+    }
+
+    /// Reorder items by score, highest first; ties keep their existing order
+    pub fn apply<F>(&self, items: &mut [Item], key_fn: F)
+    where
+        F: Fn(&Item) -> &str,
+    {
+        let now = chrono::Utc::now().timestamp();
+        items.sort_by(|a, b| {
+            let score_a = self.score_at(key_fn(a), now);
+            let score_b = self.score_at(key_fn(b), now);
+            score_b.total_cmp(&score_a)
+        });
+    }
+
+    /// Forget a single key
+    pub fn remove(&mut self, key: &str) {
+        self.entries.remove(key);
+    }
+
+    /// Forget every key
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Write the store to disk atomically, creating the parent directory as needed
+    pub fn save(&self) -> Result<(), Error> {
+        let data = serde_json::to_string_pretty(&self.entries).map_err(|e| Error::Io(e.into()))?;
+        write_atomic(&self.path, data.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixture_dir;
+
+    const DAY: i64 = 86_400;
+
+    #[test]
+    fn test_score_decays_by_half_life() {
+        let dir = fixture_dir("frecency-decay");
+        let mut store = Frecency::open(dir.join("f.json")).half_life(7.0);
+
+        store.record_at("a", 0);
+        store.record_at("a", 0);
+        assert!((store.score_at("a", 0) - 2.0).abs() < 1e-9);
+        assert!((store.score_at("a", 7 * DAY) - 1.0).abs() < 1e-9);
+        assert!((store.score_at("a", 14 * DAY) - 0.5).abs() < 1e-9);
+
+        // A new pick adds to the decayed score
+        store.record_at("a", 7 * DAY);
+        assert!((store.score_at("a", 7 * DAY) - 2.0).abs() < 1e-9);
+        assert_eq!(store.score_at("missing", 0), 0.0);
+    }
+
+    #[test]
+    fn test_half_life_ignores_non_positive_days() {
+        let dir = fixture_dir("frecency-half-life");
+        for days in [0.0, -3.0, f64::NAN, f64::INFINITY] {
+            let mut store = Frecency::open(dir.join("f.json")).half_life(days);
+            store.record_at("a", 0);
+            assert!((store.score_at("a", 14 * DAY) - 0.5).abs() < 1e-9, "{days}");
+        }
+    }
+
+    #[test]
+    fn test_persists_across_reload_and_ranks_items() {
+        let dir = fixture_dir("frecency-persist");
+        let path = dir.join("data/frecency.json");
+
+        let mut store = Frecency::open(&path);
+        store.record("beta").unwrap();
+        store.record("beta").unwrap();
+        store.record("gamma").unwrap();
+
+        let reloaded = Frecency::open(&path);
+        assert!(reloaded.score("beta") > reloaded.score("gamma"));
+
+        let mut items = vec![
+            Item::title_only("alpha").uid("alpha"),
+            Item::title_only("gamma").uid("gamma"),
+            Item::title_only("beta").uid("beta"),
+        ];
+        reloaded.apply(&mut items, |item| item.uid.as_deref().unwrap_or(""));
+        let titles: Vec<&str> = items.iter().map(|i| i.title.as_str()).collect();
+        assert_eq!(titles, ["beta", "gamma", "alpha"]);
+    }
+}
@@ -0,0 +1,275 @@
+//! Long-running work behind a polling Script Filter
+//!
+//! A [`BackgroundJob`] runs a command detached from the Script Filter, so the
+//! filter can return a [`progress`](crate::Output::progress) item right away
+//! and poll on rerun until the result is in:
+//!
+//! ```no_run
+//! use flow_alfred::jobs::BackgroundJob;
+//! use flow_alfred::{Item, Output};
+//!
+//! let job = BackgroundJob::new("fetch-issues").expect("running in Alfred");
+//! if let Some(stdout) = job.result() {
+//!     Output::new(stdout.lines().map(Item::title_only).collect()).print();
+//! } else {
+//!     if !job.is_running() {
+//!         job.start("gh", ["issue", "list"]).expect("spawn gh");
+//!     }
+//!     Output::progress("Fetching issues…", "", 0.5).print();
+//! }
+//! ```
+//!
+//! Each job keeps three files under `cache_dir()/jobs`: `<name>.pid` while it
+//! runs, and `<name>.out` (stdout) plus `<name>.status` (exit code) once done.
+//!
+//! Alfred can rerun a filter while the previous run is still deciding whether
+//! to start the job; hold a [`FileLock`] around the `is_running` check and
+//! `start` so only one of them spawns it.
+
+use std::ffi::OsStr;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::{cache_dir, write_atomic, Error};
+
+/// Runs the job's command, then publishes stdout and the exit code; the
+/// status file is written last, so its presence means the output is complete
+const JOB_SCRIPT: &str = r#""$@" >"$FLOW_JOB_OUT.tmp" 2>/dev/null
+code=$?
+mv "$FLOW_JOB_OUT.tmp" "$FLOW_JOB_OUT"
+echo "$code" >"$FLOW_JOB_STATUS""#;
+
+/// A named command run detached from the Script Filter that started it
+#[derive(Debug, Clone)]
+pub struct BackgroundJob {
+    name: String,
+    dir: PathBuf,
+}
+
+impl BackgroundJob {
+    /// Job with state under `cache_dir()/jobs` (None outside Alfred)
+    pub fn new(name: impl Into<String>) -> Option<Self> {
+        cache_dir().map(|dir| Self::in_dir(name, dir.join("jobs")))
+    }
+
+    /// Job with state files in a specific directory
+    pub fn in_dir(name: impl Into<String>, dir: impl Into<PathBuf>) -> Self {
+        Self {
+            name: name.into(),
+            dir: dir.into(),
+        }
+    }
+
+    fn file(&self, extension: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", self.name, extension))
+    }
+
+    /// Spawn `program` detached, discarding any previous result
+    ///
+    /// The process gets its own process group and no stdio, so Alfred doesn't
+    /// wait on it and it outlives the Script Filter.
+    pub fn start<I, S>(&self, program: impl AsRef<OsStr>, args: I) -> Result<(), Error>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        fs::create_dir_all(&self.dir)?;
+        for extension in ["out", "status"] {
+            let _ = fs::remove_file(self.file(extension));
+        }
+
+        let mut command = Command::new("sh");
+        command
+            .arg("-c")
+            .arg(JOB_SCRIPT)
+            .arg("sh")
+            .arg(program)
+            .args(args)
+            .env("FLOW_JOB_OUT", self.file("out"))
+            .env("FLOW_JOB_STATUS", self.file("status"))
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            command.process_group(0);
+        }
+        let child = command.spawn()?;
+        write_atomic(&self.file("pid"), child.id().to_string().as_bytes())
+    }
+
+    /// Whether the job was started and hasn't finished or died yet
+    pub fn is_running(&self) -> bool {
+        !self.file("status").exists() && read_pid(&self.file("pid")).is_some_and(pid_is_alive)
+    }
+
+    /// The job's stdout once it has finished, whatever its exit code
+    pub fn result(&self) -> Option<String> {
+        if !self.file("status").exists() {
+            return None;
+        }
+        fs::read_to_string(self.file("out")).ok()
+    }
+
+    /// Exit code of the finished job
+    pub fn exit_code(&self) -> Option<i32> {
+        fs::read_to_string(self.file("status"))
+            .ok()?
+            .trim()
+            .parse()
+            .ok()
+    }
+
+    /// Forget the job's pid and result, e.g. to force a fresh run
+    pub fn clear(&self) {
+        for extension in ["pid", "out", "status"] {
+            let _ = fs::remove_file(self.file(extension));
+        }
+    }
+}
+
+/// Single-instance guard: an exclusive `flock` on a `.lock` file, released on drop
+///
+/// The OS drops the lock when its holder exits, however it exits, so a lock
+/// left by a crashed process is never stale: the next caller simply gets it.
+/// The file holds the owner's pid for inspection and is left in place, since
+/// unlinking it could let a second caller lock a fresh file while a third
+/// still waits on the old one.
+#[derive(Debug)]
+pub struct FileLock {
+    path: PathBuf,
+    _file: File,
+}
+
+impl FileLock {
+    /// Try to take the lock `cache_dir()/locks/<name>.lock`
+    ///
+    /// Returns `Ok(None)` while another holder has it.
+    pub fn try_acquire(name: &str) -> Result<Option<Self>, Error> {
+        let dir = cache_dir().ok_or(Error::MissingEnv("alfred_workflow_cache"))?;
+        Self::try_acquire_at(dir.join("locks").join(format!("{}.lock", name)))
+    }
+
+    /// Try to take a lock at a specific path
+    pub fn try_acquire_at(path: impl Into<PathBuf>) -> Result<Option<Self>, Error> {
+        let path = path.into();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => return Ok(None),
+            Err(TryLockError::Error(e)) => return Err(e.into()),
+        }
+        file.set_len(0)?;
+        file.write_all(std::process::id().to_string().as_bytes())?;
+        Ok(Some(Self { path, _file: file }))
+    }
+
+    /// Path of the lock file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+fn read_pid(path: &Path) -> Option<u32> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// Whether a process with `pid` exists (`kill -0` succeeds)
+pub(crate) fn pid_is_alive(pid: u32) -> bool {
+    Command::new("kill")
+        .args(["-0", &pid.to_string()])
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixture_dir;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_background_job_lifecycle() {
+        let dir = fixture_dir("jobs-lifecycle");
+        let job = BackgroundJob::in_dir("greet", &dir);
+        assert!(!job.is_running());
+        assert_eq!(job.result(), None);
+
+        job.start("sh", ["-c", "sleep 0.3; echo hello; exit 3"])
+            .unwrap();
+        assert!(job.is_running());
+        assert_eq!(job.result(), None);
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while job.result().is_none() {
+            assert!(Instant::now() < deadline, "job did not finish");
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        assert!(!job.is_running());
+        assert_eq!(job.result().as_deref(), Some("hello\n"));
+        assert_eq!(job.exit_code(), Some(3));
+
+        job.clear();
+        assert_eq!(job.result(), None);
+        assert!(!dir.join("greet.pid").exists());
+    }
+
+    #[test]
+    fn test_file_lock_is_exclusive_until_dropped() {
+        let dir = fixture_dir("jobs-lock");
+        let path = dir.join("locks/fetch.lock");
+
+        let first = FileLock::try_acquire_at(&path)
+            .unwrap()
+            .expect("first acquisition");
+        assert_eq!(read_pid(first.path()), Some(std::process::id()));
+        assert!(FileLock::try_acquire_at(&path).unwrap().is_none());
+        drop(first);
+        assert!(FileLock::try_acquire_at(&path).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_file_lock_left_by_exited_process_is_taken_once() {
+        let dir = fixture_dir("jobs-lock-stale");
+        let path = dir.join("fetch.lock");
+        let mut exited = Command::new("true").spawn().unwrap();
+        exited.wait().unwrap();
+
+        for _ in 0..20 {
+            fs::write(&path, exited.id().to_string()).unwrap();
+            let start = std::sync::Barrier::new(4);
+            let held = std::sync::Barrier::new(4);
+            let acquired = std::thread::scope(|scope| {
+                let racers: Vec<_> = (0..4)
+                    .map(|_| {
+                        scope.spawn(|| {
+                            start.wait();
+                            let lock = FileLock::try_acquire_at(&path).unwrap();
+                            // Hold until every racer has tried
+                            held.wait();
+                            lock.is_some()
+                        })
+                    })
+                    .collect();
+                racers
+                    .into_iter()
+                    .map(|racer| racer.join().unwrap())
+                    .filter(|&won| won)
+                    .count()
+            });
+            assert_eq!(acquired, 1);
+        }
+    }
+}
@@ -13,35 +13,145 @@
 //! Output::new(items).print();
 //! ```
 
+pub mod catalog;
+pub mod frecency;
+pub mod jobs;
+mod png;
+#[cfg(feature = "schema")]
+pub mod schema;
+pub mod sessions;
+
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fmt;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 
+/// Errors returned by library operations
+#[derive(Debug)]
+pub enum Error {
+    /// Underlying filesystem or process I/O failure
+    Io(std::io::Error),
+    /// A required file or directory does not exist
+    NotFound(PathBuf),
+    /// A required environment variable is not set (e.g. running outside Alfred)
+    MissingEnv(&'static str),
+    /// An external command (e.g. `osascript`) exited unsuccessfully; holds its stderr
+    Command(String),
+    /// File contents are malformed or fail validation; holds the reason
+    Parse(String),
+    /// An external command ran longer than its timeout and was killed
+    TimedOut(std::time::Duration),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "{}", e),
+            Error::NotFound(path) => write!(f, "Not found: {:?}", path),
+            Error::MissingEnv(name) => write!(f, "{} is not set", name),
+            Error::Command(stderr) => write!(f, "Command failed: {}", stderr),
+            Error::Parse(reason) => write!(f, "Parse error: {}", reason),
+            Error::TimedOut(after) => write!(f, "Command timed out after {:?}", after),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
 /// Configuration for flow-alfred
-#[derive(Debug, Default, Deserialize)]
+#[derive(Debug, Deserialize)]
 pub struct Config {
     /// Paths to exclude from search (supports glob patterns)
     #[serde(default)]
     pub exclude: Vec<String>,
+    /// Roots scanned by the `code` command when no `--root` is given
+    #[serde(default = "default_code_roots")]
+    pub code_roots: Vec<String>,
+    /// Owner/repo roots scanned by the `repos` command when no `--root` is given
+    #[serde(default = "default_repos_roots")]
+    pub repos_roots: Vec<String>,
+    /// Repository discovery options
+    #[serde(default)]
+    pub discovery: DiscoveryOptions,
+}
+
+/// Options controlling the repository walk
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct DiscoveryOptions {
+    /// Maximum directory depth below the root to descend (unlimited if unset)
+    #[serde(default)]
+    pub max_depth: Option<usize>,
+    /// Stop the repo walk once this many repos are found (unlimited if unset);
+    /// pair with breadth-first traversal to keep the shallowest repos
+    #[serde(default)]
+    pub max_results: Option<usize>,
+    /// Order directories are walked in (`depth-first` or `breadth-first`)
+    #[serde(default)]
+    pub traversal: Traversal,
+}
+
+/// Directory walk order for repo discovery
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Traversal {
+    /// Finish each subtree before moving to the next sibling
+    #[default]
+    DepthFirst,
+    /// Visit every directory at one depth before going deeper
+    BreadthFirst,
+}
+
+fn default_code_roots() -> Vec<String> {
+    vec!["~/code".to_string()]
+}
+
+fn default_repos_roots() -> Vec<String> {
+    vec!["~/repos".to_string()]
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            exclude: Vec::new(),
+            code_roots: default_code_roots(),
+            repos_roots: default_repos_roots(),
+            discovery: DiscoveryOptions::default(),
+        }
+    }
 }
 
 impl Config {
-    /// Load config from ~/.config/flow-alfred/config.toml
+    /// Load config from ~/.config/flow-alfred/config.toml, falling back to
+    /// `data_dir()/config.toml`, then to defaults
     pub fn load() -> Self {
-        let config_path = dirs::home_dir()
-            .map(|h| h.join(".config/flow-alfred/config.toml"))
-            .unwrap_or_default();
+        let candidates = [
+            dirs::home_dir().map(|h| h.join(".config/flow-alfred/config.toml")),
+            data_dir().map(|d| d.join("config.toml")),
+        ];
+        Self::load_from(&candidates.into_iter().flatten().collect::<Vec<_>>())
+    }
 
-        if config_path.exists() {
-            if let Ok(content) = fs::read_to_string(&config_path) {
-                if let Ok(config) = toml::from_str(&content) {
-                    return config;
-                }
-            }
-        }
-        Config::default()
+    /// Load the first existing, parseable config among `paths`
+    pub fn load_from(paths: &[PathBuf]) -> Self {
+        paths
+            .iter()
+            .filter(|path| path.exists())
+            .find_map(|path| fs::read_to_string(path).ok().and_then(|c| Self::parse(&c).ok()))
+            .unwrap_or_default()
+    }
+
+    /// Parse config from TOML; missing fields take their defaults
+    pub fn parse(content: &str) -> Result<Self, String> {
+        toml::from_str(content).map_err(|e| format!("Invalid config: {}", e))
     }
 
     /// Check if a path should be excluded
@@ -77,46 +187,316 @@ impl Config {
     }
 }
 
+/// Shortest rerun interval Alfred accepts, in seconds
+pub const RERUN_MIN: f64 = 0.1;
+/// Longest rerun interval Alfred accepts, in seconds
+pub const RERUN_MAX: f64 = 10.0;
+
 /// Alfred JSON output wrapper
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Output {
+    #[serde(default)]
     pub items: Vec<Item>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub rerun: Option<f64>,
+    /// Workflow variables passed to every connected object
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub variables: BTreeMap<String, String>,
+    /// Item count above which printing logs a warning (see [`Output::warn_above`])
+    #[serde(skip)]
+    pub warn_above: Option<usize>,
 }
 
 impl Output {
     pub fn new(items: Vec<Item>) -> Self {
-        Self { items, rerun: None }
+        Self {
+            items,
+            rerun: None,
+            variables: BTreeMap::new(),
+            warn_above: None,
+        }
+    }
+
+    /// Like [`Output::new`], but fails if any item doesn't pass [`Item::validate`]
+    ///
+    /// Each problem is prefixed with the item's index and title.
+    pub fn try_new(items: Vec<Item>) -> Result<Self, Vec<String>> {
+        let problems: Vec<String> = items
+            .iter()
+            .enumerate()
+            .filter_map(|(i, item)| Some((i, item, item.validate().err()?)))
+            .flat_map(|(i, item, problems)| {
+                problems
+                    .into_iter()
+                    .map(move |problem| format!("item {} ({:?}): {}", i, item.title, problem))
+            })
+            .collect();
+        if problems.is_empty() {
+            Ok(Self::new(items))
+        } else {
+            Err(problems)
+        }
     }
 
     pub fn empty() -> Self {
-        Self {
-            items: vec![],
-            rerun: None,
+        Self::new(vec![])
+    }
+
+    /// Set a top-level workflow variable
+    pub fn variable(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.variables.insert(key.into(), value.into());
+        self
+    }
+
+    /// Append items after the existing ones
+    pub fn extend(mut self, items: impl IntoIterator<Item = Item>) -> Self {
+        self.items.extend(items);
+        self
+    }
+
+    /// Combine with another output, e.g. results from a second source
+    ///
+    /// `other`'s items follow this output's. The later value wins on
+    /// conflicts: `other`'s `rerun` replaces this one when set, and its
+    /// variables overwrite same-named ones here.
+    pub fn merge(mut self, other: Output) -> Self {
+        self.items.extend(other.items);
+        if other.rerun.is_some() {
+            self.rerun = other.rerun;
         }
+        self.variables.extend(other.variables);
+        self
     }
 
     /// Set rerun interval in seconds (Alfred will re-query)
+    ///
+    /// Alfred only accepts 0.1 to 10.0 and silently ignores anything else, so
+    /// the value is clamped into that range.
     pub fn rerun(mut self, seconds: f64) -> Self {
-        self.rerun = Some(seconds);
+        let seconds = if seconds.is_nan() { RERUN_MIN } else { seconds };
+        self.rerun = Some(seconds.clamp(RERUN_MIN, RERUN_MAX));
+        self
+    }
+
+    /// Single non-actionable "working" item that re-queries every `rerun_seconds`
+    ///
+    /// Return this while a background task runs; once it finishes, return the
+    /// real results with `rerun_until(.., true)` so polling stops.
+    pub fn progress(title: impl Into<String>, subtitle: impl Into<String>, rerun_seconds: f64) -> Self {
+        Self::new(vec![Item::new(title, subtitle).valid(false)]).rerun(rerun_seconds)
+    }
+
+    /// Poll until finished: set `rerun` only while `done` is false
+    pub fn rerun_until(self, seconds: f64, done: bool) -> Self {
+        if done {
+            self
+        } else {
+            self.rerun(seconds)
+        }
+    }
+
+    /// Keep only the first `n` items (call after sorting)
+    pub fn limit(mut self, n: usize) -> Self {
+        self.items.truncate(n);
+        self
+    }
+
+    /// Keep the `n` items best matching `query`, best first
+    ///
+    /// Combines [`fuzzy_filter_sort`] and [`Output::limit`] so the top matches
+    /// survive Alfred's result cap. Non-matching and `valid(false)` items
+    /// (errors, notices) are dropped rather than ranked.
+    pub fn top_n_by_score<F>(mut self, query: &str, key_fn: F, n: usize) -> Self
+    where
+        F: Fn(&Item) -> &str,
+    {
+        let items: Vec<Item> = std::mem::take(&mut self.items)
+            .into_iter()
+            .filter(|item| item.valid != Some(false))
+            .collect();
+        self.items = fuzzy_filter_sort(items, query, key_fn)
+            .into_iter()
+            .map(|(item, _)| item)
+            .collect();
+        self.limit(n)
+    }
+
+    /// Keep the first `n` items and, if any were dropped, append an
+    /// informational (non-actionable) item saying how many more there are
+    pub fn limit_with_notice(mut self, n: usize) -> Self {
+        let hidden = self.items.len().saturating_sub(n);
+        self.items.truncate(n);
+        self.hidden_notice(hidden)
+    }
+
+    /// Append [`Output::limit_with_notice`]'s "… N more" item for `hidden`
+    /// results the caller already dropped, e.g. before doing per-item work
+    /// (nothing is added for 0)
+    pub fn hidden_notice(mut self, hidden: usize) -> Self {
+        if hidden > 0 {
+            self.items.push(
+                Item::new(format!("… {} more", hidden), "Refine your search to see more results")
+                    .valid(false),
+            );
+        }
+        self
+    }
+
+    /// Sort items with a comparator, e.g. by score and then recency
+    ///
+    /// The sort is stable: items comparing equal keep their current order.
+    pub fn sort_items_by<F>(mut self, cmp: F) -> Self
+    where
+        F: FnMut(&Item, &Item) -> std::cmp::Ordering,
+    {
+        self.items.sort_by(cmp);
+        self
+    }
+
+    /// Sort items by a key, ascending and stable (wrap the key in
+    /// `std::cmp::Reverse` for descending)
+    pub fn sort_items_by_key<K, F>(mut self, key_fn: F) -> Self
+    where
+        F: FnMut(&Item) -> K,
+        K: Ord,
+    {
+        self.items.sort_by_key(key_fn);
+        self
+    }
+
+    /// Move the first item matching `predicate` to the top, e.g. an exact match
+    ///
+    /// Alfred selects the item at index 0 when results appear (unless its
+    /// knowledge reorders them; see `skipknowledge`). The other items keep
+    /// their relative order, and nothing changes when no item matches.
+    pub fn select_first_matching<P>(mut self, predicate: P) -> Self
+    where
+        P: Fn(&Item) -> bool,
+    {
+        if let Some(index) = self.items.iter().position(predicate) {
+            self.items[..=index].rotate_right(1);
+        }
+        self
+    }
+
+    /// Drop items whose uid was already seen, keeping the first occurrence
+    ///
+    /// Items without a uid are always kept; relative order is unchanged.
+    pub fn dedup_by_uid(mut self) -> Self {
+        let mut seen = HashSet::new();
+        self.items.retain(|item| match &item.uid {
+            Some(uid) => seen.insert(uid.clone()),
+            None => true,
+        });
+        self
+    }
+
+    /// Log a stderr warning when printing more than `max_items` items
+    ///
+    /// Off by default. Alfred slows down with tens of thousands of results
+    /// (10000 is a reasonable threshold), so this flags outputs that should
+    /// paginate or use [`Output::print_capped`]. Nothing is dropped.
+    pub fn warn_above(mut self, max_items: usize) -> Self {
+        self.warn_above = Some(max_items);
         self
     }
 
     /// Print JSON to stdout for Alfred
+    ///
+    /// While Alfred's debugger is open, first logs a warning to stderr for
+    /// each item whose `arg` or `quicklookurl` names a path that doesn't exist.
     pub fn print(&self) {
+        let _ = self.write_size_warning(&mut std::io::stderr());
+        let _ = self.write_path_warnings(&mut std::io::stderr(), debug_enabled());
         println!("{}", serde_json::to_string(self).unwrap_or_default());
     }
 
+    /// Print at most `max` items, ending with a notice item saying how many
+    /// were left out (see [`Output::limit_with_notice`])
+    pub fn print_capped(self, max: usize) {
+        let _ = self.write_capped(&mut std::io::stdout(), max);
+    }
+
+    fn write_capped(self, out: &mut impl std::io::Write, max: usize) -> std::io::Result<()> {
+        let capped = self.limit_with_notice(max);
+        let _ = capped.write_path_warnings(&mut std::io::stderr(), debug_enabled());
+        writeln!(out, "{}", capped.to_json())
+    }
+
+    fn write_size_warning(&self, out: &mut impl std::io::Write) -> std::io::Result<()> {
+        match self.warn_above {
+            Some(max) if self.items.len() > max => write_log(
+                out,
+                &format!(
+                    "warning: printing {} items (more than {}); consider paginating",
+                    self.items.len(),
+                    max
+                ),
+            ),
+            _ => Ok(()),
+        }
+    }
+
     /// Get JSON string
     pub fn to_json(&self) -> String {
         serde_json::to_string(self).unwrap_or_default()
     }
+
+    /// Print indented JSON to stdout, for reading while developing
+    ///
+    /// Warns about size and missing paths, like [`Output::print`].
+    pub fn print_pretty(&self) {
+        let _ = self.write_size_warning(&mut std::io::stderr());
+        let _ = self.write_path_warnings(&mut std::io::stderr(), debug_enabled());
+        println!("{}", self.to_json_pretty());
+    }
+
+    /// Get indented JSON string (Alfred accepts it just like [`Output::to_json`])
+    pub fn to_json_pretty(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    fn write_path_warnings(
+        &self,
+        out: &mut impl std::io::Write,
+        debug: bool,
+    ) -> std::io::Result<()> {
+        if !debug {
+            return Ok(());
+        }
+        for item in &self.items {
+            let arg = item
+                .arg
+                .as_deref()
+                .filter(|arg| arg.starts_with('/') || arg.starts_with("~/"));
+            let quicklook = item.quicklookurl.as_deref().and_then(|url| {
+                if url.starts_with('/') {
+                    Some(PathBuf::from(url))
+                } else {
+                    file_url_path(url)
+                }
+            });
+            let paths = [("arg", arg.map(expand_path)), ("quicklookurl", quicklook)];
+            for (field, path) in paths {
+                if let Some(path) = path.filter(|path| !path.exists()) {
+                    let msg = format!(
+                        "warning: \"{}\" {} is missing: {}",
+                        item.title,
+                        field,
+                        path.display()
+                    );
+                    write_log(out, &msg)?;
+                }
+            }
+        }
+        Ok(())
+    }
 }
 
 /// Alfred list item
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Item {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub uid: Option<String>,
     pub title: String,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -178,11 +558,48 @@ impl Item {
         }
     }
 
+    /// Item for a filesystem path: file name as title, parent directory as
+    /// subtitle, and the path as uid/arg/fileicon/Quick Look with `file` type
+    ///
+    /// `~` is expanded; non-UTF-8 paths are converted lossily.
+    pub fn from_path(path: &Path) -> Self {
+        let path = expand_path(&path.to_string_lossy());
+        let path_str = path.to_string_lossy().to_string();
+        let title = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| path_str.clone());
+        Self::title_only(title)
+            .subtitle_opt(path.parent().map(|parent| parent.to_string_lossy()))
+            .uid(&path_str)
+            .arg(&path_str)
+            .icon(Icon::for_path(&path))
+            .file_type()
+            .quicklook_file(&path_str)
+    }
+
     pub fn uid(mut self, uid: impl Into<String>) -> Self {
         self.uid = Some(uid.into());
         self
     }
 
+    pub fn subtitle(mut self, subtitle: impl Into<String>) -> Self {
+        self.subtitle = Some(subtitle.into());
+        self
+    }
+
+    /// Remove the subtitle so Alfred shows the title alone
+    pub fn no_subtitle(mut self) -> Self {
+        self.subtitle = None;
+        self
+    }
+
+    /// Set the subtitle when there is one, else clear it
+    pub fn subtitle_opt(mut self, subtitle: Option<impl Into<String>>) -> Self {
+        self.subtitle = subtitle.map(Into::into);
+        self
+    }
+
     pub fn arg(mut self, arg: impl Into<String>) -> Self {
         self.arg = Some(arg.into());
         self
@@ -208,6 +625,15 @@ impl Item {
         self
     }
 
+    /// Mark the item invalid if it has no `arg`, so Enter can't action an
+    /// item that would do nothing. Call last, after `arg` would have been set.
+    pub fn with_default_arg_or_invalid(mut self) -> Self {
+        if self.arg.is_none() {
+            self.valid = Some(false);
+        }
+        self
+    }
+
     pub fn autocomplete(mut self, autocomplete: impl Into<String>) -> Self {
         self.autocomplete = Some(autocomplete.into());
         self
@@ -218,22 +644,71 @@ impl Item {
         self
     }
 
+    /// Build `match` from the title plus `extra_keywords`
+    ///
+    /// Each source string is kept as-is, and any containing `-`, `_`, `/` or
+    /// `.` is also added split on those separators and joined with spaces, so
+    /// `flow-code` matches both `flow-code` and `flow code`. Duplicate forms
+    /// are dropped. Only used by Alfred when `alfred_filters_results` is on.
+    pub fn auto_match(mut self, extra_keywords: &[&str]) -> Self {
+        let mut forms: Vec<String> = Vec::new();
+        let sources = std::iter::once(self.title.as_str()).chain(extra_keywords.iter().copied());
+        for source in sources {
+            let split: Vec<&str> = source
+                .split(['-', '_', '/', '.'])
+                .filter(|part| !part.is_empty())
+                .collect();
+            for form in [source.to_string(), split.join(" ")] {
+                if !form.is_empty() && !forms.contains(&form) {
+                    forms.push(form);
+                }
+            }
+        }
+        self.match_field = Some(forms.join(" "));
+        self
+    }
+
     pub fn file_type(mut self) -> Self {
         self.item_type = Some("file".to_string());
         self
     }
 
+    /// Like [`Item::file_type`], but Alfred skips checking the path exists
+    ///
+    /// Cheaper for long lists of just-discovered paths, and keeps items for
+    /// files that don't exist yet.
+    pub fn file_type_skipcheck(mut self) -> Self {
+        self.item_type = Some("file:skipcheck".to_string());
+        self
+    }
+
+    /// Set `quicklookurl` verbatim
+    ///
+    /// Alfred previews `http(s)://` URLs as web pages and `file://` URLs or
+    /// absolute paths as files. Use [`Item::quicklook_file`] for local paths.
     pub fn quicklook(mut self, url: impl Into<String>) -> Self {
         self.quicklookurl = Some(url.into());
         self
     }
 
+    /// Set `quicklookurl` to a `file://` URL for a local path, expanding `~`
+    /// and resolving symlinks when the path exists
+    pub fn quicklook_file(mut self, path: impl AsRef<str>) -> Self {
+        let expanded = expand_path(path.as_ref());
+        let resolved = expanded.canonicalize().unwrap_or(expanded);
+        self.quicklookurl = Some(file_url(&resolved));
+        self
+    }
+
     pub fn copy_text(mut self, text: impl Into<String>) -> Self {
-        let text_val = text.into();
-        self.text = Some(Text {
-            copy: Some(text_val.clone()),
-            largetype: None,
-        });
+        if let Some(ref mut t) = self.text {
+            t.copy = Some(text.into());
+        } else {
+            self.text = Some(Text {
+                copy: Some(text.into()),
+                largetype: None,
+            });
+        }
         self
     }
 
@@ -249,31 +724,81 @@ impl Item {
         self
     }
 
-    /// Set cmd modifier action (Cmd+Return)
-    pub fn cmd_mod(mut self, arg: impl Into<String>, subtitle: impl Into<String>) -> Self {
-        let mods = self.mods.get_or_insert_with(Mods::default);
-        mods.cmd = Some(ModItem {
-            valid: Some(true),
-            arg: Some(arg.into()),
-            subtitle: Some(subtitle.into()),
-        });
+    /// Set the action for a modifier or combination, e.g. `ModKey::CMD | ModKey::ALT`
+    pub fn mod_action(
+        mut self,
+        key: ModKey,
+        arg: impl Into<String>,
+        subtitle: impl Into<String>,
+    ) -> Self {
+        self.mods.get_or_insert_with(Mods::default).keys.insert(
+            key,
+            ModItem {
+                valid: Some(true),
+                arg: Some(arg.into()),
+                subtitle: Some(subtitle.into()),
+            },
+        );
         self
     }
 
+    /// Set cmd modifier action (Cmd+Return)
+    pub fn cmd_mod(self, arg: impl Into<String>, subtitle: impl Into<String>) -> Self {
+        self.mod_action(ModKey::CMD, arg, subtitle)
+    }
+
     /// Set alt modifier action (Alt/Option+Return)
-    pub fn alt_mod(mut self, arg: impl Into<String>, subtitle: impl Into<String>) -> Self {
+    pub fn alt_mod(self, arg: impl Into<String>, subtitle: impl Into<String>) -> Self {
+        self.mod_action(ModKey::ALT, arg, subtitle)
+    }
+
+    /// Set ctrl modifier action (Ctrl+Return)
+    pub fn ctrl_mod(self, arg: impl Into<String>, subtitle: impl Into<String>) -> Self {
+        self.mod_action(ModKey::CTRL, arg, subtitle)
+    }
+
+    /// Set shift modifier action (Shift+Return)
+    pub fn shift_mod(self, arg: impl Into<String>, subtitle: impl Into<String>) -> Self {
+        self.mod_action(ModKey::SHIFT, arg, subtitle)
+    }
+
+    /// Set only the subtitle shown while a modifier is held, leaving the
+    /// modifier's arg and validity to fall back to the item's own
+    pub fn mod_subtitle(mut self, key: ModKey, subtitle: impl Into<String>) -> Self {
         let mods = self.mods.get_or_insert_with(Mods::default);
-        mods.alt = Some(ModItem {
-            valid: Some(true),
-            arg: Some(arg.into()),
-            subtitle: Some(subtitle.into()),
-        });
+        mods.keys.entry(key).or_default().subtitle = Some(subtitle.into());
         self
     }
+
+    /// Check field combinations Alfred mishandles instead of rejecting
+    ///
+    /// Reports an empty title, a `file` type without an `arg` path (Alfred
+    /// has nothing to act on), and a `quicklookurl` on a `valid(false)` item
+    /// (Quick Look isn't offered for it). Not run on construction; call it in
+    /// tests or through [`Output::try_new`].
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut problems = Vec::new();
+        if self.title.trim().is_empty() {
+            problems.push("title is empty".to_string());
+        }
+        if let Some(item_type) = self.item_type.as_deref().filter(|t| t.starts_with("file")) {
+            if self.arg.as_deref().is_none_or(|arg| arg.trim().is_empty()) {
+                problems.push(format!("type \"{}\" needs an arg path", item_type));
+            }
+        }
+        if self.valid == Some(false) && self.quicklookurl.is_some() {
+            problems.push("quicklookurl is set on an invalid item".to_string());
+        }
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems)
+        }
+    }
 }
 
 /// Icon for Alfred item
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Icon {
     #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
     pub icon_type: Option<String>,
@@ -301,23 +826,252 @@ impl Icon {
             path: uti.into(),
         }
     }
+
+    /// `filetype` icon for a common file extension (case-insensitive, without
+    /// the dot), e.g. `rs` -> `public.source-code`; None for unknown ones
+    pub fn for_extension(ext: &str) -> Option<Self> {
+        let ext = ext.to_ascii_lowercase();
+        EXTENSION_UTIS
+            .iter()
+            .find(|(known, _)| *known == ext)
+            .map(|(_, uti)| Self::filetype(*uti))
+    }
+
+    /// Icon for a file: [`Icon::for_extension`] when the extension is known,
+    /// else the file's own icon via `fileicon`
+    ///
+    /// Type icons are cached by Alfred, so they are cheaper than per-file ones.
+    /// Directories always get `fileicon`, keeping custom folder icons.
+    pub fn for_path(path: &Path) -> Self {
+        path.extension()
+            .filter(|_| !path.is_dir())
+            .and_then(|ext| Self::for_extension(&ext.to_string_lossy()))
+            .unwrap_or_else(|| Self::fileicon(path.to_string_lossy()))
+    }
+
+    /// `light` or `dark` depending on the Alfred theme (see [`theme_is_dark`]),
+    /// `light` when the theme is unknown
+    pub fn themed(light: impl Into<String>, dark: impl Into<String>) -> Self {
+        if theme_is_dark() == Some(true) {
+            Self::path(dark)
+        } else {
+            Self::path(light)
+        }
+    }
+
+    /// Solid colored dot, e.g. a status badge; `rgb` is `[r, g, b]`
+    ///
+    /// The PNG is generated once and cached as `icons/dot-<rrggbb>.png` under
+    /// `cache_dir()` (the system temp dir outside Alfred).
+    pub fn dot(rgb: [u8; 3]) -> Self {
+        let dir = cache_dir()
+            .unwrap_or_else(|| std::env::temp_dir().join("flow-alfred"))
+            .join("icons");
+        let path = dot_png_in(&dir, rgb).unwrap_or_else(|_| dir.join(dot_file_name(rgb)));
+        Self::path(path.to_string_lossy())
+    }
+}
+
+/// Diameter in pixels of [`Icon::dot`] images
+const DOT_SIZE: u32 = 32;
+
+fn dot_file_name(rgb: [u8; 3]) -> String {
+    format!("dot-{:02x}{:02x}{:02x}.png", rgb[0], rgb[1], rgb[2])
+}
+
+/// Write the dot PNG for `rgb` into `dir` unless it is already cached
+fn dot_png_in(dir: &Path, rgb: [u8; 3]) -> Result<PathBuf, Error> {
+    let path = dir.join(dot_file_name(rgb));
+    if path.exists() {
+        return Ok(path);
+    }
+
+    let center = DOT_SIZE as f64 / 2.0;
+    let radius = center - 2.0;
+    let mut pixels = Vec::with_capacity((DOT_SIZE * DOT_SIZE * 4) as usize);
+    for y in 0..DOT_SIZE {
+        for x in 0..DOT_SIZE {
+            let dx = x as f64 + 0.5 - center;
+            let dy = y as f64 + 0.5 - center;
+            // One pixel of linear falloff keeps the edge smooth
+            let coverage = (radius - (dx * dx + dy * dy).sqrt() + 0.5).clamp(0.0, 1.0);
+            pixels.extend_from_slice(&rgb);
+            pixels.push((coverage * 255.0).round() as u8);
+        }
+    }
+
+    write_atomic(&path, &png::encode_rgba(DOT_SIZE, DOT_SIZE, &pixels))?;
+    Ok(path)
 }
 
-/// Modifier key actions
-#[derive(Debug, Clone, Serialize, Default)]
+/// Uniform Type Identifiers for [`Icon::for_extension`]
+const EXTENSION_UTIS: &[(&str, &str)] = &[
+    ("md", "net.daringfireball.markdown"),
+    ("markdown", "net.daringfireball.markdown"),
+    ("txt", "public.plain-text"),
+    ("json", "public.json"),
+    ("yaml", "public.yaml"),
+    ("yml", "public.yaml"),
+    ("xml", "public.xml"),
+    ("plist", "com.apple.property-list"),
+    ("csv", "public.comma-separated-values-text"),
+    ("html", "public.html"),
+    ("htm", "public.html"),
+    ("css", "public.css"),
+    ("rs", "public.source-code"),
+    ("go", "public.source-code"),
+    ("ts", "public.source-code"),
+    ("tsx", "public.source-code"),
+    ("toml", "public.source-code"),
+    ("js", "com.netscape.javascript-source"),
+    ("swift", "public.swift-source"),
+    ("c", "public.c-source"),
+    ("h", "public.c-header"),
+    ("cpp", "public.c-plus-plus-source"),
+    ("cc", "public.c-plus-plus-source"),
+    ("m", "public.objective-c-source"),
+    ("py", "public.python-script"),
+    ("rb", "public.ruby-script"),
+    ("sh", "public.shell-script"),
+    ("bash", "public.shell-script"),
+    ("zsh", "public.shell-script"),
+    ("pdf", "com.adobe.pdf"),
+    ("png", "public.png"),
+    ("jpg", "public.jpeg"),
+    ("jpeg", "public.jpeg"),
+    ("gif", "com.compuserve.gif"),
+    ("svg", "public.svg-image"),
+    ("zip", "public.zip-archive"),
+];
+
+/// Modifier key actions, keyed by `"cmd"`, `"alt"`, `"cmd+alt"` and so on
+///
+/// A BTreeMap so the JSON key order is stable.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[serde(transparent)]
 pub struct Mods {
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub cmd: Option<ModItem>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub alt: Option<ModItem>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub ctrl: Option<ModItem>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub shift: Option<ModItem>,
+    pub keys: BTreeMap<ModKey, ModItem>,
+}
+
+impl Mods {
+    /// Action for exactly this modifier or combination
+    pub fn get(&self, key: ModKey) -> Option<&ModItem> {
+        self.keys.get(&key)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+}
+
+/// Modifier key, or a combination of them built with `|`
+///
+/// Serializes to Alfred's names, joined with `+` in the order cmd, alt,
+/// ctrl, shift, fn (e.g. `ModKey::CMD | ModKey::ALT` is `"cmd+alt"`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ModKey(u8);
+
+/// Single keys in serialization order, with their Alfred names
+const MOD_KEY_NAMES: [(ModKey, &str); 5] = [
+    (ModKey::CMD, "cmd"),
+    (ModKey::ALT, "alt"),
+    (ModKey::CTRL, "ctrl"),
+    (ModKey::SHIFT, "shift"),
+    (ModKey::FN, "fn"),
+];
+
+impl ModKey {
+    pub const CMD: ModKey = ModKey(1);
+    pub const ALT: ModKey = ModKey(1 << 1);
+    pub const CTRL: ModKey = ModKey(1 << 2);
+    pub const SHIFT: ModKey = ModKey(1 << 3);
+    pub const FN: ModKey = ModKey(1 << 4);
+
+    /// Whether every key in `other` is part of this combination
+    pub fn contains(self, other: ModKey) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// macOS modifier flags for this combination, the integer Alfred stores
+    /// for hotkeys (`cmd+shift` is 1179648)
+    pub fn event_flags(self) -> u32 {
+        MOD_KEY_EVENT_FLAGS
+            .iter()
+            .filter(|(key, _)| self.contains(*key))
+            .map(|(_, flag)| flag)
+            .sum()
+    }
+
+    /// Combination for a hotkey's `hotmod` integer, None without modifiers;
+    /// the inverse of [`ModKey::event_flags`]
+    pub fn from_event_flags(flags: u32) -> Option<ModKey> {
+        let combo = MOD_KEY_EVENT_FLAGS
+            .iter()
+            .filter(|(_, flag)| flags & flag != 0)
+            .fold(ModKey(0), |combo, (key, _)| combo | *key);
+        (combo.0 != 0).then_some(combo)
+    }
+}
+
+/// `NSEvent` modifier flag of each single key
+const MOD_KEY_EVENT_FLAGS: [(ModKey, u32); 5] = [
+    (ModKey::CMD, 1 << 20),
+    (ModKey::ALT, 1 << 19),
+    (ModKey::CTRL, 1 << 18),
+    (ModKey::SHIFT, 1 << 17),
+    (ModKey::FN, 1 << 23),
+];
+
+impl std::ops::BitOr for ModKey {
+    type Output = ModKey;
+
+    fn bitor(self, other: ModKey) -> ModKey {
+        ModKey(self.0 | other.0)
+    }
+}
+
+impl fmt::Display for ModKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<&str> = MOD_KEY_NAMES
+            .iter()
+            .filter(|(key, _)| self.contains(*key))
+            .map(|(_, name)| *name)
+            .collect();
+        f.write_str(&names.join("+"))
+    }
+}
+
+impl std::str::FromStr for ModKey {
+    type Err = String;
+
+    /// Parse `"cmd"` or a combination like `"shift+ctrl"`, in any order
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split('+').try_fold(ModKey(0), |combo, part| {
+            let part = part.trim().to_ascii_lowercase();
+            MOD_KEY_NAMES
+                .iter()
+                .find(|(_, name)| *name == part)
+                .map(|(key, _)| combo | *key)
+                .ok_or_else(|| format!("Unknown modifier key '{}' in '{}'", part, s))
+        })
+    }
+}
+
+impl Serialize for ModKey {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ModKey {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
 }
 
 /// Modifier item override
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 pub struct ModItem {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub valid: Option<bool>,
@@ -328,7 +1082,7 @@ pub struct ModItem {
 }
 
 /// Text for copy/largetype
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Text {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub copy: Option<String>,
@@ -336,6 +1090,45 @@ pub struct Text {
     pub largetype: Option<String>,
 }
 
+/// Build items from a script's line-oriented output, one item per line
+///
+/// Blank lines are skipped. Lines `parse` rejects are skipped too, and logged
+/// with [`debug`] so they show up in Alfred's debugger.
+pub fn items_from_lines<R: std::io::BufRead>(
+    reader: R,
+    parse: impl Fn(&str) -> Option<Item>,
+) -> Vec<Item> {
+    let mut items = Vec::new();
+    for (index, line) in reader.lines().map_while(Result::ok).enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match parse(&line) {
+            Some(item) => items.push(item),
+            None => debug(&format!("Skipping malformed line {}: {}", index + 1, line)),
+        }
+    }
+    items
+}
+
+/// Parse a `title[\tsubtitle[\targ]]` row, for use with [`items_from_lines`]
+///
+/// The arg defaults to the title. Rows with an empty title or more than three
+/// columns are rejected.
+pub fn parse_tsv_item(line: &str) -> Option<Item> {
+    let columns: Vec<&str> = line.split('\t').collect();
+    let (title, subtitle, arg) = match columns[..] {
+        [title] => (title, None, title),
+        [title, subtitle] => (title, Some(subtitle), title),
+        [title, subtitle, arg] => (title, Some(subtitle), arg),
+        _ => return None,
+    };
+    if title.trim().is_empty() {
+        return None;
+    }
+    Some(Item::title_only(title).arg(arg).subtitle_opt(subtitle.filter(|s| !s.is_empty())))
+}
+
 // ============================================================================
 // Workflow Management
 // ============================================================================
@@ -380,49 +1173,736 @@ pub fn workflows_dir() -> Option<PathBuf> {
     }
 }
 
+/// What [`link_workflow`] would do, as reported by [`link_workflow_dry_run`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkPlan {
+    /// Workflow directory the symlink will point at
+    pub source: PathBuf,
+    /// Symlink location inside Alfred's workflows directory
+    pub dest: PathBuf,
+    /// Whether an existing symlink at `dest` would be replaced
+    pub overwrites: bool,
+}
+
 /// Link a workflow directory into Alfred
 pub fn link_workflow(workflow_dir: &Path, bundle_id: &str) -> Result<PathBuf, String> {
     let workflows = workflows_dir().ok_or("Alfred workflows directory not found")?;
+    link_workflow_in(&workflows, workflow_dir, bundle_id)
+}
+
+/// Report what [`link_workflow`] would do without touching the filesystem
+pub fn link_workflow_dry_run(workflow_dir: &Path, bundle_id: &str) -> Result<LinkPlan, String> {
+    let workflows = workflows_dir().ok_or("Alfred workflows directory not found")?;
+    plan_link_in(&workflows, workflow_dir, bundle_id)
+}
+
+fn plan_link_in(workflows: &Path, workflow_dir: &Path, bundle_id: &str) -> Result<LinkPlan, String> {
     let dest = workflows.join(bundle_id);
+    let overwrites = dest.is_symlink();
+    if dest.exists() && !overwrites {
+        return Err(format!("Destination exists and is not a symlink: {:?}", dest));
+    }
+    Ok(LinkPlan {
+        source: workflow_dir.to_path_buf(),
+        dest,
+        overwrites,
+    })
+}
 
-    if dest.exists() {
-        // Remove existing symlink or directory
-        if dest.is_symlink() {
-            fs::remove_file(&dest).map_err(|e| format!("Failed to remove symlink: {}", e))?;
-        } else {
-            return Err(format!("Destination exists and is not a symlink: {:?}", dest));
-        }
+fn link_workflow_in(workflows: &Path, workflow_dir: &Path, bundle_id: &str) -> Result<PathBuf, String> {
+    let plan = plan_link_in(workflows, workflow_dir, bundle_id)?;
+
+    if plan.overwrites {
+        // Remove existing symlink
+        fs::remove_file(&plan.dest).map_err(|e| format!("Failed to remove symlink: {}", e))?;
     }
 
     #[cfg(unix)]
     {
-        std::os::unix::fs::symlink(workflow_dir, &dest)
+        std::os::unix::fs::symlink(&plan.source, &plan.dest)
             .map_err(|e| format!("Failed to create symlink: {}", e))?;
     }
 
-    Ok(dest)
+    Ok(plan.dest)
 }
 
-/// Unlink a workflow from Alfred
-pub fn unlink_workflow(bundle_id: &str) -> Result<(), String> {
+/// Link a workflow, first moving a real installed copy aside
+///
+/// When the destination is a directory rather than a symlink (e.g. the same
+/// workflow installed from the gallery), it is moved to
+/// `workflows/.backups/<bundle_id>-<timestamp>` before linking. Undo with
+/// [`restore_workflow_backup`].
+pub fn link_workflow_backup(workflow_dir: &Path, bundle_id: &str) -> Result<PathBuf, String> {
     let workflows = workflows_dir().ok_or("Alfred workflows directory not found")?;
-    let dest = workflows.join(bundle_id);
+    link_workflow_backup_in(&workflows, workflow_dir, bundle_id)
+}
 
-    if dest.exists() && dest.is_symlink() {
-        fs::remove_file(&dest).map_err(|e| format!("Failed to remove symlink: {}", e))?;
-    }
-    Ok(())
+/// Replace the dev symlink for `bundle_id` with its most recent backup
+pub fn restore_workflow_backup(bundle_id: &str) -> Result<PathBuf, String> {
+    let workflows = workflows_dir().ok_or("Alfred workflows directory not found")?;
+    restore_workflow_backup_in(&workflows, bundle_id)
+}
+
+fn link_workflow_backup_in(workflows: &Path, workflow_dir: &Path, bundle_id: &str) -> Result<PathBuf, String> {
+    let dest = workflows.join(bundle_id);
+    if dest.exists() && !dest.is_symlink() {
+        let backups = workflows.join(".backups");
+        fs::create_dir_all(&backups).map_err(|e| format!("Failed to create backups directory: {}", e))?;
+        let stamp = chrono::Local::now().format("%Y%m%d%H%M%S");
+        let backup = backups.join(format!("{}-{}", bundle_id, stamp));
+        if backup.exists() {
+            return Err(format!("Backup already exists: {:?}", backup));
+        }
+        fs::rename(&dest, &backup).map_err(|e| format!("Failed to back up workflow: {}", e))?;
+    }
+    link_workflow_in(workflows, workflow_dir, bundle_id)
+}
+
+fn restore_workflow_backup_in(workflows: &Path, bundle_id: &str) -> Result<PathBuf, String> {
+    let backups = workflows.join(".backups");
+    let prefix = format!("{}-", bundle_id);
+    let latest = fs::read_dir(&backups)
+        .map_err(|_| format!("No backups found for {}", bundle_id))?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|n| n.to_str())
+                .and_then(|n| n.strip_prefix(&prefix))
+                .is_some_and(|stamp| !stamp.is_empty() && stamp.chars().all(|c| c.is_ascii_digit()))
+        })
+        .max()
+        .ok_or_else(|| format!("No backups found for {}", bundle_id))?;
+
+    let dest = workflows.join(bundle_id);
+    if dest.is_symlink() {
+        fs::remove_file(&dest).map_err(|e| format!("Failed to remove symlink: {}", e))?;
+    } else if dest.exists() {
+        return Err(format!("Destination exists and is not a symlink: {:?}", dest));
+    }
+    fs::rename(&latest, &dest).map_err(|e| format!("Failed to restore workflow: {}", e))?;
+    Ok(dest)
+}
+
+/// Unlink a workflow from Alfred
+pub fn unlink_workflow(bundle_id: &str) -> Result<(), String> {
+    let workflows = workflows_dir().ok_or("Alfred workflows directory not found")?;
+    let dest = workflows.join(bundle_id);
+
+    if dest.exists() && dest.is_symlink() {
+        fs::remove_file(&dest).map_err(|e| format!("Failed to remove symlink: {}", e))?;
+    }
+    Ok(())
+}
+
+/// Uninstall a workflow by moving it to the Trash
+///
+/// A dev symlink is simply removed, like [`unlink_workflow`]. A real
+/// installed directory is only trashed when `force` is set, so a stray
+/// bundle id can't take out an installed workflow by accident.
+pub fn remove_workflow(bundle_id: &str, force: bool) -> Result<PathBuf, String> {
+    let workflows = workflows_dir().ok_or("Alfred workflows directory not found")?;
+    remove_workflow_in(&workflows, bundle_id, force)
+}
+
+fn remove_workflow_in(workflows: &Path, bundle_id: &str, force: bool) -> Result<PathBuf, String> {
+    let dest = workflows.join(bundle_id);
+    if dest.is_symlink() {
+        fs::remove_file(&dest).map_err(|e| format!("Failed to remove symlink: {}", e))?;
+    } else if !dest.exists() {
+        return Err(format!("Workflow not installed: {}", bundle_id));
+    } else if !force {
+        return Err(format!(
+            "{:?} is an installed workflow, not a dev symlink; pass force to move it to the Trash",
+            dest
+        ));
+    } else {
+        osascript(&trash_script(&dest)).map_err(|e| format!("Failed to move workflow to Trash: {}", e))?;
+    }
+    Ok(dest)
+}
+
+fn trash_script(path: &Path) -> String {
+    format!(
+        "tell application \"Finder\" to delete POSIX file {}",
+        applescript_string(&path.to_string_lossy())
+    )
+}
+
+/// An installed workflow, as found by [`list_installed_workflows`]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WorkflowInfo {
+    pub name: String,
+    /// Empty for workflows that never set one
+    pub bundle_id: String,
+    pub version: Option<String>,
+    /// Folder inside Alfred's workflows directory
+    pub path: PathBuf,
+    /// Whether the folder is a dev symlink made by [`link_workflow`]
+    pub is_symlink: bool,
+}
+
+/// Every workflow in [`workflows_dir`], sorted by name
+///
+/// Folders without a readable `info.plist` are skipped.
+pub fn list_installed_workflows() -> Vec<WorkflowInfo> {
+    workflows_dir()
+        .map(|workflows| list_installed_workflows_in(&workflows))
+        .unwrap_or_default()
+}
+
+fn list_installed_workflows_in(workflows: &Path) -> Vec<WorkflowInfo> {
+    let Ok(entries) = fs::read_dir(workflows) else {
+        return Vec::new();
+    };
+    let mut found: Vec<WorkflowInfo> = entries
+        .flatten()
+        .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+        .filter_map(|entry| {
+            let path = entry.path();
+            let plist = plist::Value::from_file(path.join("info.plist")).ok()?;
+            let plist = plist.as_dictionary()?;
+            let get = |key: &str| plist.get(key).and_then(plist::Value::as_string).map(str::to_string);
+            Some(WorkflowInfo {
+                name: get("name").unwrap_or_else(|| entry.file_name().to_string_lossy().into_owned()),
+                bundle_id: get("bundleid").unwrap_or_default(),
+                version: get("version").filter(|v| !v.is_empty()),
+                is_symlink: path.is_symlink(),
+                path,
+            })
+        })
+        .collect();
+    found.sort_by_key(|info| info.name.to_lowercase());
+    found
 }
 
 /// Reload a workflow in Alfred (refreshes canvas without restart)
 pub fn reload_workflow(bundle_id: &str) -> Result<(), String> {
-    Command::new("osascript")
-        .args(["-e", &format!("tell application \"Alfred\" to reload workflow \"{}\"", bundle_id)])
-        .output()
+    tell_alfred(&format!("reload workflow {}", applescript_string(bundle_id)))
         .map_err(|e| format!("Failed to reload workflow: {}", e))?;
     Ok(())
 }
 
+/// How long [`osascript`] waits, since it blocks while Alfred shows a dialog
+const OSASCRIPT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Run `program` with a wall-clock timeout, capturing stdout and stderr
+///
+/// The process is killed and [`Error::TimedOut`] returned if it runs longer
+/// than `timeout`. A non-zero exit is not an error; check `status`.
+pub fn run_command<I, S>(
+    program: impl AsRef<std::ffi::OsStr>,
+    args: I,
+    timeout: std::time::Duration,
+) -> Result<std::process::Output, Error>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<std::ffi::OsStr>,
+{
+    use std::io::Read;
+    use std::process::Stdio;
+
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // Drain both pipes concurrently so a chatty child can't block on a full pipe
+    fn drain(pipe: Option<impl Read + Send + 'static>) -> std::thread::JoinHandle<Vec<u8>> {
+        std::thread::spawn(move || {
+            let mut buf = Vec::new();
+            if let Some(mut pipe) = pipe {
+                let _ = pipe.read_to_end(&mut buf);
+            }
+            buf
+        })
+    }
+    let stdout = drain(child.stdout.take());
+    let stderr = drain(child.stderr.take());
+
+    let deadline = std::time::Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if std::time::Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(Error::TimedOut(timeout));
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    };
+
+    Ok(std::process::Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+/// Run an AppleScript with `osascript`, returning its trimmed stdout
+///
+/// Interpolate untrusted values into `script` with [`applescript_string`].
+/// Gives up with [`Error::TimedOut`] after 10 seconds.
+pub fn osascript(script: &str) -> Result<String, Error> {
+    let output = run_command("osascript", ["-e", script], OSASCRIPT_TIMEOUT)?;
+    if !output.status.success() {
+        return Err(Error::Command(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Send `command` to Alfred, e.g. `reload workflow "nikiv.dev.flow"`
+pub fn tell_alfred(command: &str) -> Result<String, Error> {
+    osascript(&alfred_script(command))
+}
+
+/// Fire an External Trigger in a workflow, optionally passing an argument
+///
+/// Lets a script hand off to another workflow node (see [`ExternalTrigger`]).
+pub fn run_trigger(bundle_id: &str, trigger_id: &str, argument: Option<&str>) -> Result<(), Error> {
+    tell_alfred(&trigger_command(bundle_id, trigger_id, argument))?;
+    Ok(())
+}
+
+fn trigger_command(bundle_id: &str, trigger_id: &str, argument: Option<&str>) -> String {
+    let mut command = format!(
+        "run trigger {} in workflow {}",
+        applescript_string(trigger_id),
+        applescript_string(bundle_id)
+    );
+    if let Some(argument) = argument {
+        command.push_str(&format!(" with argument {}", applescript_string(argument)));
+    }
+    command
+}
+
+fn alfred_script(command: &str) -> String {
+    format!("tell application \"Alfred\" to {}", command)
+}
+
+/// Post a macOS notification from a running script
+///
+/// Alfred has no scripting command to post its own notifications, so this
+/// uses `display notification`; inside Alfred the workflow name is added as
+/// the subtitle so the notification is attributable.
+pub fn notify(title: &str, message: &str) -> Result<(), String> {
+    let subtitle = if in_alfred() { env("workflow_name") } else { None };
+    osascript(&notification_script(title, message, subtitle.as_deref()))
+        .map_err(|e| format!("Failed to post notification: {}", e))?;
+    Ok(())
+}
+
+fn notification_script(title: &str, message: &str, subtitle: Option<&str>) -> String {
+    let mut script = format!(
+        "display notification {} with title {}",
+        applescript_string(message),
+        applescript_string(title)
+    );
+    if let Some(subtitle) = subtitle {
+        script.push_str(&format!(" subtitle {}", applescript_string(subtitle)));
+    }
+    script
+}
+
+/// Quote a value as an AppleScript string literal
+///
+/// Backslashes and double quotes are escaped, and newlines, carriage
+/// returns and tabs become `\n`, `\r` and `\t`, so the value can't end the
+/// literal or break the script across lines.
+pub fn applescript_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            _ => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Structurally compare a generated `info.plist` against an installed one
+///
+/// Objects are matched by `uid` and compared field by field (including each
+/// `config` key), connections are compared per source uid regardless of
+/// order, and any other top-level key is compared by value. Key order never
+/// produces a difference. Returns one line per difference; empty if equal.
+pub fn diff_plist(generated: &str, installed: &Path) -> Vec<String> {
+    let generated = match plist::Value::from_reader(std::io::Cursor::new(generated.as_bytes())) {
+        Ok(value) => value,
+        Err(e) => return vec![format!("Failed to parse generated plist: {}", e)],
+    };
+    let installed = match plist::Value::from_file(installed) {
+        Ok(value) => value,
+        Err(e) => return vec![format!("Failed to parse {:?}: {}", installed, e)],
+    };
+    let (Some(generated), Some(installed)) = (generated.as_dictionary(), installed.as_dictionary()) else {
+        return vec!["Top-level value is not a dictionary".to_string()];
+    };
+
+    let mut diffs = Vec::new();
+    diff_plist_objects(generated, installed, &mut diffs);
+    diff_plist_connections(generated, installed, &mut diffs);
+
+    for key in union_keys(generated, installed) {
+        if key == "objects" || key == "connections" {
+            continue;
+        }
+        match (generated.get(key), installed.get(key)) {
+            (Some(a), Some(b)) if a != b => diffs.push(format!(
+                "{} differs: {} vs {}",
+                key,
+                plist_summary(a),
+                plist_summary(b)
+            )),
+            (Some(_), None) => diffs.push(format!("{}: only in generated", key)),
+            (None, Some(_)) => diffs.push(format!("{}: only in installed", key)),
+            _ => {}
+        }
+    }
+    diffs
+}
+
+fn diff_plist_objects(generated: &plist::Dictionary, installed: &plist::Dictionary, diffs: &mut Vec<String>) {
+    let by_uid = |dict: &plist::Dictionary| -> HashMap<String, plist::Dictionary> {
+        dict.get("objects")
+            .and_then(plist::Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(plist::Value::as_dictionary)
+            .filter_map(|object| {
+                let uid = object.get("uid")?.as_string()?.to_string();
+                Some((uid, object.clone()))
+            })
+            .collect()
+    };
+    let generated = by_uid(generated);
+    let installed = by_uid(installed);
+
+    let mut uids: Vec<&String> = generated.keys().chain(installed.keys()).collect();
+    uids.sort();
+    uids.dedup();
+
+    for uid in uids {
+        let (a, b) = match (generated.get(uid), installed.get(uid)) {
+            (Some(a), Some(b)) => (a, b),
+            (Some(_), None) => {
+                diffs.push(format!("object {}: only in generated", uid));
+                continue;
+            }
+            _ => {
+                diffs.push(format!("object {}: only in installed", uid));
+                continue;
+            }
+        };
+        let label = match a.get("type").and_then(plist::Value::as_string) {
+            Some(kind) => format!("object {} ({})", uid, kind),
+            None => format!("object {}", uid),
+        };
+
+        for key in union_keys(a, b) {
+            let (value_a, value_b) = (a.get(key), b.get(key));
+            if let (Some(config_a), Some(config_b)) = (
+                value_a.and_then(plist::Value::as_dictionary),
+                value_b.and_then(plist::Value::as_dictionary),
+            ) {
+                for config_key in union_keys(config_a, config_b) {
+                    push_value_diff(
+                        diffs,
+                        &format!("{}: {}.{}", label, key, config_key),
+                        config_a.get(config_key),
+                        config_b.get(config_key),
+                    );
+                }
+            } else {
+                push_value_diff(diffs, &format!("{}: {}", label, key), value_a, value_b);
+            }
+        }
+    }
+}
+
+fn diff_plist_connections(generated: &plist::Dictionary, installed: &plist::Dictionary, diffs: &mut Vec<String>) {
+    let empty = plist::Dictionary::new();
+    let generated = generated.get("connections").and_then(plist::Value::as_dictionary).unwrap_or(&empty);
+    let installed = installed.get("connections").and_then(plist::Value::as_dictionary).unwrap_or(&empty);
+
+    for source in union_keys(generated, installed) {
+        let links = |dict: &plist::Dictionary| -> Vec<plist::Value> {
+            dict.get(source).and_then(plist::Value::as_array).cloned().unwrap_or_default()
+        };
+        let (a, b) = (links(generated), links(installed));
+        let same = a.len() == b.len() && a.iter().all(|link| b.contains(link));
+        if !same {
+            diffs.push(format!(
+                "connections from {} differ: {} vs {} links",
+                source,
+                a.len(),
+                b.len()
+            ));
+        }
+    }
+}
+
+fn push_value_diff(diffs: &mut Vec<String>, label: &str, a: Option<&plist::Value>, b: Option<&plist::Value>) {
+    match (a, b) {
+        (Some(a), Some(b)) if a != b => {
+            diffs.push(format!("{} differs: {} vs {}", label, plist_summary(a), plist_summary(b)))
+        }
+        (Some(_), None) => diffs.push(format!("{}: only in generated", label)),
+        (None, Some(_)) => diffs.push(format!("{}: only in installed", label)),
+        _ => {}
+    }
+}
+
+/// Sorted keys present in either dictionary
+fn union_keys<'a>(a: &'a plist::Dictionary, b: &'a plist::Dictionary) -> Vec<&'a String> {
+    let mut keys: Vec<&String> = a.keys().chain(b.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    keys
+}
+
+/// Short, single-line rendering of a plist value for diff output
+fn plist_summary(value: &plist::Value) -> String {
+    match value {
+        plist::Value::String(s) => format!("{:?}", s),
+        plist::Value::Integer(i) => i.to_string(),
+        plist::Value::Real(r) => r.to_string(),
+        plist::Value::Boolean(b) => b.to_string(),
+        plist::Value::Array(a) => format!("<array of {}>", a.len()),
+        plist::Value::Dictionary(d) => format!("<dict of {}>", d.len()),
+        _ => "<data>".to_string(),
+    }
+}
+
+/// How serious a [`Diagnostic`] from [`validate_workflow`] is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Alfred will reject the import or the workflow will fail at runtime
+    Error,
+    /// Likely a mistake, but may be fine (e.g. a variable set upstream)
+    Warning,
+}
+
+/// One problem found by [`validate_workflow`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn error(message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            message: message.into(),
+        }
+    }
+
+    fn warning(message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.severity {
+            Severity::Error => write!(f, "error: {}", self.message),
+            Severity::Warning => write!(f, "warning: {}", self.message),
+        }
+    }
+}
+
+/// Environment variables scripts may use without declaring them
+const AMBIENT_ENV_VARS: &[&str] = &["HOME", "PATH", "PWD", "SHELL", "TMPDIR", "USER", "LANG"];
+
+/// Check a workflow directory for problems before packing or linking
+///
+/// Parses `info.plist` and reports: a missing or unparsable plist, objects
+/// without a uid or with a duplicate one, connections to or from unknown
+/// objects, missing or non-executable `scriptfile`s, and script variables
+/// that aren't declared as workflow variables (warnings, since an upstream
+/// Arg and Vars object may set them). Empty if the workflow looks sound.
+pub fn validate_workflow(dir: &Path) -> Vec<Diagnostic> {
+    let plist_path = dir.join("info.plist");
+    if !plist_path.exists() {
+        return vec![Diagnostic::error(format!("Missing {:?}", plist_path))];
+    }
+    let root = match plist::Value::from_file(&plist_path) {
+        Ok(value) => value,
+        Err(e) => return vec![Diagnostic::error(format!("Failed to parse {:?}: {}", plist_path, e))],
+    };
+    let Some(root) = root.as_dictionary() else {
+        return vec![Diagnostic::error("Top-level value is not a dictionary")];
+    };
+
+    let mut diagnostics = Vec::new();
+    let objects: Vec<&plist::Dictionary> = root
+        .get("objects")
+        .and_then(plist::Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(plist::Value::as_dictionary)
+        .collect();
+
+    let mut uids = HashSet::new();
+    for (i, object) in objects.iter().enumerate() {
+        match object.get("uid").and_then(plist::Value::as_string) {
+            Some(uid) if !uids.insert(uid) => {
+                diagnostics.push(Diagnostic::error(format!("Duplicate object uid: {}", uid)))
+            }
+            Some(_) => {}
+            None => diagnostics.push(Diagnostic::error(format!("Object {} has no uid", i))),
+        }
+    }
+
+    let empty = plist::Dictionary::new();
+    let connections = root.get("connections").and_then(plist::Value::as_dictionary).unwrap_or(&empty);
+    for (source, links) in connections {
+        if !uids.contains(source.as_str()) {
+            diagnostics.push(Diagnostic::error(format!("Connection from unknown object {}", source)));
+        }
+        let links = links.as_array().map(Vec::as_slice).unwrap_or_default();
+        for link in links.iter().filter_map(plist::Value::as_dictionary) {
+            let destination = link.get("destinationuid").and_then(plist::Value::as_string).unwrap_or("");
+            if !uids.contains(destination) {
+                diagnostics.push(Diagnostic::error(format!(
+                    "Connection {} -> {} references unknown object {}",
+                    source, destination, destination
+                )));
+            }
+        }
+    }
+
+    let declared = declared_variables(root, &objects);
+    for object in &objects {
+        let uid = object.get("uid").and_then(plist::Value::as_string).unwrap_or("?");
+        let Some(config) = object.get("config").and_then(plist::Value::as_dictionary) else {
+            continue;
+        };
+        let get_str = |key: &str| config.get(key).and_then(plist::Value::as_string).unwrap_or("");
+
+        let mut script = get_str("script").to_string();
+        let script_file = get_str("scriptfile");
+        let is_external = config.get("type").and_then(plist::Value::as_signed_integer) == Some(8);
+        if is_external && !script_file.is_empty() {
+            let path = dir.join(script_file);
+            match fs::read_to_string(&path) {
+                Ok(text) => {
+                    if !is_executable(&path) {
+                        diagnostics.push(Diagnostic::error(format!(
+                            "object {}: script file {} is not executable",
+                            uid, script_file
+                        )));
+                    }
+                    script = text;
+                }
+                Err(_) => diagnostics.push(Diagnostic::error(format!(
+                    "object {}: script file {} not found",
+                    uid, script_file
+                ))),
+            }
+        }
+
+        let assigned = assigned_shell_variables(&script);
+        let mut reported = HashSet::new();
+        for name in referenced_shell_variables(&script) {
+            let known = declared.contains(name)
+                || assigned.contains(name)
+                || name.starts_with("alfred_")
+                || AMBIENT_ENV_VARS.contains(&name);
+            if !known && reported.insert(name) {
+                diagnostics.push(Diagnostic::warning(format!(
+                    "object {}: script uses undeclared variable {}",
+                    uid, name
+                )));
+            }
+        }
+    }
+    diagnostics
+}
+
+/// Variables declared in the workflow, its user configuration, or any
+/// object's `variables` config (Arg and Vars)
+fn declared_variables<'a>(root: &'a plist::Dictionary, objects: &[&'a plist::Dictionary]) -> HashSet<&'a str> {
+    let mut declared: HashSet<&str> = root
+        .get("variables")
+        .and_then(plist::Value::as_dictionary)
+        .into_iter()
+        .flat_map(|vars| vars.keys().map(String::as_str))
+        .collect();
+    declared.extend(
+        root.get("userconfigurationconfig")
+            .and_then(plist::Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|field| field.as_dictionary()?.get("variable")?.as_string()),
+    );
+    for object in objects {
+        let vars = object
+            .get("config")
+            .and_then(plist::Value::as_dictionary)
+            .and_then(|config| config.get("variables"))
+            .and_then(plist::Value::as_dictionary);
+        declared.extend(vars.into_iter().flat_map(|vars| vars.keys().map(String::as_str)));
+    }
+    declared
+}
+
+/// Names referenced as `$name` or `${name}` in a shell script
+fn referenced_shell_variables(script: &str) -> Vec<&str> {
+    let is_name_char = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    let mut names = Vec::new();
+    let mut rest = script;
+    while let Some(pos) = rest.find('$') {
+        rest = &rest[pos + 1..];
+        let body = rest.strip_prefix('{').unwrap_or(rest);
+        let end = body.find(|c: char| !is_name_char(c)).unwrap_or(body.len());
+        let name = &body[..end];
+        if name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+            names.push(name);
+        }
+    }
+    names
+}
+
+/// Names assigned in a shell script (`name=...`, optionally after `export`/`local`)
+fn assigned_shell_variables(script: &str) -> HashSet<&str> {
+    script
+        .split(|c: char| c.is_whitespace() || c == ';')
+        .filter_map(|word| word.split_once('=').map(|(name, _)| name))
+        .filter(|name| {
+            name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        })
+        .collect()
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).map(|m| m.permissions().mode() & 0o111 != 0).unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
 /// Pack a workflow directory into .alfredworkflow file
 pub fn pack_workflow(workflow_dir: &Path, output_path: &Path) -> Result<(), String> {
     let resolved_output = if output_path.is_absolute() {
@@ -454,6 +1934,31 @@ pub fn pack_workflow(workflow_dir: &Path, output_path: &Path) -> Result<(), Stri
     Ok(())
 }
 
+/// List the files [`pack_workflow`] would include, relative to `workflow_dir`
+///
+/// Nothing is written. Hidden files are listed too, since `zip -r` packs them.
+pub fn pack_workflow_dry_run(workflow_dir: &Path) -> Result<Vec<PathBuf>, String> {
+    if !workflow_dir.is_dir() {
+        return Err(format!("Workflow directory not found: {:?}", workflow_dir));
+    }
+
+    let mut files = Vec::new();
+    let mut stack = vec![workflow_dir.to_path_buf()];
+    while let Some(dir) = stack.pop() {
+        let entries = fs::read_dir(&dir).map_err(|e| format!("Failed to read {:?}: {}", dir, e))?;
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                stack.push(path);
+            } else if let Ok(relative) = path.strip_prefix(workflow_dir) {
+                files.push(relative.to_path_buf());
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
 /// Open a .alfredworkflow file to install it
 pub fn install_workflow(workflow_path: &Path) -> Result<(), String> {
     Command::new("open")
@@ -463,17 +1968,152 @@ pub fn install_workflow(workflow_path: &Path) -> Result<(), String> {
     Ok(())
 }
 
+/// Side length in pixels of the placeholder `icon.png` from [`scaffold_workflow`]
+const SCAFFOLD_ICON_SIZE: u32 = 128;
+
+/// Run Script target of a scaffolded workflow
+const SCAFFOLD_SCRIPT: &str = r#"#!/bin/bash
+# Runs when a result is actioned; the item's arg is $1
+echo "$1"
+"#;
+
+/// Script Filter of a scaffolded workflow; Alfred filters the fixed items
+const SCAFFOLD_FILTER_SCRIPT: &str = r#"#!/bin/bash
+# Prints Script Filter JSON; the query is $1
+cat <<'JSON'
+{"items": [
+  {"uid": "hello", "title": "Hello", "subtitle": "Edit filter.sh to change these results", "arg": "hello"},
+  {"uid": "world", "title": "World", "arg": "world"}
+]}
+JSON
+"#;
+
+/// Create a new workflow directory ready to [`link_workflow`]
+///
+/// Writes a minimal `info.plist` (built with [`WorkflowBuilder`]), a
+/// placeholder `icon.png` and an executable `script.sh`. Fails if `dir`
+/// already has an `info.plist`. Returns the files created.
+pub fn scaffold_workflow(dir: &Path, bundle_id: &str, name: &str) -> Result<Vec<PathBuf>, String> {
+    scaffold_workflow_with(dir, bundle_id, name, false)
+}
+
+/// [`scaffold_workflow`], optionally adding a Script Filter (`filter.sh`,
+/// keyword derived from `name`) connected to a Run Script calling `script.sh`
+pub fn scaffold_workflow_with(
+    dir: &Path,
+    bundle_id: &str,
+    name: &str,
+    with_script_filter: bool,
+) -> Result<Vec<PathBuf>, String> {
+    let plist_path = dir.join("info.plist");
+    if plist_path.exists() {
+        return Err(format!("Workflow already exists: {:?}", plist_path));
+    }
+
+    let mut builder = WorkflowBuilder::new(bundle_id, name).version("0.1.0");
+    let mut scripts = vec![("script.sh", SCAFFOLD_SCRIPT)];
+    if with_script_filter {
+        let keyword: String = name
+            .chars()
+            .filter(char::is_ascii_alphanumeric)
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+        let keyword = if keyword.is_empty() { "workflow".to_string() } else { keyword };
+        builder = builder
+            .object(
+                ScriptFilter::new("FILTER", &keyword)
+                    .title(name)
+                    .with_space(true)
+                    .alfred_filters_results(true)
+                    .script_file("filter.sh"),
+            )
+            .object(RunScript::new("SCRIPT", "./script.sh \"$1\""))
+            .connect("FILTER", "SCRIPT");
+        scripts.push(("filter.sh", SCAFFOLD_FILTER_SCRIPT));
+    }
+    let plist = builder.build()?;
+
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create {:?}: {}", dir, e))?;
+    let write = |file: &str, contents: &[u8]| -> Result<PathBuf, String> {
+        let path = dir.join(file);
+        fs::write(&path, contents).map_err(|e| format!("Failed to write {:?}: {}", path, e))?;
+        Ok(path)
+    };
+
+    let mut created = vec![write("info.plist", plist.as_bytes())?];
+    let pixels = [0x5a, 0x6b, 0x7d, 0xff].repeat((SCAFFOLD_ICON_SIZE * SCAFFOLD_ICON_SIZE) as usize);
+    let icon = png::encode_rgba(SCAFFOLD_ICON_SIZE, SCAFFOLD_ICON_SIZE, &pixels);
+    created.push(write("icon.png", &icon)?);
+    for (file, contents) in scripts {
+        let path = write(file, contents.as_bytes())?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o755))
+                .map_err(|e| format!("Failed to make {:?} executable: {}", path, e))?;
+        }
+        created.push(path);
+    }
+    Ok(created)
+}
+
 // ============================================================================
 // Fuzzy Matching
 // ============================================================================
 
-/// Check if query matches target fuzzily
+/// How the fuzzy functions compare letter case
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MatchOptions {
+    /// Always match case exactly (`"API"` doesn't match `api`)
+    pub case_sensitive: bool,
+    /// Match case exactly only when the query has an uppercase letter, like
+    /// vim's `smartcase`: `"api"` matches `API`, `"API"` doesn't match `api`
+    pub smart_case: bool,
+}
+
+impl MatchOptions {
+    /// Case is ignored, as in [`fuzzy_match`] and [`fuzzy_score`]
+    pub const IGNORE_CASE: Self = Self {
+        case_sensitive: false,
+        smart_case: false,
+    };
+
+    /// Whether `query` is compared case-sensitively under these options
+    pub fn is_case_sensitive(&self, query: &str) -> bool {
+        self.case_sensitive || (self.smart_case && query.chars().any(char::is_uppercase))
+    }
+
+    /// `query` and `target`, lowercased unless matching case-sensitively
+    fn fold<'a>(&self, query: &'a str, target: &'a str) -> (Cow<'a, str>, Cow<'a, str>) {
+        if self.is_case_sensitive(query) {
+            (Cow::Borrowed(query), Cow::Borrowed(target))
+        } else {
+            (Cow::Owned(query.to_lowercase()), Cow::Owned(target.to_lowercase()))
+        }
+    }
+}
+
+/// Smart-case: case-sensitive only for queries with an uppercase letter
+impl Default for MatchOptions {
+    fn default() -> Self {
+        Self {
+            case_sensitive: false,
+            smart_case: true,
+        }
+    }
+}
+
+/// Check if query matches target fuzzily, ignoring case
 pub fn fuzzy_match(query: &str, target: &str) -> bool {
+    fuzzy_match_opts(query, target, MatchOptions::IGNORE_CASE)
+}
+
+/// [`fuzzy_match`] with the case handling chosen by `opts`
+pub fn fuzzy_match_opts(query: &str, target: &str, opts: MatchOptions) -> bool {
     if query.is_empty() {
         return true;
     }
-    let query = query.to_lowercase();
-    let target = target.to_lowercase();
+    let (query, target) = opts.fold(query, target);
 
     let mut query_chars = query.chars().peekable();
     for c in target.chars() {
@@ -487,13 +2127,17 @@ pub fn fuzzy_match(query: &str, target: &str) -> bool {
     query_chars.peek().is_none()
 }
 
-/// Score a fuzzy match (higher is better)
+/// Score a fuzzy match (higher is better), ignoring case
 pub fn fuzzy_score(query: &str, target: &str) -> i32 {
+    fuzzy_score_opts(query, target, MatchOptions::IGNORE_CASE)
+}
+
+/// [`fuzzy_score`] with the case handling chosen by `opts` (-1 if no match)
+pub fn fuzzy_score_opts(query: &str, target: &str, opts: MatchOptions) -> i32 {
     if query.is_empty() {
         return 0;
     }
-    let query = query.to_lowercase();
-    let target = target.to_lowercase();
+    let (query, target) = opts.fold(query, target);
 
     let mut score = 0;
     let mut query_chars = query.chars().peekable();
@@ -537,67 +2181,308 @@ pub fn fuzzy_score(query: &str, target: &str) -> i32 {
         return -1; // Didn't match all chars
     }
 
+    // Bonus for abbreviating the target by its word initials ("fc" → flow-code)
+    if is_initialism(&query, &target) {
+        score += ACRONYM_BONUS * query.chars().count() as i32;
+    }
+
     score
 }
 
-/// Sort items by fuzzy score
-pub fn fuzzy_sort<T, F>(items: &mut [T], query: &str, get_str: F)
-where
-    F: Fn(&T) -> &str,
-{
-    items.sort_by(|a, b| {
-        let score_a = fuzzy_score(query, get_str(a));
-        let score_b = fuzzy_score(query, get_str(b));
-        score_b.cmp(&score_a)
+/// Per-character bonus when every query char is a word initial
+const ACRONYM_BONUS: i32 = 20;
+
+/// Whether `query` matches, in order, only the first letters of `target`'s
+/// words (split on the separators [`fuzzy_score`] rewards); both case-folded alike
+fn is_initialism(query: &str, target: &str) -> bool {
+    let mut prev = None;
+    let mut initials = target.chars().filter(|&c| {
+        let is_initial = !is_word_separator(c) && prev.is_none_or(is_word_separator);
+        prev = Some(c);
+        is_initial
     });
+    query.chars().all(|q| initials.any(|c| c == q))
 }
 
-// ============================================================================
-// Code/Project Discovery
-// ============================================================================
-
-/// Entry representing a discovered code repository
-pub struct CodeEntry {
-    /// Display name (relative path from root)
-    pub display: String,
-    /// Full path to the repository
-    pub path: PathBuf,
+fn is_word_separator(c: char) -> bool {
+    matches!(c, '/' | '-' | '_' | ' ')
 }
 
-/// Discover git repositories under a root directory
-pub fn discover_repos(root: &Path) -> Vec<CodeEntry> {
-    discover_repos_with_config(root, &Config::load())
+/// [`fuzzy_score`] scaled to 0.0–1.0, comparable across queries and targets
+///
+/// The raw score is divided by the best score a query of `n` characters can
+/// get, a contiguous match at the start of the target:
+/// `20 + 5n + 10 * n(n - 1) / 2` (start bonus, per-char points, and the
+/// growing consecutive-run bonus). Separator and acronym bonuses can push
+/// some matches past that, so the result is clamped to 1.0. None if `query` doesn't match;
+/// an empty query matches everything with 1.0.
+pub fn fuzzy_score_normalized(query: &str, target: &str) -> Option<f32> {
+    let n = query.chars().count() as i32;
+    if n == 0 {
+        return Some(1.0);
+    }
+    let score = fuzzy_score(query, target);
+    if score < 0 {
+        return None;
+    }
+    let max = 20 + 5 * n + 10 * n * (n - 1) / 2;
+    Some((score as f32 / max as f32).min(1.0))
 }
 
-/// Discover git repositories with custom config
-pub fn discover_repos_with_config(root: &Path, config: &Config) -> Vec<CodeEntry> {
-    let mut repos = Vec::new();
-    let mut seen = HashSet::new();
-    let mut stack = vec![root.to_path_buf()];
-
-    while let Some(dir) = stack.pop() {
-        // Check if this directory should be excluded
-        if config.should_exclude(&dir) {
-            continue;
+/// Character indices in `target` matched by [`fuzzy_match`]'s greedy walk
+///
+/// Returns None when `query` doesn't match; an empty query matches nothing.
+pub fn fuzzy_match_indices(query: &str, target: &str) -> Option<Vec<usize>> {
+    let mut query_chars = query.chars().peekable();
+    let mut indices = Vec::new();
+    for (i, c) in target.chars().enumerate() {
+        match query_chars.peek() {
+            Some(q) if c.to_lowercase().eq(q.to_lowercase()) => {
+                query_chars.next();
+                indices.push(i);
+            }
+            Some(_) => {}
+            None => break,
         }
+    }
+    query_chars.peek().is_none().then_some(indices)
+}
 
-        let entries = match fs::read_dir(&dir) {
-            Ok(entries) => entries,
+/// Wrap each run of matched characters in `[` `]`, e.g. `[fl]ow-[c]ode`
+///
+/// Alfred can't style part of a title, so this is meant for subtitles.
+pub fn highlight_matches(target: &str, indices: &[usize]) -> String {
+    let mut out = String::new();
+    let mut in_run = false;
+    for (i, c) in target.chars().enumerate() {
+        let matched = indices.contains(&i);
+        if matched && !in_run {
+            out.push('[');
+        } else if !matched && in_run {
+            out.push(']');
+        }
+        in_run = matched;
+        out.push(c);
+    }
+    if in_run {
+        out.push(']');
+    }
+    out
+}
+
+/// `target` up to the separator (`/`, `-`, `_`, `.`, space) following its
+/// last matched character, for a Tab `autocomplete` that extends the query
+/// to the end of the segment being typed
+pub fn complete_to_boundary(target: &str, indices: &[usize]) -> String {
+    let Some(&last) = indices.last() else {
+        return target.to_string();
+    };
+    target
+        .char_indices()
+        .skip(last + 1)
+        .find(|(_, c)| matches!(c, '/' | '-' | '_' | '.' | ' '))
+        .map(|(byte, _)| target[..byte].to_string())
+        .unwrap_or_else(|| target.to_string())
+}
+
+/// Check that every whitespace-separated query token fuzzy-matches the target
+///
+/// Unlike [`fuzzy_match`], `"flow code"` matches `flow-code` because the
+/// space is a token separator instead of a literal character to match.
+pub fn fuzzy_match_tokens(query: &str, target: &str) -> bool {
+    query.split_whitespace().all(|token| fuzzy_match(token, target))
+}
+
+/// Score a multi-token query: the sum of each token's [`fuzzy_score`]
+///
+/// Returns -1 if any token fails to match. When the tokens' matches start in
+/// the target in the same order as in the query, a bonus is added, so
+/// `"flow code"` ranks `flow-code` above `code-flow`. A token matches where it
+/// occurs whole if it does (`cat` in `abc-cat` starts at the second `c`), else
+/// where its [`fuzzy_match_indices`] begin.
+pub fn fuzzy_score_tokens(query: &str, target: &str) -> i32 {
+    let target_lower = target.to_lowercase();
+    let mut total = 0;
+    let mut last_start: Option<usize> = None;
+    let mut in_order = true;
+
+    for token in query.split_whitespace() {
+        let score = fuzzy_score(token, target);
+        if score < 0 {
+            return -1;
+        }
+        total += score;
+
+        let start = token_match_start(&token.to_lowercase(), &target_lower);
+        if let (Some(prev), Some(start)) = (last_start, start) {
+            if start < prev {
+                in_order = false;
+            }
+        }
+        last_start = start.or(last_start);
+    }
+
+    if in_order && query.split_whitespace().count() > 1 {
+        total += 25;
+    }
+    total
+}
+
+/// Character index where `token` matches in `target` (both lowercased)
+fn token_match_start(token: &str, target: &str) -> Option<usize> {
+    match target.find(token) {
+        Some(byte) => Some(target[..byte].chars().count()),
+        None => fuzzy_match_indices(token, target)?.first().copied(),
+    }
+}
+
+/// Score `query` against several weighted fields, e.g. a repo's name and
+/// its description
+///
+/// Each field's [`fuzzy_score`] is multiplied by its weight and the best
+/// (maximum) weighted score wins, rounded to the nearest integer; fields
+/// are not summed, so a long description can't outrank a short name just by
+/// matching too. Returns -1 if no field matches, and 0 for an empty query.
+pub fn fuzzy_score_fields(query: &str, fields: &[(&str, f32)]) -> i32 {
+    if query.is_empty() {
+        return 0;
+    }
+    fields
+        .iter()
+        .filter_map(|&(target, weight)| {
+            let score = fuzzy_score(query, target);
+            (score >= 0).then(|| (score as f32 * weight).round() as i32)
+        })
+        .max()
+        .unwrap_or(-1)
+}
+
+/// Sort items by fuzzy score
+///
+/// Equal scores are broken by shorter target first, then lexicographically,
+/// so the same query yields the same order whatever the input order was.
+pub fn fuzzy_sort<T, F>(items: &mut [T], query: &str, get_str: F)
+where
+    F: Fn(&T) -> &str,
+{
+    items.sort_by(|a, b| {
+        let (target_a, target_b) = (get_str(a), get_str(b));
+        let score_a = fuzzy_score(query, target_a);
+        let score_b = fuzzy_score(query, target_b);
+        score_b
+            .cmp(&score_a)
+            .then_with(|| target_a.len().cmp(&target_b.len()))
+            .then_with(|| target_a.cmp(target_b))
+    });
+}
+
+/// Keep only items matching `query`, paired with their score, best first
+///
+/// Scores each item once with [`fuzzy_score`], drops those below 0 and sorts
+/// the rest descending. Ties intentionally keep their input order, unlike
+/// [`fuzzy_sort`]'s, so a prior ordering such as [`sort_repos`] survives.
+/// An empty query keeps every item with score 0.
+pub fn fuzzy_filter_sort<T, F>(items: Vec<T>, query: &str, get_str: F) -> Vec<(T, i32)>
+where
+    F: Fn(&T) -> &str,
+{
+    let mut scored: Vec<(T, i32)> = items
+        .into_iter()
+        .filter_map(|item| {
+            let score = fuzzy_score(query, get_str(&item));
+            (score >= 0).then_some((item, score))
+        })
+        .collect();
+    scored.sort_by_key(|&(_, score)| std::cmp::Reverse(score));
+    scored
+}
+
+// ============================================================================
+// Code/Project Discovery
+// ============================================================================
+
+/// Entry representing a discovered code repository
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CodeEntry {
+    /// Display name (relative path from root)
+    pub display: String,
+    /// Full path to the repository
+    pub path: PathBuf,
+    /// Current branch, or short sha when HEAD is detached
+    #[serde(default)]
+    pub branch: Option<String>,
+    /// Whether the working tree has uncommitted changes
+    #[serde(default)]
+    pub is_dirty: Option<bool>,
+    /// One-line summary from the package manifest or README
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+/// Discover git repositories under a root directory
+pub fn discover_repos(root: &Path) -> Vec<CodeEntry> {
+    discover_repos_with_config(root, &Config::load())
+}
+
+/// Discover git repositories with custom config
+///
+/// Directory symlinks are followed, but each real directory is walked once,
+/// so symlink cycles terminate and a linked repo is only listed once.
+pub fn discover_repos_with_config(root: &Path, config: &Config) -> Vec<CodeEntry> {
+    discover_repos_skipping(root, config, &extra_skip_dirs())
+}
+
+/// [`discover_repos_with_config`] with the extra directory names to skip
+fn discover_repos_skipping(
+    root: &Path,
+    config: &Config,
+    extra_skips: &HashSet<String>,
+) -> Vec<CodeEntry> {
+    let mut repos = Vec::new();
+    let mut seen = HashSet::new();
+    let mut visited = HashSet::new();
+    let mut queue = VecDeque::from([(root.to_path_buf(), 0)]);
+    let max_depth = config.discovery.max_depth.unwrap_or(usize::MAX);
+    let max_results = config.discovery.max_results.unwrap_or(usize::MAX);
+    let breadth_first = config.discovery.traversal == Traversal::BreadthFirst;
+
+    'walk: loop {
+        let next = if breadth_first { queue.pop_front() } else { queue.pop_back() };
+        let Some((dir, depth)) = next else {
+            break;
+        };
+        // Check if this directory should be excluded
+        if config.should_exclude(&dir) {
+            continue;
+        }
+        if depth >= max_depth {
+            continue;
+        }
+        if !visited.insert(fs::canonicalize(&dir).unwrap_or_else(|_| dir.clone())) {
+            continue;
+        }
+
+        let mut entries: Vec<fs::DirEntry> = match fs::read_dir(&dir) {
+            Ok(entries) => entries.flatten().collect(),
             Err(_) => continue,
         };
+        // Sorted so the walk, and so which repos a `max_results` cap keeps, is stable
+        entries.sort_by_key(|entry| entry.file_name());
+        let mut children = Vec::new();
 
-        for entry in entries.flatten() {
+        for entry in entries {
             let path = entry.path();
             let file_type = match entry.file_type() {
                 Ok(ft) => ft,
                 Err(_) => continue,
             };
-            if !file_type.is_dir() {
+            let is_dir = if file_type.is_symlink() { path.is_dir() } else { file_type.is_dir() };
+            if !is_dir {
                 continue;
             }
 
             let name = entry.file_name().to_string_lossy().to_string();
-            if should_skip_dir(&name) {
+            if should_skip_dir(&name) || extra_skips.contains(&name) {
                 continue;
             }
 
@@ -613,14 +2498,30 @@ pub fn discover_repos_with_config(root: &Path, config: &Config) -> Vec<CodeEntry
                     .unwrap_or(&path)
                     .to_string_lossy()
                     .to_string();
-                let key = path.to_string_lossy().to_string();
+                let key = fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
                 if seen.insert(key) {
-                    repos.push(CodeEntry { display, path: path.clone() });
+                    repos.push(CodeEntry {
+                        display,
+                        path: path.clone(),
+                        branch: None,
+                        is_dirty: None,
+                        description: None,
+                    });
+                    if repos.len() >= max_results {
+                        break 'walk;
+                    }
                 }
                 // Continue searching for nested repos
             }
 
-            stack.push(path);
+            children.push((path, depth + 1));
+        }
+
+        // Depth-first pops from the back, so push in reverse to visit in name order
+        if breadth_first {
+            queue.extend(children);
+        } else {
+            queue.extend(children.into_iter().rev());
         }
     }
 
@@ -696,6 +2597,9 @@ pub fn discover_repos_structured_with_config(root: &Path, config: &Config) -> Ve
                 repos.push(CodeEntry {
                     display: format!("{}/{}", owner_name, repo_name),
                     path: repo_path,
+                    branch: None,
+                    is_dirty: None,
+                    description: None,
                 });
             }
         }
@@ -705,6 +2609,562 @@ pub fn discover_repos_structured_with_config(root: &Path, config: &Config) -> Ve
     repos
 }
 
+/// Discover git repositories under several roots as one list
+pub fn discover_repos_multi(roots: &[PathBuf]) -> Vec<CodeEntry> {
+    discover_repos_multi_with(roots, discover_repos)
+}
+
+/// Merge per-root discovery results from `discover`
+///
+/// Repos reachable from more than one root (overlapping or symlinked roots)
+/// appear once, keyed by canonical path. When two distinct repos share a
+/// display name, both are prefixed with their root's directory name, e.g.
+/// `code/alfred` and `work/alfred`.
+pub fn discover_repos_multi_with<F>(roots: &[PathBuf], discover: F) -> Vec<CodeEntry>
+where
+    F: Fn(&Path) -> Vec<CodeEntry>,
+{
+    let mut seen = HashSet::new();
+    let mut merged: Vec<(&Path, CodeEntry)> = Vec::new();
+
+    for root in roots {
+        for entry in discover(root) {
+            let canonical = fs::canonicalize(&entry.path).unwrap_or_else(|_| entry.path.clone());
+            if seen.insert(canonical) {
+                merged.push((root.as_path(), entry));
+            }
+        }
+    }
+
+    let mut display_counts: HashMap<String, usize> = HashMap::new();
+    for (_, entry) in &merged {
+        *display_counts.entry(entry.display.clone()).or_default() += 1;
+    }
+
+    let mut repos: Vec<CodeEntry> = merged
+        .into_iter()
+        .map(|(root, mut entry)| {
+            if display_counts[&entry.display] > 1 {
+                let root_name = root
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_else(|| root.to_string_lossy().to_string());
+                entry.display = format!("{}/{}", root_name, entry.display);
+            }
+            entry
+        })
+        .collect();
+
+    repos.sort_by(|a, b| a.display.cmp(&b.display));
+    repos
+}
+
+/// Ordering for discovered repositories
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortOrder {
+    /// By display name
+    #[default]
+    Alphabetical,
+    /// Most recently modified repo directory first
+    RecentlyModified,
+    /// Most recent commit (from the HEAD reflog) first
+    RecentlyCommitted,
+}
+
+impl std::str::FromStr for SortOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "alphabetical" | "name" => Ok(SortOrder::Alphabetical),
+            "modified" | "recently-modified" => Ok(SortOrder::RecentlyModified),
+            "committed" | "recently-committed" => Ok(SortOrder::RecentlyCommitted),
+            other => Err(format!(
+                "Unknown sort order '{}' (expected alphabetical, modified or committed)",
+                other
+            )),
+        }
+    }
+}
+
+/// Discover git repositories under a root directory in the given order
+pub fn discover_repos_sorted(root: &Path, order: SortOrder) -> Vec<CodeEntry> {
+    let mut repos = discover_repos(root);
+    sort_repos(&mut repos, order);
+    repos
+}
+
+/// Sort repositories in place
+///
+/// The sort is stable, so a later [`fuzzy_filter_sort`] keeps this order
+/// among repos with equal fuzzy scores. [`fuzzy_sort`] does not: it breaks
+/// ties by length and then name, overriding the chosen `SortOrder`. Repos
+/// without a readable time sort last.
+pub fn sort_repos(repos: &mut [CodeEntry], order: SortOrder) {
+    match order {
+        SortOrder::Alphabetical => repos.sort_by(|a, b| a.display.cmp(&b.display)),
+        SortOrder::RecentlyModified => repos.sort_by_cached_key(|r| {
+            std::cmp::Reverse(fs::metadata(&r.path).and_then(|m| m.modified()).ok())
+        }),
+        SortOrder::RecentlyCommitted => {
+            repos.sort_by_cached_key(|r| std::cmp::Reverse(git_last_commit_time(&r.path)))
+        }
+    }
+}
+
+/// Unix time of the latest commit on HEAD, read from `.git/logs/HEAD`
+///
+/// Each reflog line ends with `<name> <email> <unix> <tz>\t<message>`, so
+/// this avoids spawning `git log`. Only entries whose message starts with
+/// `commit` count (`commit:`, `commit (amend):`, `commit (merge):`, ...);
+/// checkouts, resets and pulls are skipped.
+pub fn git_last_commit_time(repo: &Path) -> Option<i64> {
+    let reflog = fs::read_to_string(git_dir(repo)?.join("logs").join("HEAD")).ok()?;
+    let (meta, _message) = reflog
+        .lines()
+        .rev()
+        .filter_map(|l| l.split_once('\t'))
+        .find(|(_, message)| message.starts_with("commit"))?;
+    let mut fields = meta.rsplit(' ');
+    let _tz = fields.next()?;
+    fields.next()?.parse().ok()
+}
+
+/// Discover git repositories and populate their branch (and optionally dirty state)
+pub fn discover_repos_with_status(root: &Path, check_dirty: bool) -> Vec<CodeEntry> {
+    let mut repos = discover_repos(root);
+    fill_git_status(&mut repos, check_dirty);
+    repos
+}
+
+/// Populate `branch` from `.git/HEAD`, and `is_dirty` via `git status` when
+/// `check_dirty` is set
+///
+/// Reading HEAD is a single small file read per repo. The dirty check spawns
+/// one `git` process per repo, so keep it off for large roots.
+pub fn fill_git_status(repos: &mut [CodeEntry], check_dirty: bool) {
+    for repo in repos {
+        repo.branch = git_branch(&repo.path);
+        if check_dirty {
+            repo.is_dirty = git_is_dirty(&repo.path);
+        }
+    }
+}
+
+/// Populate `description` for each repo (see [`repo_description`])
+pub fn fill_descriptions(repos: &mut [CodeEntry]) {
+    for repo in repos {
+        repo.description = repo_description(&repo.path);
+    }
+}
+
+/// Bytes read from each file [`repo_description`] looks at
+const DESCRIPTION_READ_LIMIT: u64 = 4096;
+
+/// One-line description of a repo
+///
+/// Prefers the `description` of `Cargo.toml` or `package.json`, which are
+/// written to be one line, then falls back to the first paragraph line of the
+/// README that isn't a heading, badge or HTML. Only the first few KB of each
+/// file are read.
+pub fn repo_description(repo: &Path) -> Option<String> {
+    let manifest = |name: &str, parse: fn(&str) -> Option<String>| {
+        read_head(&repo.join(name)).and_then(|head| parse(&head))
+    };
+    manifest("Cargo.toml", cargo_description)
+        .or_else(|| manifest("package.json", package_json_description))
+        .or_else(|| {
+            ["README.md", "readme.md", "Readme.md", "README"]
+                .iter()
+                .find_map(|name| read_head(&repo.join(name)))
+                .and_then(|head| readme_description(&head))
+        })
+}
+
+fn read_head(path: &Path) -> Option<String> {
+    use std::io::Read;
+
+    let mut head = Vec::new();
+    fs::File::open(path)
+        .ok()?
+        .take(DESCRIPTION_READ_LIMIT)
+        .read_to_end(&mut head)
+        .ok()?;
+    Some(String::from_utf8_lossy(&head).into_owned())
+}
+
+/// `description = "..."` in the `[package]` table, parsed line by line so a
+/// truncated file still works
+fn cargo_description(head: &str) -> Option<String> {
+    let mut in_package = false;
+    for line in head.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_package = line == "[package]";
+        } else if in_package && line.starts_with("description") {
+            let table: toml::Table = line.parse().ok()?;
+            return non_empty(table.get("description")?.as_str()?);
+        }
+    }
+    None
+}
+
+/// Top-level `"description": "..."` line of a package.json
+fn package_json_description(head: &str) -> Option<String> {
+    head.lines()
+        .map(|line| line.trim().trim_end_matches(','))
+        .filter(|line| line.starts_with("\"description\""))
+        .find_map(|line| {
+            let object: serde_json::Value = serde_json::from_str(&format!("{{{}}}", line)).ok()?;
+            non_empty(object.get("description")?.as_str()?)
+        })
+}
+
+fn readme_description(head: &str) -> Option<String> {
+    let mut in_code_block = false;
+    for line in head.lines().map(str::trim) {
+        if line.starts_with("```") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+        let skipped = line.is_empty()
+            || line.starts_with('#')
+            || line.starts_with("[!")
+            || line.starts_with("![")
+            || line.starts_with('<')
+            || line.chars().all(|c| c == '=' || c == '-');
+        if !in_code_block && !skipped {
+            return non_empty(line);
+        }
+    }
+    None
+}
+
+fn non_empty(s: &str) -> Option<String> {
+    let s = s.trim();
+    (!s.is_empty()).then(|| s.to_string())
+}
+
+/// Resolve a repo's git directory, following `gitdir:` files used by
+/// worktrees and submodules
+pub fn git_dir(repo: &Path) -> Option<PathBuf> {
+    let dot_git = repo.join(".git");
+    if dot_git.is_dir() {
+        return Some(dot_git);
+    }
+    let content = fs::read_to_string(&dot_git).ok()?;
+    let target = content.trim().strip_prefix("gitdir:")?.trim();
+    Some(repo.join(target))
+}
+
+/// Read the current branch from `.git/HEAD` without spawning git
+///
+/// Returns the branch name for a symbolic ref, or the 7-char short sha when
+/// HEAD is detached.
+pub fn git_branch(repo: &Path) -> Option<String> {
+    let head = fs::read_to_string(git_dir(repo)?.join("HEAD")).ok()?;
+    let head = head.trim();
+    if let Some(reference) = head.strip_prefix("ref:") {
+        let reference = reference.trim();
+        return Some(
+            reference
+                .strip_prefix("refs/heads/")
+                .unwrap_or(reference)
+                .to_string(),
+        );
+    }
+    if head.len() >= 7 && head.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Some(head[..7].to_string());
+    }
+    None
+}
+
+/// Browsable web URL for a repo's `origin` remote, read from `.git/config`
+///
+/// SSH (`git@host:owner/repo.git`, `ssh://git@host/owner/repo`) and HTTPS
+/// forms are normalized to `https://host/owner/repo`, which is the project
+/// page on GitHub, GitLab and Bitbucket alike. Returns None without an origin
+/// or when it is a local path.
+pub fn git_remote_url(repo: &Path) -> Option<String> {
+    let git_dir = git_dir(repo)?;
+    // Worktrees share the main repository's config
+    let config_dir = match fs::read_to_string(git_dir.join("commondir")) {
+        Ok(common) => git_dir.join(common.trim()),
+        Err(_) => git_dir,
+    };
+    let config = fs::read_to_string(config_dir.join("config")).ok()?;
+
+    let mut in_origin = false;
+    for line in config.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_origin = line == "[remote \"origin\"]";
+        } else if in_origin {
+            if let Some((key, value)) = line.split_once('=') {
+                if key.trim() == "url" {
+                    return browsable_remote(value.trim());
+                }
+            }
+        }
+    }
+    None
+}
+
+fn browsable_remote(url: &str) -> Option<String> {
+    let (host, path) = if let Some(rest) = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))
+        .or_else(|| url.strip_prefix("ssh://"))
+        .or_else(|| url.strip_prefix("git://"))
+    {
+        let (authority, path) = rest.split_once('/')?;
+        // Drop credentials and ports: `git@host:22` -> `host`
+        let host = authority.rsplit('@').next()?;
+        (host.split(':').next()?, path)
+    } else {
+        // scp-like syntax: `git@github.com:owner/repo.git`
+        let (authority, path) = url.split_once(':')?;
+        if authority.contains('/') {
+            return None;
+        }
+        (authority.rsplit('@').next()?, path)
+    };
+
+    let path = path.trim_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path);
+    if host.is_empty() || path.is_empty() {
+        return None;
+    }
+    Some(format!("https://{}/{}", host, path))
+}
+
+/// Whether `git status --porcelain` reports any changes
+pub fn git_is_dirty(repo: &Path) -> Option<bool> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(["status", "--porcelain"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(!output.stdout.iter().all(|b| b.is_ascii_whitespace()))
+}
+
+/// On-disk snapshot of a discovery run
+#[derive(Serialize, Deserialize)]
+struct RepoCache {
+    root: PathBuf,
+    /// Unix seconds when the snapshot was taken
+    created: u64,
+    entries: Vec<CodeEntry>,
+}
+
+/// Discover git repositories, reusing a snapshot under `cache_dir()` while it
+/// is younger than `ttl`
+///
+/// The cache is keyed by root path. Outside Alfred (no `cache_dir()`) this is
+/// the same as [`discover_repos`].
+///
+/// Overlapping invocations (Alfred reruns the Script Filter on every
+/// keystroke) are safe: the snapshot is replaced atomically, so readers never
+/// see a partial file, and a [`FileLock`](jobs::FileLock) next to it lets one
+/// invocation write at a time. One that finds the lock held skips writing,
+/// since the holder is saving an equivalent snapshot.
+pub fn discover_repos_cached(root: &Path, ttl: std::time::Duration) -> Vec<CodeEntry> {
+    match cache_dir() {
+        Some(dir) => discover_repos_cached_at(
+            root,
+            ttl,
+            &repo_cache_file(&dir, root),
+            std::time::SystemTime::now(),
+            &Config::load(),
+        ),
+        None => discover_repos(root),
+    }
+}
+
+fn repo_cache_file(cache_dir: &Path, root: &Path) -> PathBuf {
+    use std::hash::{Hash, Hasher};
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    root.hash(&mut hasher);
+    cache_dir.join(format!("repos-{:016x}.json", hasher.finish()))
+}
+
+fn discover_repos_cached_at(
+    root: &Path,
+    ttl: std::time::Duration,
+    cache_file: &Path,
+    now: std::time::SystemTime,
+    config: &Config,
+) -> Vec<CodeEntry> {
+    let now_secs = now
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    let cached = fs::read_to_string(cache_file)
+        .ok()
+        .and_then(|data| serde_json::from_str::<RepoCache>(&data).ok())
+        .filter(|cache| cache.root == root && now_secs.saturating_sub(cache.created) < ttl.as_secs());
+    if let Some(cache) = cached {
+        return cache.entries;
+    }
+
+    let entries = discover_repos_with_config(root, config);
+    let cache = RepoCache {
+        root: root.to_path_buf(),
+        created: now_secs,
+        entries,
+    };
+    if let Ok(data) = serde_json::to_string(&cache) {
+        let lock = jobs::FileLock::try_acquire_at(cache_file.with_extension("lock"));
+        if !matches!(lock, Ok(None)) {
+            let _ = write_atomic(cache_file, data.as_bytes());
+        }
+    }
+    cache.entries
+}
+
+/// Which entries [`list_files`] returns
+#[derive(Debug, Clone, Default)]
+pub struct FileFilter {
+    /// Extensions to keep, without the dot (case-insensitive); empty keeps all files
+    pub extensions: Vec<String>,
+    /// Stop after this many results (applied after sorting)
+    pub max_results: Option<usize>,
+    /// Also return directories (regardless of `extensions`)
+    pub include_dirs: bool,
+    /// Walk depth limit, as for repo discovery
+    pub discovery: DiscoveryOptions,
+}
+
+impl FileFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn extensions(mut self, extensions: &[&str]) -> Self {
+        self.extensions = extensions.iter().map(|e| e.trim_start_matches('.').to_lowercase()).collect();
+        self
+    }
+
+    pub fn max_results(mut self, n: usize) -> Self {
+        self.max_results = Some(n);
+        self
+    }
+
+    pub fn include_dirs(mut self, include: bool) -> Self {
+        self.include_dirs = include;
+        self
+    }
+
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.discovery.max_depth = Some(depth);
+        self
+    }
+
+    fn keeps_file(&self, path: &Path) -> bool {
+        if self.extensions.is_empty() {
+            return true;
+        }
+        path.extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .is_some_and(|ext| self.extensions.contains(&ext))
+    }
+}
+
+/// Recursively list files under `root` matching `filter`, sorted by path
+///
+/// Hidden entries and the directories repo discovery skips (`node_modules`,
+/// `target`, ...) are not descended into or returned. The whole tree (up to
+/// the depth limit) is walked before sorting so the cap keeps the first
+/// paths by name rather than whichever the filesystem listed first.
+pub fn list_files(root: &Path, filter: &FileFilter) -> Vec<PathBuf> {
+    let mut results = Vec::new();
+    let mut stack = vec![(root.to_path_buf(), 0)];
+    let max_depth = filter.discovery.max_depth.unwrap_or(usize::MAX);
+
+    while let Some((dir, depth)) = stack.pop() {
+        if depth >= max_depth {
+            continue;
+        }
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().to_string();
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if file_type.is_dir() {
+                if should_skip_dir(&name) {
+                    continue;
+                }
+                if filter.include_dirs {
+                    results.push(path.clone());
+                }
+                stack.push((path, depth + 1));
+            } else if !name.starts_with('.') && filter.keeps_file(&path) {
+                results.push(path);
+            }
+        }
+    }
+
+    results.sort();
+    if let Some(max) = filter.max_results {
+        results.truncate(max);
+    }
+    results
+}
+
+/// Most recently modified files under `root`, newest first
+///
+/// Returns up to `limit` files, only those modified within `since` when
+/// given. Skips the same entries as [`list_files`]. Every file in the tree is
+/// stat'ed before sorting (there is no way to stop early on a time-sorted
+/// result), so prefer narrow roots on large trees.
+pub fn recent_files(
+    root: &Path,
+    limit: usize,
+    since: Option<std::time::Duration>,
+) -> Vec<(PathBuf, std::time::SystemTime)> {
+    let now = std::time::SystemTime::now();
+    let cutoff = since.and_then(|since| now.checked_sub(since));
+
+    let mut files: Vec<(PathBuf, std::time::SystemTime)> = list_files(root, &FileFilter::new())
+        .into_iter()
+        .filter_map(|path| {
+            let modified = fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+            Some((path, modified))
+        })
+        .filter(|(_, modified)| cutoff.is_none_or(|cutoff| *modified >= cutoff))
+        .collect();
+    files.sort_by_key(|(_, modified)| std::cmp::Reverse(*modified));
+    files.truncate(limit);
+    files
+}
+
+/// Directory names from the comma-separated `alfred_extra_skip_dirs`
+/// workflow variable, skipped in addition to [`should_skip_dir`]'s built-ins
+fn extra_skip_dirs() -> HashSet<String> {
+    extra_skip_dirs_from(workflow_var("alfred_extra_skip_dirs").as_deref())
+}
+
+/// Names in an `alfred_extra_skip_dirs` value, trimmed, dropping empty ones
+fn extra_skip_dirs_from(value: Option<&str>) -> HashSet<String> {
+    value
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
 fn should_skip_dir(name: &str) -> bool {
     if name.starts_with('.') {
         return true;
@@ -735,7 +3195,7 @@ fn should_skip_dir(name: &str) -> bool {
 // ============================================================================
 
 /// Argument type for Script Filters
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub enum ArgumentType {
     /// Argument is required (argumenttype = 0)
     Required,
@@ -756,6 +3216,51 @@ impl ArgumentType {
     }
 }
 
+impl std::str::FromStr for ArgumentType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "required" => Ok(ArgumentType::Required),
+            "optional" => Ok(ArgumentType::Optional),
+            "none" => Ok(ArgumentType::None),
+            other => Err(format!(
+                "Unknown argument type '{}' (expected required, optional or none)",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ArgumentType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ArgumentType::Required => "required",
+            ArgumentType::Optional => "optional",
+            ArgumentType::None => "none",
+        })
+    }
+}
+
+/// Whitespace trimming of a Script Filter's argument
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TrimMode {
+    /// Trim leading/trailing whitespace (argumenttrimmode = 0)
+    #[default]
+    Auto,
+    /// Pass the query exactly as typed (argumenttrimmode = 1)
+    Off,
+}
+
+impl TrimMode {
+    pub fn to_plist_value(&self) -> u8 {
+        match self {
+            TrimMode::Auto => 0,
+            TrimMode::Off => 1,
+        }
+    }
+}
+
 /// Script Filter configuration
 #[derive(Debug, Clone)]
 pub struct ScriptFilter {
@@ -765,10 +3270,16 @@ pub struct ScriptFilter {
     pub subtitle: String,
     pub running_subtext: String,
     pub script: String,
+    /// Path of an external script run instead of the inline `script`
+    pub script_file: Option<String>,
     pub argument_type: ArgumentType,
     pub with_space: bool,
     pub alfred_filters_results: bool,
     pub queue_delay_immediately: bool,
+    /// Bit flags for characters Alfred escapes in `{query}` (see [`ScriptFilter::escaping`])
+    pub escaping: u32,
+    pub trim_mode: TrimMode,
+    pub empty_query_as_nil: bool,
 }
 
 impl ScriptFilter {
@@ -780,10 +3291,14 @@ impl ScriptFilter {
             subtitle: String::new(),
             running_subtext: "Loading...".to_string(),
             script: String::new(),
+            script_file: None,
             argument_type: ArgumentType::Optional, // Default to optional for external trigger support
             with_space: false,
             alfred_filters_results: false,
             queue_delay_immediately: true,
+            escaping: 102,
+            trim_mode: TrimMode::Auto,
+            empty_query_as_nil: false,
         }
     }
 
@@ -807,7 +3322,15 @@ impl ScriptFilter {
         self
     }
 
-    pub fn argument_type(mut self, arg_type: ArgumentType) -> Self {
+    /// Run an external script file (relative to the workflow directory)
+    /// instead of an inline script; clears any inline `script`
+    pub fn script_file(mut self, path: &str) -> Self {
+        self.script = String::new();
+        self.script_file = Some(path.to_string());
+        self
+    }
+
+    pub fn argument_type(mut self, arg_type: ArgumentType) -> Self {
         self.argument_type = arg_type;
         self
     }
@@ -822,9 +3345,48 @@ impl ScriptFilter {
         self
     }
 
+    /// Characters Alfred backslash-escapes in the query, as summed flags:
+    /// 1 spaces, 2 backquotes, 4 double quotes, 8 brackets, 16 semicolons,
+    /// 32 dollars, 64 backslashes
+    ///
+    /// Defaults to 102 (backquotes, double quotes, dollars and backslashes),
+    /// which is what a quoted `"$1"`-style bash script needs. Use 0 when the
+    /// query arrives as argv.
+    pub fn escaping(mut self, flags: u32) -> Self {
+        self.escaping = flags;
+        self
+    }
+
+    /// Whether Alfred trims whitespace around the query (default: trim)
+    pub fn trim_mode(mut self, mode: TrimMode) -> Self {
+        self.trim_mode = mode;
+        self
+    }
+
+    /// Run the script with no argument, rather than an empty string, when
+    /// the query is empty (default false); useful for keyword-less filters
+    pub fn empty_query_as_nil(mut self, as_nil: bool) -> Self {
+        self.empty_query_as_nil = as_nil;
+        self
+    }
+
+    /// Check that exactly one of an inline script or a script file is set
+    pub fn validate(&self) -> Result<(), String> {
+        let has_file = self.script_file.as_deref().is_some_and(|f| !f.is_empty());
+        match (!self.script.is_empty(), has_file) {
+            (true, false) | (false, true) => Ok(()),
+            (false, false) => Err(format!("Script Filter {} has no script or script file", self.uid)),
+            (true, true) => Err(format!("Script Filter {} has both a script and a script file", self.uid)),
+        }
+    }
+
     /// Generate plist XML for this Script Filter object
+    ///
+    /// Call [`ScriptFilter::validate`] first; this renders whatever is set.
     pub fn to_plist_object(&self) -> String {
         let script_escaped = xml_escape(&self.script);
+        // type 0 runs the inline script with /bin/bash, 8 runs `scriptfile`
+        let script_type = if self.script_file.is_some() { 8 } else { 0 };
         format!(
             r#"<dict>
     <key>config</key>
@@ -834,13 +3396,13 @@ impl ScriptFilter {
         <key>alfredfiltersresultsmatchmode</key>
         <integer>2</integer>
         <key>argumenttreatemptyqueryasnil</key>
-        <false/>
+        <{emptyqueryasnil}/>
         <key>argumenttrimmode</key>
-        <integer>0</integer>
+        <integer>{trimmode}</integer>
         <key>argumenttype</key>
         <integer>{argumenttype}</integer>
         <key>escaping</key>
-        <integer>102</integer>
+        <integer>{escaping}</integer>
         <key>keyword</key>
         <string>{keyword}</string>
         <key>queuedelaycustom</key>
@@ -858,13 +3420,13 @@ impl ScriptFilter {
         <key>scriptargtype</key>
         <integer>1</integer>
         <key>scriptfile</key>
-        <string></string>
+        <string>{scriptfile}</string>
         <key>subtext</key>
         <string>{subtitle}</string>
         <key>title</key>
         <string>{title}</string>
         <key>type</key>
-        <integer>0</integer>
+        <integer>{script_type}</integer>
         <key>withspace</key>
         <{withspace}/>
     </dict>
@@ -876,11 +3438,15 @@ impl ScriptFilter {
     <integer>3</integer>
 </dict>"#,
             alfredfiltersresults = if self.alfred_filters_results { "true" } else { "false" },
+            emptyqueryasnil = if self.empty_query_as_nil { "true" } else { "false" },
+            trimmode = self.trim_mode.to_plist_value(),
             argumenttype = self.argument_type.to_plist_value(),
+            escaping = self.escaping,
             keyword = xml_escape(&self.keyword),
             queuedelayimmediately = if self.queue_delay_immediately { "true" } else { "false" },
             runningsubtext = xml_escape(&self.running_subtext),
             script = script_escaped,
+            scriptfile = xml_escape(self.script_file.as_deref().unwrap_or("")),
             subtitle = xml_escape(&self.subtitle),
             title = xml_escape(&self.title),
             withspace = if self.with_space { "true" } else { "false" },
@@ -987,6 +3553,11 @@ pub struct Connection {
     pub source_uid: String,
     pub dest_uid: String,
     pub modifiers: u32, // 0 = none, 1048576 = cmd
+    /// Hint Alfred shows while the modifier is held, e.g. "Open in Finder"
+    pub modifier_subtext: String,
+    /// Output of a multi-output source, e.g. a [`Conditional`]'s condition
+    /// (None for its else output or a single-output object)
+    pub source_output_uid: Option<String>,
 }
 
 impl Connection {
@@ -995,6 +3566,8 @@ impl Connection {
             source_uid: source.to_string(),
             dest_uid: dest.to_string(),
             modifiers: 0,
+            modifier_subtext: String::new(),
+            source_output_uid: None,
         }
     }
 
@@ -1002,6 +3575,46 @@ impl Connection {
         self.modifiers = 1048576;
         self
     }
+
+    /// Subtext shown on the source item while this connection's modifier is held
+    pub fn with_modifier_subtext(mut self, text: &str) -> Self {
+        self.modifier_subtext = text.to_string();
+        self
+    }
+
+    /// Leave the source through a specific output, see [`Conditional::output_uid`]
+    pub fn from_output(mut self, output_uid: &str) -> Self {
+        self.source_output_uid = Some(output_uid.to_string());
+        self
+    }
+
+    /// Generate the `<dict>` for this connection in the `connections` entry
+    /// of its source object
+    pub fn to_plist_dict(&self) -> String {
+        format!(
+            r#"<dict>
+    <key>destinationuid</key>
+    <string>{dest}</string>
+    <key>modifiers</key>
+    <integer>{modifiers}</integer>
+    <key>modifiersubtext</key>
+    <string>{modifier_subtext}</string>{source_output}
+    <key>vitoclose</key>
+    <false/>
+</dict>"#,
+            dest = xml_escape(&self.dest_uid),
+            modifiers = self.modifiers,
+            modifier_subtext = xml_escape(&self.modifier_subtext),
+            source_output = self
+                .source_output_uid
+                .as_deref()
+                .map(|uid| format!(
+                    "\n    <key>sourceoutputuid</key>\n    <string>{}</string>",
+                    xml_escape(uid)
+                ))
+                .unwrap_or_default(),
+        )
+    }
 }
 
 /// UI position for workflow canvas
@@ -1022,99 +3635,3478 @@ impl UIPosition {
     }
 }
 
-/// Helper to escape XML special characters
-fn xml_escape(s: &str) -> String {
-    s.replace('&', "&amp;")
-        .replace('<', "&lt;")
-        .replace('>', "&gt;")
-        .replace('"', "&quot;")
-        .replace('\'', "&apos;")
+/// Keyword input configuration
+#[derive(Debug, Clone)]
+pub struct Keyword {
+    pub uid: String,
+    pub keyword: String,
+    pub title: String,
+    pub subtitle: String,
+    pub argument_type: ArgumentType,
+    pub with_space: bool,
 }
 
-// ============================================================================
-// Utilities
-// ============================================================================
+impl Keyword {
+    pub fn new(uid: &str, keyword: &str) -> Self {
+        Self {
+            uid: uid.to_string(),
+            keyword: keyword.to_string(),
+            title: String::new(),
+            subtitle: String::new(),
+            argument_type: ArgumentType::Optional,
+            with_space: true,
+        }
+    }
 
-/// Expand ~ to home directory
-pub fn expand_path(path: &str) -> PathBuf {
-    if path.starts_with("~/") {
-        if let Some(home) = dirs_home() {
-            return home.join(&path[2..]);
+    pub fn title(mut self, title: &str) -> Self {
+        self.title = title.to_string();
+        self
+    }
+
+    pub fn subtitle(mut self, subtitle: &str) -> Self {
+        self.subtitle = subtitle.to_string();
+        self
+    }
+
+    pub fn argument_type(mut self, arg_type: ArgumentType) -> Self {
+        self.argument_type = arg_type;
+        self
+    }
+
+    pub fn with_space(mut self, with_space: bool) -> Self {
+        self.with_space = with_space;
+        self
+    }
+
+    /// Generate plist XML for this Keyword input
+    pub fn to_plist_object(&self) -> String {
+        format!(
+            r#"<dict>
+    <key>config</key>
+    <dict>
+        <key>argumenttype</key>
+        <integer>{argumenttype}</integer>
+        <key>keyword</key>
+        <string>{keyword}</string>
+        <key>subtext</key>
+        <string>{subtitle}</string>
+        <key>text</key>
+        <string>{title}</string>
+        <key>withspace</key>
+        <{withspace}/>
+    </dict>
+    <key>type</key>
+    <string>alfred.workflow.input.keyword</string>
+    <key>uid</key>
+    <string>{uid}</string>
+    <key>version</key>
+    <integer>1</integer>
+</dict>"#,
+            argumenttype = self.argument_type.to_plist_value(),
+            keyword = xml_escape(&self.keyword),
+            subtitle = xml_escape(&self.subtitle),
+            title = xml_escape(&self.title),
+            withspace = if self.with_space { "true" } else { "false" },
+            uid = &self.uid,
+        )
+    }
+}
+
+/// Run Script action configuration (bash, query passed as `$1`)
+#[derive(Debug, Clone)]
+pub struct RunScript {
+    pub uid: String,
+    pub script: String,
+    pub concurrently: bool,
+}
+
+impl RunScript {
+    pub fn new(uid: &str, script: &str) -> Self {
+        Self {
+            uid: uid.to_string(),
+            script: script.to_string(),
+            concurrently: false,
         }
     }
-    PathBuf::from(path)
+
+    pub fn concurrently(mut self, concurrently: bool) -> Self {
+        self.concurrently = concurrently;
+        self
+    }
+
+    /// Generate plist XML for this Run Script action
+    pub fn to_plist_object(&self) -> String {
+        format!(
+            r#"<dict>
+    <key>config</key>
+    <dict>
+        <key>concurrently</key>
+        <{concurrently}/>
+        <key>escaping</key>
+        <integer>102</integer>
+        <key>script</key>
+        <string>{script}</string>
+        <key>scriptargtype</key>
+        <integer>1</integer>
+        <key>scriptfile</key>
+        <string></string>
+        <key>type</key>
+        <integer>0</integer>
+    </dict>
+    <key>type</key>
+    <string>alfred.workflow.action.script</string>
+    <key>uid</key>
+    <string>{uid}</string>
+    <key>version</key>
+    <integer>2</integer>
+</dict>"#,
+            concurrently = if self.concurrently { "true" } else { "false" },
+            script = xml_escape(&self.script),
+            uid = &self.uid,
+        )
+    }
 }
 
-fn dirs_home() -> Option<PathBuf> {
-    std::env::var("HOME").ok().map(PathBuf::from)
+/// Post Notification output configuration
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub uid: String,
+    pub title: String,
+    /// Body text; `{query}` is the incoming argument
+    pub text: String,
 }
 
-/// Get environment variable set by Alfred
-pub fn env(name: &str) -> Option<String> {
-    std::env::var(format!("alfred_{}", name)).ok()
+impl Notification {
+    pub fn new(uid: &str, title: &str) -> Self {
+        Self {
+            uid: uid.to_string(),
+            title: title.to_string(),
+            text: "{query}".to_string(),
+        }
+    }
+
+    pub fn text(mut self, text: &str) -> Self {
+        self.text = text.to_string();
+        self
+    }
+
+    /// Generate plist XML for this Post Notification output
+    pub fn to_plist_object(&self) -> String {
+        format!(
+            r#"<dict>
+    <key>config</key>
+    <dict>
+        <key>lastpathcomponent</key>
+        <false/>
+        <key>onlyshowifquerypopulated</key>
+        <false/>
+        <key>removeextension</key>
+        <false/>
+        <key>text</key>
+        <string>{text}</string>
+        <key>title</key>
+        <string>{title}</string>
+    </dict>
+    <key>type</key>
+    <string>alfred.workflow.output.notification</string>
+    <key>uid</key>
+    <string>{uid}</string>
+    <key>version</key>
+    <integer>1</integer>
+</dict>"#,
+            text = xml_escape(&self.text),
+            title = xml_escape(&self.title),
+            uid = &self.uid,
+        )
+    }
 }
 
-/// Check if running inside Alfred
-pub fn in_alfred() -> bool {
-    std::env::var("alfred_version").is_ok()
+/// macOS virtual key codes (ANSI layout) for [`Hotkey::shortcut`], by name
+const HOTKEY_KEY_CODES: &[(&str, u16)] = &[
+    ("a", 0), ("s", 1), ("d", 2), ("f", 3), ("h", 4), ("g", 5), ("z", 6), ("x", 7),
+    ("c", 8), ("v", 9), ("b", 11), ("q", 12), ("w", 13), ("e", 14), ("r", 15),
+    ("y", 16), ("t", 17), ("1", 18), ("2", 19), ("3", 20), ("4", 21), ("6", 22),
+    ("5", 23), ("=", 24), ("9", 25), ("7", 26), ("-", 27), ("8", 28), ("0", 29),
+    ("]", 30), ("o", 31), ("u", 32), ("[", 33), ("i", 34), ("p", 35), ("return", 36),
+    ("l", 37), ("j", 38), ("'", 39), ("k", 40), (";", 41), ("\\", 42), (",", 43),
+    ("/", 44), ("n", 45), ("m", 46), (".", 47), ("tab", 48), ("space", 49), ("`", 50),
+    ("delete", 51), ("escape", 53), ("f5", 96), ("f6", 97), ("f7", 98), ("f3", 99),
+    ("f8", 100), ("f9", 101), ("f11", 103), ("f10", 109), ("f12", 111), ("f4", 118),
+    ("f2", 120), ("f1", 122), ("left", 123), ("right", 124), ("down", 125), ("up", 126),
+];
+
+/// What a [`Hotkey`] does when pressed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HotkeyAction {
+    /// Pass the argument on to the connected objects
+    #[default]
+    PassThrough,
+    /// Open Alfred with the argument as the query
+    ShowAlfred,
 }
 
-/// Get workflow bundle ID from environment
-pub fn bundle_id() -> Option<String> {
-    std::env::var("alfred_workflow_bundleid").ok()
+/// Argument a [`Hotkey`] passes on
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum HotkeyArgument {
+    #[default]
+    None,
+    /// Text selected in the frontmost app
+    Selection,
+    /// Current clipboard contents
+    Clipboard,
+    /// Fixed text (may contain `{query}`-style placeholders)
+    Text(String),
 }
 
-/// Get workflow data directory
-pub fn data_dir() -> Option<PathBuf> {
-    std::env::var("alfred_workflow_data").ok().map(PathBuf::from)
+impl HotkeyArgument {
+    fn to_plist_value(&self) -> i32 {
+        match self {
+            HotkeyArgument::None => 0,
+            HotkeyArgument::Selection => 1,
+            HotkeyArgument::Clipboard => 2,
+            HotkeyArgument::Text(_) => 3,
+        }
+    }
 }
 
-/// Get workflow cache directory
-pub fn cache_dir() -> Option<PathBuf> {
-    std::env::var("alfred_workflow_cache")
-        .ok()
-        .map(PathBuf::from)
+/// Hotkey trigger configuration
+///
+/// Without a key, Alfred shows the trigger unassigned so the user can record
+/// their own shortcut.
+#[derive(Debug, Clone)]
+pub struct Hotkey {
+    pub uid: String,
+    /// macOS virtual key code (0 with an empty `key_label` when unassigned)
+    pub key_code: u16,
+    /// Key as shown on the canvas, e.g. `"Space"` or `"K"`
+    pub key_label: String,
+    pub modifiers: Option<ModKey>,
+    pub action: HotkeyAction,
+    pub argument: HotkeyArgument,
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+impl Hotkey {
+    pub fn new(uid: &str) -> Self {
+        Self {
+            uid: uid.to_string(),
+            key_code: 0,
+            key_label: String::new(),
+            modifiers: None,
+            action: HotkeyAction::PassThrough,
+            argument: HotkeyArgument::None,
+        }
+    }
 
-    #[test]
-    fn test_item_builder() {
-        let item = Item::new("Title", "Subtitle")
-            .arg("/path")
-            .uid("123")
-            .valid(true);
+    /// Set the key and modifiers from a shortcut such as `"cmd+shift+space"`
+    ///
+    /// The last `+`-separated part is the key (a letter, digit, punctuation,
+    /// `space`, `return`, `tab`, `delete`, `escape`, an arrow or `f1`–`f12`);
+    /// the others are modifier names as in [`ModKey`]'s `FromStr`.
+    pub fn shortcut(mut self, shortcut: &str) -> Result<Self, String> {
+        let (modifiers, key) = match shortcut.rsplit_once('+') {
+            Some((modifiers, key)) => (Some(modifiers.parse::<ModKey>()?), key),
+            None => (None, shortcut),
+        };
+        let key = key.trim().to_ascii_lowercase();
+        let &(name, code) = HOTKEY_KEY_CODES
+            .iter()
+            .find(|(name, _)| *name == key)
+            .ok_or_else(|| format!("Unknown key '{}' in '{}'", key, shortcut))?;
+        let mut label = name.to_string();
+        label[..1].make_ascii_uppercase();
+        self.key_code = code;
+        self.key_label = label;
+        self.modifiers = modifiers;
+        Ok(self)
+    }
 
-        assert_eq!(item.title, "Title");
-        assert_eq!(item.subtitle, Some("Subtitle".to_string()));
-        assert_eq!(item.arg, Some("/path".to_string()));
-        assert_eq!(item.uid, Some("123".to_string()));
-        assert_eq!(item.valid, Some(true));
+    /// Set a raw key code and its label, for keys [`Hotkey::shortcut`] doesn't name
+    pub fn key(mut self, key_code: u16, label: &str) -> Self {
+        self.key_code = key_code;
+        self.key_label = label.to_string();
+        self
     }
 
-    #[test]
-    fn test_output_json() {
-        let output = Output::new(vec![Item::new("Test", "Sub").arg("val")]);
-        let json = output.to_json();
-        assert!(json.contains("\"title\":\"Test\""));
-        assert!(json.contains("\"arg\":\"val\""));
+    pub fn modifiers(mut self, modifiers: ModKey) -> Self {
+        self.modifiers = Some(modifiers);
+        self
     }
 
-    #[test]
-    fn test_fuzzy_match() {
-        assert!(fuzzy_match("fc", "flow-code"));
-        assert!(fuzzy_match("fl", "flow"));
-        assert!(fuzzy_match("", "anything"));
-        assert!(!fuzzy_match("xyz", "abc"));
+    pub fn action(mut self, action: HotkeyAction) -> Self {
+        self.action = action;
+        self
     }
 
-    #[test]
-    fn test_fuzzy_score() {
-        // Exact prefix should score higher
-        let score_prefix = fuzzy_score("fl", "flow");
-        let score_middle = fuzzy_score("fl", "alfred");
-        assert!(score_prefix > score_middle);
+    pub fn argument(mut self, argument: HotkeyArgument) -> Self {
+        self.argument = argument;
+        self
+    }
+
+    /// Generate plist XML for this Hotkey trigger
+    pub fn to_plist_object(&self) -> String {
+        let argument_text = match &self.argument {
+            HotkeyArgument::Text(text) => text.as_str(),
+            _ => "",
+        };
+        format!(
+            r#"<dict>
+    <key>config</key>
+    <dict>
+        <key>action</key>
+        <integer>{action}</integer>
+        <key>argument</key>
+        <integer>{argument}</integer>
+        <key>argumenttext</key>
+        <string>{argument_text}</string>
+        <key>focusedappvariable</key>
+        <false/>
+        <key>focusedappvariablename</key>
+        <string></string>
+        <key>hotkey</key>
+        <integer>{hotkey}</integer>
+        <key>hotmod</key>
+        <integer>{hotmod}</integer>
+        <key>hotstring</key>
+        <string>{hotstring}</string>
+        <key>leftcursor</key>
+        <false/>
+        <key>modsmode</key>
+        <integer>0</integer>
+        <key>relatedAppsMode</key>
+        <integer>0</integer>
+    </dict>
+    <key>type</key>
+    <string>alfred.workflow.trigger.hotkey</string>
+    <key>uid</key>
+    <string>{uid}</string>
+    <key>version</key>
+    <integer>2</integer>
+</dict>"#,
+            action = match self.action {
+                HotkeyAction::PassThrough => 0,
+                HotkeyAction::ShowAlfred => 1,
+            },
+            argument = self.argument.to_plist_value(),
+            argument_text = xml_escape(argument_text),
+            hotkey = self.key_code,
+            hotmod = self.modifiers.map_or(0, ModKey::event_flags),
+            hotstring = xml_escape(&self.key_label),
+            uid = &self.uid,
+        )
+    }
+}
+
+/// How a [`Condition`] compares its input with its match string
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MatchMode {
+    #[default]
+    Equal,
+    NotEqual,
+    GreaterThan,
+    LessThan,
+    Regex,
+}
+
+impl MatchMode {
+    fn to_plist_value(self) -> i32 {
+        match self {
+            MatchMode::Equal => 0,
+            MatchMode::NotEqual => 1,
+            MatchMode::GreaterThan => 2,
+            MatchMode::LessThan => 3,
+            MatchMode::Regex => 4,
+        }
+    }
+}
+
+/// One branch of a [`Conditional`]
+#[derive(Debug, Clone)]
+pub struct Condition {
+    /// Value tested, `{query}` or e.g. `{var:mode}`
+    pub input: String,
+    pub match_mode: MatchMode,
+    pub match_string: String,
+    pub match_case_sensitive: bool,
+    /// Name of this branch's output on the canvas
+    pub output_label: String,
+}
+
+impl Condition {
+    /// Branch taken when `{query}` equals `match_string`
+    pub fn new(match_string: &str, output_label: &str) -> Self {
+        Self {
+            input: "{query}".to_string(),
+            match_mode: MatchMode::Equal,
+            match_string: match_string.to_string(),
+            match_case_sensitive: false,
+            output_label: output_label.to_string(),
+        }
+    }
+
+    pub fn input(mut self, input: &str) -> Self {
+        self.input = input.to_string();
+        self
+    }
+
+    pub fn match_mode(mut self, mode: MatchMode) -> Self {
+        self.match_mode = mode;
+        self
+    }
+
+    pub fn case_sensitive(mut self, case_sensitive: bool) -> Self {
+        self.match_case_sensitive = case_sensitive;
+        self
+    }
+}
+
+/// Conditional utility: routes its input to the first matching condition's
+/// output, or to the else output
+///
+/// Connect a branch with [`Connection::from_output`] and
+/// [`Conditional::output_uid`]; a connection without an output leaves
+/// through else.
+#[derive(Debug, Clone)]
+pub struct Conditional {
+    pub uid: String,
+    pub conditions: Vec<Condition>,
+    pub else_label: String,
+}
+
+impl Conditional {
+    pub fn new(uid: &str) -> Self {
+        Self {
+            uid: uid.to_string(),
+            conditions: Vec::new(),
+            else_label: "else".to_string(),
+        }
+    }
+
+    /// Append a condition; conditions are tested in the order added
+    pub fn condition(mut self, condition: Condition) -> Self {
+        self.conditions.push(condition);
+        self
+    }
+
+    pub fn else_label(mut self, label: &str) -> Self {
+        self.else_label = label.to_string();
+        self
+    }
+
+    /// Uid of the output for the condition labeled `output_label`
+    ///
+    /// Outputs are numbered by position, `<uid>-1` for the first condition.
+    pub fn output_uid(&self, output_label: &str) -> Option<String> {
+        let index = self.conditions.iter().position(|c| c.output_label == output_label)?;
+        Some(format!("{}-{}", self.uid, index + 1))
+    }
+
+    /// Require at least one condition and distinct, non-empty output labels
+    pub fn validate(&self) -> Result<(), String> {
+        if self.conditions.is_empty() {
+            return Err(format!("Conditional {} has no conditions", self.uid));
+        }
+        let mut labels = HashSet::new();
+        for condition in &self.conditions {
+            if condition.output_label.is_empty() {
+                return Err(format!("Conditional {} has a condition without a label", self.uid));
+            }
+            if !labels.insert(condition.output_label.as_str()) {
+                return Err(format!(
+                    "Conditional {} has duplicate output label {}",
+                    self.uid, condition.output_label
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Generate plist XML for this Conditional utility
+    pub fn to_plist_object(&self) -> String {
+        let conditions: String = self
+            .conditions
+            .iter()
+            .enumerate()
+            .map(|(i, condition)| {
+                format!(
+                    r#"            <dict>
+                <key>inputstring</key>
+                <string>{input}</string>
+                <key>matchcasesensitive</key>
+                <{case_sensitive}/>
+                <key>matchmode</key>
+                <integer>{match_mode}</integer>
+                <key>matchstring</key>
+                <string>{match_string}</string>
+                <key>outputlabel</key>
+                <string>{output_label}</string>
+                <key>uid</key>
+                <string>{uid}-{n}</string>
+            </dict>
+"#,
+                    input = xml_escape(&condition.input),
+                    case_sensitive = if condition.match_case_sensitive { "true" } else { "false" },
+                    match_mode = condition.match_mode.to_plist_value(),
+                    match_string = xml_escape(&condition.match_string),
+                    output_label = xml_escape(&condition.output_label),
+                    uid = xml_escape(&self.uid),
+                    n = i + 1,
+                )
+            })
+            .collect();
+        format!(
+            r#"<dict>
+    <key>config</key>
+    <dict>
+        <key>conditions</key>
+        <array>
+{conditions}        </array>
+        <key>elselabel</key>
+        <string>{else_label}</string>
+        <key>hideelse</key>
+        <false/>
+    </dict>
+    <key>type</key>
+    <string>alfred.workflow.utility.conditional</string>
+    <key>uid</key>
+    <string>{uid}</string>
+    <key>version</key>
+    <integer>1</integer>
+</dict>"#,
+            else_label = xml_escape(&self.else_label),
+            uid = &self.uid,
+        )
+    }
+}
+
+/// Text change applied by a [`Transform`], in the order of Alfred's popup
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TransformMode {
+    #[default]
+    TrimWhitespace,
+    Lowercase,
+    Uppercase,
+    CapitalizeWords,
+    StripDiacritics,
+    StripNonAlphanumeric,
+    RandomCase,
+}
+
+impl TransformMode {
+    fn to_plist_value(self) -> i32 {
+        match self {
+            TransformMode::TrimWhitespace => 0,
+            TransformMode::Lowercase => 1,
+            TransformMode::Uppercase => 2,
+            TransformMode::CapitalizeWords => 3,
+            TransformMode::StripDiacritics => 4,
+            TransformMode::StripNonAlphanumeric => 5,
+            TransformMode::RandomCase => 6,
+        }
+    }
+}
+
+/// Transform utility configuration (find-and-replace is Alfred's separate
+/// Replace utility)
+#[derive(Debug, Clone)]
+pub struct Transform {
+    pub uid: String,
+    pub mode: TransformMode,
+}
+
+impl Transform {
+    pub fn new(uid: &str, mode: TransformMode) -> Self {
+        Self {
+            uid: uid.to_string(),
+            mode,
+        }
+    }
+
+    /// Generate plist XML for this Transform utility
+    pub fn to_plist_object(&self) -> String {
+        format!(
+            r#"<dict>
+    <key>config</key>
+    <dict>
+        <key>type</key>
+        <integer>{mode}</integer>
+    </dict>
+    <key>type</key>
+    <string>alfred.workflow.utility.transform</string>
+    <key>uid</key>
+    <string>{uid}</string>
+    <key>version</key>
+    <integer>1</integer>
+</dict>"#,
+            mode = self.mode.to_plist_value(),
+            uid = &self.uid,
+        )
+    }
+}
+
+/// A workflow object that [`WorkflowBuilder`] can place on the canvas
+pub trait WorkflowObject {
+    /// The object's uid, used as its id in connections
+    fn uid(&self) -> &str;
+
+    /// The object's `<dict>` for the `objects` array
+    fn to_plist_object(&self) -> String;
+
+    /// Reject configurations Alfred can't run
+    fn validate(&self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+macro_rules! impl_workflow_object {
+    ($($ty:ty),*) => {$(
+        impl WorkflowObject for $ty {
+            fn uid(&self) -> &str {
+                &self.uid
+            }
+
+            fn to_plist_object(&self) -> String {
+                <$ty>::to_plist_object(self)
+            }
+        }
+    )*};
+}
+
+impl_workflow_object!(
+    Keyword,
+    ExternalTrigger,
+    Hotkey,
+    OpenFileAction,
+    RunScript,
+    Notification,
+    Transform
+);
+
+impl WorkflowObject for Conditional {
+    fn uid(&self) -> &str {
+        &self.uid
+    }
+
+    fn to_plist_object(&self) -> String {
+        Conditional::to_plist_object(self)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        Conditional::validate(self)
+    }
+}
+
+impl WorkflowObject for ScriptFilter {
+    fn uid(&self) -> &str {
+        &self.uid
+    }
+
+    fn to_plist_object(&self) -> String {
+        ScriptFilter::to_plist_object(self)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        ScriptFilter::validate(self)
+    }
+}
+
+/// Horizontal and vertical spacing of [`layout_positions`], in canvas points
+const LAYOUT_STEP: (f64, f64) = (230.0, 130.0);
+
+/// Place objects left to right by how many connections lead to them
+///
+/// Each object's column is the length of the longest connection path from a
+/// source (an object nothing connects into); objects in the same column are
+/// stacked in `uids` order. Connections forming a cycle are ignored for
+/// placement.
+pub fn layout_positions(uids: &[&str], connections: &[Connection]) -> Vec<UIPosition> {
+    let mut column: HashMap<&str, usize> = uids.iter().map(|uid| (*uid, 0)).collect();
+    // Relax at most once per object so cycles can't loop forever
+    for _ in 0..uids.len() {
+        let mut changed = false;
+        for connection in connections {
+            let (Some(&from), Some(&to)) = (
+                column.get(connection.source_uid.as_str()),
+                column.get(connection.dest_uid.as_str()),
+            ) else {
+                continue;
+            };
+            if to < from + 1 {
+                column.insert(connection.dest_uid.as_str(), from + 1);
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    let mut rows: HashMap<usize, usize> = HashMap::new();
+    uids.iter()
+        .map(|uid| {
+            let col = column[uid];
+            let row = rows.entry(col).or_insert(0);
+            let position = UIPosition::new(
+                uid,
+                30.0 + LAYOUT_STEP.0 * col as f64,
+                30.0 + LAYOUT_STEP.1 * *row as f64,
+            );
+            *row += 1;
+            position
+        })
+        .collect()
+}
+
+/// Describe a workflow as a graph of objects and connections, then render
+/// the complete `info.plist`
+///
+/// Objects are added with [`WorkflowBuilder::object`] and joined by uid with
+/// [`WorkflowBuilder::connect`]. Objects without an explicit
+/// [`WorkflowBuilder::position`] are placed by [`layout_positions`].
+pub struct WorkflowBuilder {
+    bundle_id: String,
+    name: String,
+    version: String,
+    description: String,
+    created_by: String,
+    web_address: String,
+    readme: String,
+    variables: BTreeMap<String, String>,
+    dont_export: Vec<String>,
+    objects: Vec<Box<dyn WorkflowObject>>,
+    connections: Vec<Connection>,
+    positions: Vec<UIPosition>,
+}
+
+impl WorkflowBuilder {
+    pub fn new(bundle_id: &str, name: &str) -> Self {
+        Self {
+            bundle_id: bundle_id.to_string(),
+            name: name.to_string(),
+            version: String::new(),
+            description: String::new(),
+            created_by: String::new(),
+            web_address: String::new(),
+            readme: String::new(),
+            variables: BTreeMap::new(),
+            dont_export: Vec::new(),
+            objects: Vec::new(),
+            connections: Vec::new(),
+            positions: Vec::new(),
+        }
+    }
+
+    pub fn version(mut self, version: &str) -> Self {
+        self.version = version.to_string();
+        self
+    }
+
+    pub fn description(mut self, description: &str) -> Self {
+        self.description = description.to_string();
+        self
+    }
+
+    pub fn created_by(mut self, author: &str) -> Self {
+        self.created_by = author.to_string();
+        self
+    }
+
+    pub fn web_address(mut self, url: &str) -> Self {
+        self.web_address = url.to_string();
+        self
+    }
+
+    /// Text shown in the workflow's "About" panel
+    pub fn readme(mut self, readme: &str) -> Self {
+        self.readme = readme.to_string();
+        self
+    }
+
+    /// Set a workflow environment variable, visible to every script
+    pub fn variable(mut self, key: &str, value: &str) -> Self {
+        self.variables.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    /// Mark a variable as "don't export" so Alfred strips its value when the
+    /// workflow is exported or shared; use for secrets such as API keys
+    pub fn dont_export(mut self, key: &str) -> Self {
+        if !self.dont_export.iter().any(|k| k == key) {
+            self.dont_export.push(key.to_string());
+        }
+        self
+    }
+
+    /// Add an object to the canvas
+    pub fn object(mut self, object: impl WorkflowObject + 'static) -> Self {
+        self.objects.push(Box::new(object));
+        self
+    }
+
+    /// Connect two objects by uid with no modifier
+    pub fn connect(self, from_uid: &str, to_uid: &str) -> Self {
+        self.connection(Connection::new(from_uid, to_uid))
+    }
+
+    /// Add a fully specified connection (e.g. with a modifier)
+    pub fn connection(mut self, connection: Connection) -> Self {
+        self.connections.push(connection);
+        self
+    }
+
+    /// Pin an object to a canvas position instead of auto-placing it
+    pub fn position(mut self, uid: &str, x: f64, y: f64) -> Self {
+        self.positions.push(UIPosition::new(uid, x, y));
+        self
+    }
+
+    /// Render `info.plist`, failing on duplicate uids, connections to unknown
+    /// objects, or an object that doesn't validate
+    pub fn build(&self) -> Result<String, String> {
+        let mut uids: Vec<&str> = Vec::new();
+        for object in &self.objects {
+            object.validate()?;
+            if uids.contains(&object.uid()) {
+                return Err(format!("Duplicate object uid: {}", object.uid()));
+            }
+            uids.push(object.uid());
+        }
+        for connection in &self.connections {
+            for uid in [&connection.source_uid, &connection.dest_uid] {
+                if !uids.contains(&uid.as_str()) {
+                    return Err(format!(
+                        "Connection {} -> {} references unknown object {}",
+                        connection.source_uid, connection.dest_uid, uid
+                    ));
+                }
+            }
+        }
+        for position in &self.positions {
+            if !uids.contains(&position.uid.as_str()) {
+                return Err(format!("Position references unknown object {}", position.uid));
+            }
+        }
+        for key in &self.dont_export {
+            if !self.variables.contains_key(key) {
+                return Err(format!("Don't-export variable {} is not defined", key));
+            }
+        }
+
+        let mut connections = String::new();
+        for uid in &uids {
+            let outgoing: Vec<&Connection> =
+                self.connections.iter().filter(|c| c.source_uid == *uid).collect();
+            if outgoing.is_empty() {
+                continue;
+            }
+            connections.push_str(&format!("\t\t<key>{}</key>\n\t\t<array>\n", xml_escape(uid)));
+            for connection in outgoing {
+                connections.push_str(&indent(&connection.to_plist_dict(), 3));
+            }
+            connections.push_str("\t\t</array>\n");
+        }
+
+        let objects: String =
+            self.objects.iter().map(|o| indent(&o.to_plist_object(), 2)).collect();
+
+        let mut uidata = String::new();
+        for auto in layout_positions(&uids, &self.connections) {
+            let position = self.positions.iter().find(|p| p.uid == auto.uid).unwrap_or(&auto);
+            let dict = format!(
+                "<dict>\n    <key>xpos</key>\n    <real>{}</real>\n    <key>ypos</key>\n    <real>{}</real>\n</dict>",
+                position.x, position.y
+            );
+            uidata.push_str(&format!("\t\t<key>{}</key>\n", xml_escape(&position.uid)));
+            uidata.push_str(&indent(&dict, 2));
+        }
+
+        let mut variables = String::new();
+        for (key, value) in &self.variables {
+            variables.push_str(&format!(
+                "\t\t<key>{}</key>\n\t\t<string>{}</string>\n",
+                xml_escape(key),
+                xml_escape(value)
+            ));
+        }
+
+        let dont_export: String = self
+            .dont_export
+            .iter()
+            .map(|key| format!("\t\t<string>{}</string>\n", xml_escape(key)))
+            .collect();
+
+        Ok(format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<key>bundleid</key>
+	<string>{bundle_id}</string>
+	<key>connections</key>
+	<dict>
+{connections}	</dict>
+	<key>createdby</key>
+	<string>{created_by}</string>
+	<key>description</key>
+	<string>{description}</string>
+	<key>name</key>
+	<string>{name}</string>
+	<key>objects</key>
+	<array>
+{objects}	</array>
+	<key>readme</key>
+	<string>{readme}</string>
+	<key>uidata</key>
+	<dict>
+{uidata}	</dict>
+	<key>variables</key>
+	<dict>
+{variables}	</dict>
+	<key>variablesdontexport</key>
+	<array>
+{dont_export}	</array>
+	<key>version</key>
+	<string>{version}</string>
+	<key>webaddress</key>
+	<string>{web_address}</string>
+</dict>
+</plist>
+"#,
+            bundle_id = xml_escape(&self.bundle_id),
+            created_by = xml_escape(&self.created_by),
+            description = xml_escape(&self.description),
+            name = xml_escape(&self.name),
+            readme = xml_escape(&self.readme),
+            version = xml_escape(&self.version),
+            web_address = xml_escape(&self.web_address),
+        ))
+    }
+}
+
+/// An object read back from a workflow's `info.plist` by [`parse_workflow_objects`]
+#[derive(Debug, Clone)]
+pub enum ParsedObject {
+    ScriptFilter(ScriptFilter),
+    Keyword(Keyword),
+    ExternalTrigger(ExternalTrigger),
+    Hotkey(Hotkey),
+    RunScript(RunScript),
+    OpenFile(OpenFileAction),
+    Notification(Notification),
+    Transform(Transform),
+    /// Any other type, with its `config` dict as stored
+    Unknown {
+        uid: String,
+        object_type: String,
+        config: plist::Dictionary,
+    },
+}
+
+impl ParsedObject {
+    pub fn uid(&self) -> &str {
+        match self {
+            ParsedObject::ScriptFilter(o) => &o.uid,
+            ParsedObject::Keyword(o) => &o.uid,
+            ParsedObject::ExternalTrigger(o) => &o.uid,
+            ParsedObject::Hotkey(o) => &o.uid,
+            ParsedObject::RunScript(o) => &o.uid,
+            ParsedObject::OpenFile(o) => &o.uid,
+            ParsedObject::Notification(o) => &o.uid,
+            ParsedObject::Transform(o) => &o.uid,
+            ParsedObject::Unknown { uid, .. } => uid,
+        }
+    }
+}
+
+/// Read a workflow's objects into the builder structs, in `objects` order
+///
+/// Settings the builders don't model are dropped, so a parsed object
+/// rendered with `to_plist_object` may differ from the original in them.
+/// Conditionals are [`ParsedObject::Unknown`], since [`Conditional`]
+/// generates its output uids and existing connections would no longer match.
+pub fn parse_workflow_objects(plist_path: &Path) -> Result<Vec<ParsedObject>, Error> {
+    if !plist_path.exists() {
+        return Err(Error::NotFound(plist_path.to_path_buf()));
+    }
+    let root = plist::Value::from_file(plist_path).map_err(|e| Error::Parse(e.to_string()))?;
+    let objects = root
+        .as_dictionary()
+        .and_then(|root| root.get("objects"))
+        .and_then(plist::Value::as_array)
+        .ok_or_else(|| Error::Parse("info.plist has no objects array".to_string()))?;
+
+    let mut parsed = Vec::new();
+    for (i, object) in objects.iter().enumerate() {
+        let object = object
+            .as_dictionary()
+            .ok_or_else(|| Error::Parse(format!("object {} is not a dictionary", i)))?;
+        let uid = object
+            .get("uid")
+            .and_then(plist::Value::as_string)
+            .ok_or_else(|| Error::Parse(format!("object {} has no uid", i)))?;
+        let object_type = object.get("type").and_then(plist::Value::as_string).unwrap_or("");
+        let config = object
+            .get("config")
+            .and_then(plist::Value::as_dictionary)
+            .cloned()
+            .unwrap_or_default();
+        parsed.push(parse_object(uid, object_type, config));
+    }
+    Ok(parsed)
+}
+
+fn parse_object(uid: &str, object_type: &str, config: plist::Dictionary) -> ParsedObject {
+    let string = |key: &str| config.get(key).and_then(plist::Value::as_string).unwrap_or("");
+    let boolean = |key: &str| config.get(key).and_then(plist::Value::as_boolean);
+    let integer = |key: &str| config.get(key).and_then(plist::Value::as_signed_integer);
+    let argument_type = match integer("argumenttype") {
+        Some(0) => ArgumentType::Required,
+        Some(2) => ArgumentType::None,
+        _ => ArgumentType::Optional,
+    };
+
+    match object_type {
+        "alfred.workflow.input.scriptfilter" => {
+            let mut filter = ScriptFilter::new(uid, string("keyword"))
+                .title(string("title"))
+                .subtitle(string("subtext"))
+                .running_subtext(string("runningsubtext"))
+                .argument_type(argument_type)
+                .with_space(boolean("withspace").unwrap_or(false))
+                .alfred_filters_results(boolean("alfredfiltersresults").unwrap_or(false))
+                .escaping(integer("escaping").unwrap_or(102) as u32)
+                .trim_mode(match integer("argumenttrimmode") {
+                    Some(1) => TrimMode::Off,
+                    _ => TrimMode::Auto,
+                })
+                .empty_query_as_nil(boolean("argumenttreatemptyqueryasnil").unwrap_or(false));
+            filter.queue_delay_immediately =
+                boolean("queuedelayimmediatelyinitially").unwrap_or(true);
+            filter = match (integer("type"), string("scriptfile")) {
+                (Some(8), file) if !file.is_empty() => filter.script_file(file),
+                _ => filter.script(string("script")),
+            };
+            ParsedObject::ScriptFilter(filter)
+        }
+        "alfred.workflow.input.keyword" => ParsedObject::Keyword(
+            Keyword::new(uid, string("keyword"))
+                .title(string("text"))
+                .subtitle(string("subtext"))
+                .argument_type(argument_type)
+                .with_space(boolean("withspace").unwrap_or(true)),
+        ),
+        "alfred.workflow.trigger.external" => ParsedObject::ExternalTrigger(
+            ExternalTrigger::new(uid, string("triggerid"))
+                .available_via_url(boolean("availableviaurlhandler").unwrap_or(false)),
+        ),
+        "alfred.workflow.trigger.hotkey" => {
+            let mut hotkey = Hotkey::new(uid)
+                .key(integer("hotkey").unwrap_or(0) as u16, string("hotstring"))
+                .action(match integer("action") {
+                    Some(1) => HotkeyAction::ShowAlfred,
+                    _ => HotkeyAction::PassThrough,
+                })
+                .argument(match integer("argument") {
+                    Some(1) => HotkeyArgument::Selection,
+                    Some(2) => HotkeyArgument::Clipboard,
+                    Some(3) => HotkeyArgument::Text(string("argumenttext").to_string()),
+                    _ => HotkeyArgument::None,
+                });
+            hotkey.modifiers = ModKey::from_event_flags(integer("hotmod").unwrap_or(0) as u32);
+            ParsedObject::Hotkey(hotkey)
+        }
+        "alfred.workflow.action.script" => ParsedObject::RunScript(
+            RunScript::new(uid, string("script"))
+                .concurrently(boolean("concurrently").unwrap_or(false)),
+        ),
+        "alfred.workflow.action.openfile" => {
+            let mut action = OpenFileAction::new(uid);
+            if !string("openwith").is_empty() {
+                action = action.open_with(string("openwith"));
+            }
+            ParsedObject::OpenFile(action)
+        }
+        "alfred.workflow.output.notification" => ParsedObject::Notification(
+            Notification::new(uid, string("title")).text(string("text")),
+        ),
+        "alfred.workflow.utility.transform" => {
+            let mode = match integer("type") {
+                Some(1) => TransformMode::Lowercase,
+                Some(2) => TransformMode::Uppercase,
+                Some(3) => TransformMode::CapitalizeWords,
+                Some(4) => TransformMode::StripDiacritics,
+                Some(5) => TransformMode::StripNonAlphanumeric,
+                Some(6) => TransformMode::RandomCase,
+                _ => TransformMode::TrimWhitespace,
+            };
+            ParsedObject::Transform(Transform::new(uid, mode))
+        }
+        _ => ParsedObject::Unknown {
+            uid: uid.to_string(),
+            object_type: object_type.to_string(),
+            config,
+        },
+    }
+}
+
+/// Indent every line of `xml` by `depth` tabs, converting the builders'
+/// four-space indentation to tabs to match Alfred's own files
+///
+/// Continuation lines of a multi-line `<string>` (e.g. a script) are copied
+/// verbatim so the value is unchanged; escaped content can't contain a raw
+/// `</string>`, so the closing tag reliably ends the value.
+fn indent(xml: &str, depth: usize) -> String {
+    let prefix = "\t".repeat(depth);
+    let mut out = String::new();
+    let mut in_string = false;
+    for line in xml.lines() {
+        if in_string {
+            out.push_str(line);
+        } else {
+            let trimmed = line.trim_start_matches(' ');
+            let spaces = line.len() - trimmed.len();
+            out.push_str(&prefix);
+            out.push_str(&"\t".repeat(spaces / 4));
+            out.push_str(trimmed);
+            in_string = trimmed.starts_with("<string>");
+        }
+        if line.contains("</string>") {
+            in_string = false;
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// `file://` URL for an absolute path, percent-encoding reserved characters
+fn file_url(path: &Path) -> String {
+    format!("file://{}", percent_encode_path(path))
+}
+
+/// `path` with every byte outside the unreserved set and `/` percent-encoded
+fn percent_encode_path(path: &Path) -> String {
+    let mut url = String::new();
+    for byte in path.to_string_lossy().bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' => {
+                url.push(byte as char)
+            }
+            _ => url.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    url
+}
+
+/// Path of a `file://` URL, undoing [`file_url`]'s percent-encoding
+fn file_url_path(url: &str) -> Option<PathBuf> {
+    let encoded = url.strip_prefix("file://")?.as_bytes();
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut i = 0;
+    while i < encoded.len() {
+        let escaped = (encoded[i] == b'%')
+            .then(|| encoded.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(byte) => {
+                bytes.push(byte);
+                i += 3;
+            }
+            None => {
+                bytes.push(encoded[i]);
+                i += 1;
+            }
+        }
+    }
+    Some(PathBuf::from(String::from_utf8_lossy(&bytes).into_owned()))
+}
+
+/// Helper to escape XML special characters
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+// ============================================================================
+// Utilities
+// ============================================================================
+
+/// Expand a leading `~` and `$VAR`/`${VAR}` environment variables
+///
+/// `~` and `~/...` resolve against `HOME`; `~user` is not supported and is
+/// left as-is, as are unset variables. Other paths are returned unchanged.
+pub fn expand_path(path: &str) -> PathBuf {
+    expand_path_with(path, dirs_home())
+}
+
+/// [`expand_path`] resolving `~` against a given home directory
+fn expand_path_with(path: &str, home: Option<PathBuf>) -> PathBuf {
+    let path = expand_env_vars(path);
+    match home {
+        Some(home) if path == "~" => home,
+        Some(home) if path.starts_with("~/") => home.join(&path[2..]),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Like [`expand_path`], but fails with [`Error::MissingEnv`] when the path
+/// starts with `~` and `HOME` is unset, rather than keeping the literal `~`
+pub fn expand_path_checked(path: &str) -> Result<PathBuf, Error> {
+    expand_path_checked_with(path, dirs_home())
+}
+
+/// [`expand_path_checked`] with a given home directory (None when unset)
+fn expand_path_checked_with(path: &str, home: Option<PathBuf>) -> Result<PathBuf, Error> {
+    let expanded = expand_env_vars(path);
+    if (expanded == "~" || expanded.starts_with("~/")) && home.is_none() {
+        return Err(Error::MissingEnv("HOME"));
+    }
+    Ok(expand_path_with(path, home))
+}
+
+/// Replace `$NAME` and `${NAME}` with the variable's value, leaving unset
+/// variables (and a `$` not followed by a name) untouched
+fn expand_env_vars(s: &str) -> String {
+    let is_name_char = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(pos) = rest.find('$') {
+        out.push_str(&rest[..pos]);
+        let after = &rest[pos + 1..];
+        let (name, consumed) = match after.strip_prefix('{') {
+            Some(braced) => match braced.find('}') {
+                Some(end) => (&braced[..end], end + 2),
+                None => ("", 0),
+            },
+            None => {
+                let end = after.find(|c: char| !is_name_char(c)).unwrap_or(after.len());
+                (&after[..end], end)
+            }
+        };
+        let value = (!name.is_empty() && name.chars().all(is_name_char))
+            .then(|| std::env::var(name).ok())
+            .flatten();
+        match value {
+            Some(value) => {
+                out.push_str(&value);
+                rest = &after[consumed..];
+            }
+            None => {
+                out.push('$');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn dirs_home() -> Option<PathBuf> {
+    std::env::var("HOME").ok().map(PathBuf::from)
+}
+
+/// URL opening `path` in an editor via its URL scheme
+///
+/// `scheme` is the editor's scheme name such as `vscode` or `cursor` (a
+/// trailing `://` is ignored), giving e.g. `cursor://file/Users/me/code/flow`.
+/// The path is percent-encoded like a `file://` URL.
+pub fn editor_url(scheme: &str, path: &Path) -> String {
+    let scheme = scheme.trim().trim_end_matches("://");
+    format!("{}://file{}", scheme, percent_encode_path(path))
+}
+
+/// Shorten `s` to at most `max_chars` characters by replacing its middle
+/// with `…`, keeping the start and end
+pub fn truncate_middle(s: &str, max_chars: usize) -> String {
+    let chars: Vec<char> = s.chars().collect();
+    if chars.len() <= max_chars {
+        return s.to_string();
+    }
+    if max_chars == 0 {
+        return String::new();
+    }
+    let keep = max_chars - 1;
+    let head = keep / 2;
+    let tail = keep - head;
+    let mut out: String = chars[..head].iter().collect();
+    out.push('…');
+    out.extend(&chars[chars.len() - tail..]);
+    out
+}
+
+/// Shorten a `/`-separated path to at most `max_chars` characters, always
+/// keeping the last two segments
+///
+/// Leading segments are kept while they fit and the rest collapse to `…`,
+/// e.g. `~/code/org/team/project/flow` becomes `~/code/…/project/flow`. If
+/// even `…/parent/name` is too long, that is shortened with
+/// [`truncate_middle`].
+pub fn truncate_path(path: &str, max_chars: usize) -> String {
+    if path.chars().count() <= max_chars {
+        return path.to_string();
+    }
+    let segments: Vec<&str> = path.split('/').collect();
+    if segments.len() <= 2 {
+        return truncate_middle(path, max_chars);
+    }
+
+    let tail = segments[segments.len() - 2..].join("/");
+    let mut best = format!("…/{}", tail);
+    if best.chars().count() > max_chars {
+        return truncate_middle(&best, max_chars);
+    }
+    for end in 1..segments.len() - 2 {
+        let candidate = format!("{}/…/{}", segments[..end].join("/"), tail);
+        if candidate.chars().count() > max_chars {
+            break;
+        }
+        best = candidate;
+    }
+    best
+}
+
+/// Tab `autocomplete` for `text` naming `path`, e.g. a repo's display path
+///
+/// Directories get a trailing `/` so Tab leaves the query ready to go one
+/// level deeper, as in a file browser; files and missing paths are unchanged.
+pub fn autocomplete_for_path(text: &str, path: &Path) -> String {
+    if path.is_dir() && !text.ends_with('/') {
+        format!("{}/", text)
+    } else {
+        text.to_string()
+    }
+}
+
+/// Quote `s` as a single POSIX shell word
+///
+/// Wraps in single quotes, writing embedded quotes as `'\''`. Words made only
+/// of safe characters (`A-Za-z0-9` and `_-./=:@%+,`) are returned as-is.
+pub fn shell_quote(s: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "_-./=:@%+,".contains(c);
+    if !s.is_empty() && s.chars().all(safe) {
+        return s.to_string();
+    }
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+/// How long ago (or, for future times, how far ahead) a unix timestamp is
+///
+/// Gives `just now` within a minute, otherwise e.g. `5m ago`, `3h ago`,
+/// `2d ago`, `3w ago`, `4mo ago`, `2y ago`, or `in 5m` for a future time.
+pub fn format_relative_time(unix_timestamp: i64) -> String {
+    format_relative_time_at(unix_timestamp, chrono::Utc::now().timestamp())
+}
+
+fn format_relative_time_at(unix_timestamp: i64, now: i64) -> String {
+    let diff = now - unix_timestamp;
+    if diff.abs() < 60 {
+        return "just now".to_string();
+    }
+    let span = format_relative_duration(std::time::Duration::from_secs(diff.unsigned_abs()));
+    if diff < 0 {
+        format!("in {}", span)
+    } else {
+        format!("{} ago", span)
+    }
+}
+
+/// Compact length of a duration in its largest whole unit: `45s`, `5m`,
+/// `3h`, `2d`, `3w`, `4mo` (30-day months) or `2y` (365-day years)
+pub fn format_relative_duration(duration: std::time::Duration) -> String {
+    const MINUTE: u64 = 60;
+    const HOUR: u64 = 60 * MINUTE;
+    const DAY: u64 = 24 * HOUR;
+    const WEEK: u64 = 7 * DAY;
+    const MONTH: u64 = 30 * DAY;
+    const YEAR: u64 = 365 * DAY;
+
+    let secs = duration.as_secs();
+    if secs < MINUTE {
+        format!("{}s", secs)
+    } else if secs < HOUR {
+        format!("{}m", secs / MINUTE)
+    } else if secs < DAY {
+        format!("{}h", secs / HOUR)
+    } else if secs < WEEK {
+        format!("{}d", secs / DAY)
+    } else if secs < MONTH {
+        format!("{}w", secs / WEEK)
+    } else if secs < YEAR {
+        format!("{}mo", secs / MONTH)
+    } else {
+        format!("{}y", secs / YEAR)
+    }
+}
+
+/// Write a timestamped line to stderr, which Alfred shows in its debugger
+///
+/// Never write diagnostics to stdout from a Script Filter: it would corrupt
+/// the JSON Alfred reads there.
+pub fn log(msg: &str) {
+    let _ = write_log(&mut std::io::stderr(), msg);
+}
+
+/// Like [`log`], but only while Alfred's debugger is open (`alfred_debug` is set)
+pub fn debug(msg: &str) {
+    let _ = write_debug(&mut std::io::stderr(), msg, debug_enabled());
+}
+
+fn write_debug(out: &mut impl std::io::Write, msg: &str, enabled: bool) -> std::io::Result<()> {
+    if enabled {
+        write_log(out, msg)?;
+    }
+    Ok(())
+}
+
+fn debug_enabled() -> bool {
+    debug_enabled_from(std::env::var("alfred_debug").ok().as_deref())
+}
+
+/// Whether an `alfred_debug` value turns debug output on
+fn debug_enabled_from(value: Option<&str>) -> bool {
+    value.is_some_and(|v| !v.is_empty() && v != "0")
+}
+
+fn write_log(out: &mut impl std::io::Write, msg: &str) -> std::io::Result<()> {
+    writeln!(out, "[{}] {}", chrono::Local::now().format("%H:%M:%S"), msg)
+}
+
+/// Get environment variable set by Alfred
+pub fn env(name: &str) -> Option<String> {
+    std::env::var(format!("alfred_{}", name)).ok()
+}
+
+/// Get a user-defined workflow variable (exported without the `alfred_` prefix)
+pub fn workflow_var(name: &str) -> Option<String> {
+    std::env::var(name).ok()
+}
+
+/// Workflow variable as a bool: `1`/`true`/`yes`/`on` or `0`/`false`/`no`/`off`/empty
+pub fn workflow_var_bool(name: &str) -> Option<bool> {
+    match workflow_var(name)?.trim().to_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Some(true),
+        "0" | "false" | "no" | "off" | "" => Some(false),
+        _ => None,
+    }
+}
+
+/// Workflow variable as an integer
+pub fn workflow_var_int(name: &str) -> Option<i64> {
+    workflow_var(name)?.trim().parse().ok()
+}
+
+/// Workflow variable as a path, with `~` expanded
+pub fn workflow_var_path(name: &str) -> Option<PathBuf> {
+    workflow_var(name)
+        .filter(|v| !v.trim().is_empty())
+        .map(|v| expand_path(v.trim()))
+}
+
+/// Check if running inside Alfred
+pub fn in_alfred() -> bool {
+    std::env::var("alfred_version").is_ok()
+}
+
+/// Whether the current Alfred theme has a dark background
+///
+/// Parsed from `alfred_theme_background`, e.g. `rgba(30,30,30,0.95)`; None
+/// outside Alfred or when the value isn't recognised.
+pub fn theme_is_dark() -> Option<bool> {
+    std::env::var("alfred_theme_background")
+        .ok()
+        .and_then(|value| background_is_dark(&value))
+}
+
+/// Whether an `rgba(r,g,b,a)` color is dark (perceived luminance below half)
+fn background_is_dark(value: &str) -> Option<bool> {
+    let inner = value
+        .trim()
+        .strip_prefix("rgba(")
+        .or_else(|| value.trim().strip_prefix("rgb("))?
+        .strip_suffix(')')?;
+    let channels: Vec<f64> = inner
+        .split(',')
+        .take(3)
+        .map(|c| c.trim().parse::<f64>().ok())
+        .collect::<Option<_>>()?;
+    let [r, g, b] = channels[..] else {
+        return None;
+    };
+    Some(0.299 * r + 0.587 * g + 0.114 * b < 127.5)
+}
+
+/// Get workflow bundle ID from environment
+pub fn bundle_id() -> Option<String> {
+    std::env::var("alfred_workflow_bundleid").ok()
+}
+
+/// Get workflow data directory
+pub fn data_dir() -> Option<PathBuf> {
+    std::env::var("alfred_workflow_data").ok().map(PathBuf::from)
+}
+
+/// Get workflow cache directory
+pub fn cache_dir() -> Option<PathBuf> {
+    std::env::var("alfred_workflow_cache")
+        .ok()
+        .map(PathBuf::from)
+}
+
+/// Everything Alfred exports to a workflow script, read once
+///
+/// Fields are None (or `debug` false) outside Alfred. Construct one directly
+/// in tests instead of setting `alfred_*` variables.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AlfredEnv {
+    /// Alfred version, e.g. `5.5`
+    pub version: Option<String>,
+    pub bundle_id: Option<String>,
+    pub workflow_name: Option<String>,
+    pub workflow_version: Option<String>,
+    /// See [`data_dir`]
+    pub data_dir: Option<PathBuf>,
+    /// See [`cache_dir`]
+    pub cache_dir: Option<PathBuf>,
+    /// The `Alfred.alfredpreferences` bundle in use
+    pub preferences: Option<PathBuf>,
+    /// Current theme id, e.g. `alfred.theme.yosemite`
+    pub theme: Option<String>,
+    /// Whether the workflow debugger is open
+    pub debug: bool,
+}
+
+impl AlfredEnv {
+    /// Read the `alfred_*` environment variables
+    pub fn from_env() -> Self {
+        Self::from_vars(|name| std::env::var(name).ok())
+    }
+
+    fn from_vars(get: impl Fn(&str) -> Option<String>) -> Self {
+        let get = |name: &str| get(name).filter(|v| !v.is_empty());
+        Self {
+            version: get("alfred_version"),
+            bundle_id: get("alfred_workflow_bundleid"),
+            workflow_name: get("alfred_workflow_name"),
+            workflow_version: get("alfred_workflow_version"),
+            data_dir: get("alfred_workflow_data").map(PathBuf::from),
+            cache_dir: get("alfred_workflow_cache").map(PathBuf::from),
+            preferences: get("alfred_preferences").map(PathBuf::from),
+            theme: get("alfred_theme"),
+            debug: get("alfred_debug").is_some_and(|v| v != "0"),
+        }
+    }
+
+    /// Whether the script is running inside Alfred (see [`in_alfred`])
+    pub fn in_alfred(&self) -> bool {
+        self.version.is_some()
+    }
+}
+
+/// Data directory Alfred assigns to `bundle_id`, whether or not it exists
+///
+/// Unlike [`data_dir`], this works outside Alfred (e.g. from a terminal),
+/// since Alfred derives the path from the bundle id. None if `HOME` is unset.
+pub fn workflow_data_dir(bundle_id: &str) -> Option<PathBuf> {
+    Some(workflow_data_dir_in(&dirs_home()?, bundle_id))
+}
+
+/// Cache directory Alfred assigns to `bundle_id`, whether or not it exists
+///
+/// The counterpart of [`workflow_data_dir`] for [`cache_dir`].
+pub fn workflow_cache_dir(bundle_id: &str) -> Option<PathBuf> {
+    Some(workflow_cache_dir_in(&dirs_home()?, bundle_id))
+}
+
+fn workflow_data_dir_in(home: &Path, bundle_id: &str) -> PathBuf {
+    home.join("Library/Application Support/Alfred/Workflow Data").join(bundle_id)
+}
+
+fn workflow_cache_dir_in(home: &Path, bundle_id: &str) -> PathBuf {
+    home.join("Library/Caches/com.runningwithcrayons.Alfred/Workflow Data").join(bundle_id)
+}
+
+// ============================================================================
+// Persistence
+// ============================================================================
+
+/// Save `value` as JSON to `data_dir()/<name>.json`
+pub fn save_data<T: Serialize>(name: &str, value: &T) -> Result<(), Error> {
+    let dir = data_dir().ok_or(Error::MissingEnv("alfred_workflow_data"))?;
+    save_json_in(&dir, name, value)
+}
+
+/// Load `data_dir()/<name>.json`, if present and parseable
+pub fn load_data<T: serde::de::DeserializeOwned>(name: &str) -> Option<T> {
+    load_json_in(&data_dir()?, name)
+}
+
+/// Save `value` as JSON to `cache_dir()/<name>.json`
+pub fn save_cache<T: Serialize>(name: &str, value: &T) -> Result<(), Error> {
+    let dir = cache_dir().ok_or(Error::MissingEnv("alfred_workflow_cache"))?;
+    save_json_in(&dir, name, value)
+}
+
+/// Load `cache_dir()/<name>.json`, if present and parseable
+pub fn load_cache<T: serde::de::DeserializeOwned>(name: &str) -> Option<T> {
+    load_json_in(&cache_dir()?, name)
+}
+
+fn save_json_in<T: Serialize>(dir: &Path, name: &str, value: &T) -> Result<(), Error> {
+    let data = serde_json::to_vec(value).map_err(|e| Error::Io(e.into()))?;
+    write_atomic(&dir.join(format!("{}.json", name)), &data)
+}
+
+fn load_json_in<T: serde::de::DeserializeOwned>(dir: &Path, name: &str) -> Option<T> {
+    let data = fs::read(dir.join(format!("{}.json", name))).ok()?;
+    serde_json::from_slice(&data).ok()
+}
+
+/// Write a file atomically: write a sibling temp file, then rename over `path`
+///
+/// Readers see either the old or the new contents, never a partial write,
+/// even if the process is killed mid-write. Parent directories are created.
+/// Concurrent writers each use their own temp file; the last rename wins.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<(), Error> {
+    use std::io::Write;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Distinguishes temp files of threads writing the same path
+    static WRITE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let parent = path
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    fs::create_dir_all(parent)?;
+
+    let file_name = path.file_name().ok_or_else(|| Error::NotFound(path.to_path_buf()))?;
+    let tmp = parent.join(format!(
+        ".{}.tmp-{}-{}",
+        file_name.to_string_lossy(),
+        std::process::id(),
+        WRITE_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let result = (|| -> std::io::Result<()> {
+        let mut file = fs::File::create(&tmp)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        fs::rename(&tmp, path)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result.map_err(Error::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Fresh, empty scratch directory for filesystem fixtures
+    pub(crate) fn fixture_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("flow-alfred-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_pretty_json_matches_compact() {
+        let output = Output::new(vec![Item::new("flow", "~/code/flow").cmd_mod("/a", "A")])
+            .rerun(1.0)
+            .variable("mode", "code");
+        let pretty = output.to_json_pretty();
+        assert!(pretty.contains("\n  \"items\": ["));
+        let pretty: serde_json::Value = serde_json::from_str(&pretty).unwrap();
+        let compact: serde_json::Value = serde_json::from_str(&output.to_json()).unwrap();
+        assert_eq!(pretty, compact);
+    }
+
+    #[test]
+    fn test_icon_for_extension() {
+        assert_eq!(Icon::for_extension("rs"), Some(Icon::filetype("public.source-code")));
+        assert_eq!(
+            Icon::for_extension("MD"),
+            Some(Icon::filetype("net.daringfireball.markdown"))
+        );
+        assert_eq!(Icon::for_extension("pdf"), Some(Icon::filetype("com.adobe.pdf")));
+        assert_eq!(Icon::for_extension("xyz"), None);
+
+        let makefile = Path::new("/code/flow/Makefile");
+        assert_eq!(Icon::for_path(makefile), Icon::fileicon("/code/flow/Makefile"));
+    }
+
+    #[test]
+    fn test_item_validate() {
+        let file = Item::from_path(Path::new("/tmp/notes.md"));
+        assert_eq!(file.validate(), Ok(()));
+        assert!(Output::try_new(vec![file, Item::title_only("ok")]).is_ok());
+
+        let mut missing_arg = Item::title_only("notes.md").file_type_skipcheck();
+        assert_eq!(
+            missing_arg.validate(),
+            Err(vec!["type \"file:skipcheck\" needs an arg path".to_string()])
+        );
+        missing_arg.arg = Some(String::new());
+        assert!(missing_arg.validate().is_err());
+
+        let notice = Item::title_only("No results").valid(false).quicklook("https://example.com");
+        let errors = Output::try_new(vec![Item::title_only("ok"), notice]).unwrap_err();
+        assert_eq!(errors, [r#"item 1 ("No results"): quicklookurl is set on an invalid item"#]);
+    }
+
+    #[test]
+    fn test_file_type_skipcheck() {
+        let item = Item::title_only("flow").file_type_skipcheck();
+        assert_eq!(item.item_type.as_deref(), Some("file:skipcheck"));
+        assert!(serde_json::to_string(&item).unwrap().contains(r#""type":"file:skipcheck""#));
+    }
+
+    #[test]
+    fn test_item_json_round_trip() {
+        let item = Item::new("flow", "~/code/flow")
+            .uid("flow")
+            .arg("/Users/me/code/flow")
+            .icon(Icon::fileicon("/Users/me/code/flow"))
+            .match_field("flow code")
+            .file_type()
+            .autocomplete("flow")
+            .copy_text("copy")
+            .largetype("large")
+            .cmd_mod("/other", "Reveal")
+            .quicklook("https://example.com");
+        let json = serde_json::to_string(&item).unwrap();
+        assert!(json.contains(r#""match":"flow code""#));
+        assert_eq!(serde_json::from_str::<Item>(&json).unwrap(), item);
+
+        // Alfred treats a response without items as an empty list
+        let output: Output = serde_json::from_str(r#"{"rerun": 1}"#).unwrap();
+        assert!(output.items.is_empty());
+        assert_eq!(output.rerun, Some(1.0));
+    }
+
+    #[test]
+    fn test_item_builder() {
+        let item = Item::new("Title", "Subtitle")
+            .arg("/path")
+            .uid("123")
+            .valid(true);
+
+        assert_eq!(item.title, "Title");
+        assert_eq!(item.subtitle, Some("Subtitle".to_string()));
+        assert_eq!(item.arg, Some("/path".to_string()));
+        assert_eq!(item.uid, Some("123".to_string()));
+        assert_eq!(item.valid, Some(true));
+    }
+
+    #[test]
+    fn test_item_subtitle_builders() {
+        let json = serde_json::to_value(Item::title_only("Title").subtitle("x")).unwrap();
+        assert_eq!(json["subtitle"], "x");
+
+        let json = serde_json::to_value(Item::new("Title", "Subtitle").no_subtitle()).unwrap();
+        assert!(json.get("subtitle").is_none());
+
+        let item = Item::title_only("Title").subtitle_opt(Some("x"));
+        assert_eq!(item.subtitle.as_deref(), Some("x"));
+        let item = Item::new("Title", "Subtitle").subtitle_opt(None::<String>);
+        assert_eq!(item.subtitle, None);
+    }
+
+    #[test]
+    fn test_mod_subtitle_only() {
+        let item = Item::new("Title", "Subtitle")
+            .arg("/path")
+            .mod_subtitle(ModKey::CTRL, "Open in new window");
+
+        let ctrl = item.mods.as_ref().and_then(|m| m.get(ModKey::CTRL)).unwrap();
+        assert_eq!(ctrl.subtitle.as_deref(), Some("Open in new window"));
+        assert_eq!(ctrl.arg, None);
+        assert_eq!(ctrl.valid, None);
+
+        // Adding a subtitle to an existing modifier keeps its arg
+        let item = Item::new("Title", "Subtitle")
+            .cmd_mod("/other", "Paste path")
+            .mod_subtitle(ModKey::CMD, "Paste full path");
+        let cmd = item.mods.as_ref().and_then(|m| m.get(ModKey::CMD)).unwrap();
+        assert_eq!(cmd.arg.as_deref(), Some("/other"));
+        assert_eq!(cmd.subtitle.as_deref(), Some("Paste full path"));
+    }
+
+    #[test]
+    fn test_config_parse() {
+        let config = Config::parse(
+            r#"
+exclude = ["~/code/archive"]
+code_roots = ["~/code", "~/work"]
+
+[discovery]
+max_depth = 3
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.exclude, ["~/code/archive"]);
+        assert_eq!(config.code_roots, ["~/code", "~/work"]);
+        assert_eq!(config.repos_roots, ["~/repos"]);
+        assert_eq!(config.discovery.max_depth, Some(3));
+
+        assert!(Config::parse("code_roots = 1").is_err());
+    }
+
+    #[test]
+    fn test_config_default_fallback() {
+        let dir = fixture_dir("config-fallback");
+        fs::write(dir.join("broken.toml"), "code_roots = [").unwrap();
+
+        let config = Config::load_from(&[dir.join("missing.toml"), dir.join("broken.toml")]);
+        assert_eq!(config.code_roots, ["~/code"]);
+        assert_eq!(config.repos_roots, ["~/repos"]);
+        assert!(config.exclude.is_empty());
+        assert_eq!(config.discovery.max_depth, None);
+    }
+
+    #[test]
+    fn test_discovery_max_depth() {
+        let root = fixture_dir("discover-depth");
+        fs::create_dir_all(root.join("top/.git")).unwrap();
+        fs::create_dir_all(root.join("org/nested/.git")).unwrap();
+
+        let mut config = Config::default();
+        assert_eq!(discover_repos_with_config(&root, &config).len(), 2);
+
+        config.discovery.max_depth = Some(1);
+        let shallow = discover_repos_with_config(&root, &config);
+        assert_eq!(shallow.len(), 1);
+        assert_eq!(shallow[0].display, "top");
+    }
+
+    #[test]
+    fn test_discovery_max_results_caps_walk() {
+        let root = fixture_dir("discover-max-results");
+        for repo in ["a/deep/nested", "b", "c", "d/child"] {
+            fs::create_dir_all(root.join(repo).join(".git")).unwrap();
+        }
+
+        let mut config = Config::default();
+        config.discovery.max_results = Some(2);
+        config.discovery.traversal = Traversal::BreadthFirst;
+        let shallow = discover_repos_with_config(&root, &config);
+        let names: Vec<&str> = shallow.iter().map(|r| r.display.as_str()).collect();
+        assert_eq!(names, ["b", "c"]);
+
+        // One more repo comes from the next directory in walk order
+        config.discovery.max_results = Some(3);
+        let shallow = discover_repos_with_config(&root, &config);
+        let names: Vec<&str> = shallow.iter().map(|r| r.display.as_str()).collect();
+        assert_eq!(names, ["b", "c", "d/child"]);
+
+        config.discovery.traversal = Traversal::DepthFirst;
+        let deep = discover_repos_with_config(&root, &config);
+        let names: Vec<&str> = deep.iter().map(|r| r.display.as_str()).collect();
+        assert_eq!(names, ["a/deep/nested", "b", "c"]);
+
+        let parsed =
+            Config::parse("[discovery]\nmax_results = 5\ntraversal = \"breadth-first\"").unwrap();
+        assert_eq!(parsed.discovery.max_results, Some(5));
+        assert_eq!(parsed.discovery.traversal, Traversal::BreadthFirst);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_discovery_survives_symlink_loop() {
+        let root = fixture_dir("discover-symlink-loop");
+        fs::create_dir_all(root.join("org/flow/.git")).unwrap();
+        // org/loop points back at root, and a second link reaches the same repo
+        std::os::unix::fs::symlink(&root, root.join("org/loop")).unwrap();
+        std::os::unix::fs::symlink(root.join("org/flow"), root.join("flow-link")).unwrap();
+
+        let found = discover_repos_with_config(&root, &Config::default());
+        assert_eq!(found.len(), 1);
+        assert!(found[0].display == "org/flow" || found[0].display == "flow-link");
+    }
+
+    #[test]
+    fn test_discovery_extra_skip_dirs() {
+        let root = fixture_dir("discover-extra-skips");
+        fs::create_dir_all(root.join("flow/.git")).unwrap();
+        fs::create_dir_all(root.join("skip-me-archive/old/.git")).unwrap();
+        fs::create_dir_all(root.join("node_modules/dep/.git")).unwrap();
+        let config = Config::default();
+        assert_eq!(discover_repos_skipping(&root, &config, &HashSet::new()).len(), 2);
+
+        let extra = extra_skip_dirs_from(Some(" skip-me-archive , ,other"));
+        assert_eq!(extra, HashSet::from(["skip-me-archive".to_string(), "other".to_string()]));
+        assert!(extra_skip_dirs_from(None).is_empty());
+        assert!(extra_skip_dirs_from(Some(" , ")).is_empty());
+
+        let found = discover_repos_skipping(&root, &config, &extra);
+        // Built-in skips still apply alongside the extra ones
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].display, "flow");
+    }
+
+    #[test]
+    fn test_with_default_arg_or_invalid() {
+        let argless = Item::new("Title", "Subtitle").with_default_arg_or_invalid();
+        assert_eq!(argless.valid, Some(false));
+
+        let with_arg = Item::new("Title", "Subtitle").arg("/path").with_default_arg_or_invalid();
+        assert_eq!(with_arg.valid, None);
+    }
+
+    #[test]
+    fn test_quicklook_file_url() {
+        let home = dirs_home().unwrap();
+        let item = Item::title_only("foo").quicklook_file("~/flow-alfred-missing-foo");
+        assert_eq!(
+            item.quicklookurl,
+            Some(format!("file://{}/flow-alfred-missing-foo", home.to_string_lossy()))
+        );
+
+        let item = Item::title_only("x").quicklook_file("/tmp/flow alfred missing/a#b");
+        assert_eq!(item.quicklookurl.as_deref(), Some("file:///tmp/flow%20alfred%20missing/a%23b"));
+
+        // Raw URLs are left alone
+        let item = Item::title_only("web").quicklook("https://nikiv.dev");
+        assert_eq!(item.quicklookurl.as_deref(), Some("https://nikiv.dev"));
+    }
+
+    #[test]
+    fn test_copy_text_and_largetype_compose_in_any_order() {
+        let item = Item::title_only("x").largetype("L").copy_text("C");
+        let text = item.text.unwrap();
+        assert_eq!(text.copy.as_deref(), Some("C"));
+        assert_eq!(text.largetype.as_deref(), Some("L"));
+
+        let item = Item::title_only("x").copy_text("C").largetype("L");
+        let text = item.text.unwrap();
+        assert_eq!(text.copy.as_deref(), Some("C"));
+        assert_eq!(text.largetype.as_deref(), Some("L"));
+    }
+
+    #[test]
+    fn test_auto_match_expands_separators() {
+        let item = Item::title_only("flow-code").auto_match(&["nikiv/flow"]);
+        let field = item.match_field.unwrap();
+        assert_eq!(field, "flow-code flow code nikiv/flow nikiv flow");
+        assert!(field.contains("flow-code"));
+        assert!(field.contains("flow code"));
+    }
+
+    #[test]
+    fn test_fuzzy_match_indices_and_highlight() {
+        let indices = fuzzy_match_indices("FLc", "nikiv/flow-code").unwrap();
+        assert_eq!(indices, [6, 7, 11]);
+        assert_eq!(highlight_matches("nikiv/flow-code", &indices), "nikiv/[fl]ow-[c]ode");
+        assert_eq!(complete_to_boundary("nikiv/flow-code", &indices), "nikiv/flow-code");
+        assert_eq!(complete_to_boundary("nikiv/flow-code", &[6, 7]), "nikiv/flow");
+        assert_eq!(fuzzy_match_indices("zz", "flow"), None);
+    }
+
+    #[test]
+    fn test_fuzzy_sort_breaks_ties_deterministically() {
+        let expected = ["ab", "ac", "abc", "abd", "abcd"];
+        for input in [
+            vec!["abcd", "abd", "abc", "ac", "ab"],
+            vec!["abc", "ab", "abcd", "ac", "abd"],
+        ] {
+            let mut items = input;
+            // Every target scores the same for a single start-of-string match
+            fuzzy_sort(&mut items, "a", |s| s);
+            assert_eq!(items, expected);
+        }
+    }
+
+    #[test]
+    fn test_fuzzy_filter_sort_drops_non_matches() {
+        let items = vec!["xyz", "flow-code", "fc", "forecast"];
+        let result = fuzzy_filter_sort(items, "fc", |s| s);
+        let names: Vec<&str> = result.iter().map(|(s, _)| *s).collect();
+        assert_eq!(names, ["flow-code", "fc", "forecast"]);
+        assert!(result.iter().all(|(s, score)| *score == fuzzy_score("fc", s)));
+        assert!(result.windows(2).all(|w| w[0].1 >= w[1].1));
+
+        let all = fuzzy_filter_sort(vec!["b", "a"], "", |s| s);
+        assert_eq!(all, [("b", 0), ("a", 0)]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_link_dry_run_matches_link() {
+        let dir = fixture_dir("link-dry-run");
+        let workflows = dir.join("workflows");
+        let source = dir.join("Flow.alfredworkflow");
+        fs::create_dir_all(&workflows).unwrap();
+        fs::create_dir_all(&source).unwrap();
+
+        let plan = plan_link_in(&workflows, &source, "nikiv.dev.flow").unwrap();
+        assert_eq!(plan.dest, workflows.join("nikiv.dev.flow"));
+        assert!(!plan.overwrites);
+        assert!(!plan.dest.is_symlink());
+
+        let dest = link_workflow_in(&workflows, &source, "nikiv.dev.flow").unwrap();
+        assert_eq!(dest, plan.dest);
+        assert_eq!(fs::read_link(&dest).unwrap(), source);
+
+        // Relinking would replace the symlink just created
+        let plan = plan_link_in(&workflows, &source, "nikiv.dev.flow").unwrap();
+        assert!(plan.overwrites);
+
+        fs::create_dir_all(workflows.join("real")).unwrap();
+        assert!(plan_link_in(&workflows, &source, "real").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_remove_workflow_requires_force_for_real_dir() {
+        let workflows = fixture_dir("remove-workflow");
+        let installed = workflows.join("nikiv.dev.flow");
+        fs::create_dir_all(&installed).unwrap();
+        fs::write(installed.join("info.plist"), "").unwrap();
+
+        let err = remove_workflow_in(&workflows, "nikiv.dev.flow", false).unwrap_err();
+        assert!(err.contains("not a dev symlink"), "{}", err);
+        assert!(installed.join("info.plist").exists());
+
+        let err = remove_workflow_in(&workflows, "nikiv.dev.missing", true).unwrap_err();
+        assert_eq!(err, "Workflow not installed: nikiv.dev.missing");
+        assert_eq!(
+            trash_script(Path::new("/w/a \"b\"")),
+            r#"tell application "Finder" to delete POSIX file "/w/a \"b\"""#
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_list_installed_workflows() {
+        let workflows = fixture_dir("list-workflows");
+        let installed = workflows.join("user.workflow.1234");
+        fs::create_dir_all(&installed).unwrap();
+        fs::write(
+            installed.join("info.plist"),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<plist version="1.0"><dict>
+<key>bundleid</key><string>com.example.clipboard</string>
+<key>name</key><string>clipboard tools</string>
+<key>version</key><string>2.1</string>
+</dict></plist>"#,
+        )
+        .unwrap();
+        fs::create_dir_all(workflows.join(".backups/nikiv.dev.flow-20250101000000")).unwrap();
+        fs::create_dir_all(workflows.join("empty")).unwrap();
+        std::os::unix::fs::symlink(
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/workflows/valid"),
+            workflows.join("nikiv.dev.flow"),
+        )
+        .unwrap();
+
+        let found = list_installed_workflows_in(&workflows);
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].name, "clipboard tools");
+        assert_eq!(found[0].bundle_id, "com.example.clipboard");
+        assert_eq!(found[0].version.as_deref(), Some("2.1"));
+        assert!(!found[0].is_symlink);
+        assert_eq!(found[1].name, "Flow");
+        assert_eq!(found[1].bundle_id, "nikiv.dev.flow");
+        assert_eq!(found[1].version, None);
+        assert!(found[1].is_symlink);
+        assert_eq!(found[1].path, workflows.join("nikiv.dev.flow"));
+    }
+
+    #[test]
+    fn test_workflow_data_and_cache_dirs() {
+        let home = Path::new("/Users/me");
+        assert_eq!(
+            workflow_data_dir_in(home, "nikiv.dev.flow"),
+            Path::new("/Users/me/Library/Application Support/Alfred/Workflow Data/nikiv.dev.flow")
+        );
+        assert_eq!(
+            workflow_cache_dir_in(home, "nikiv.dev.flow"),
+            Path::new(
+                "/Users/me/Library/Caches/com.runningwithcrayons.Alfred/Workflow Data/nikiv.dev.flow"
+            )
+        );
+    }
+
+    #[test]
+    fn test_link_backup_restore_round_trip() {
+        let dir = fixture_dir("link-backup");
+        let workflows = dir.join("workflows");
+        let source = dir.join("dev");
+        let installed = workflows.join("nikiv.dev.flow");
+        fs::create_dir_all(&source).unwrap();
+        fs::create_dir_all(&installed).unwrap();
+        fs::write(installed.join("info.plist"), "gallery").unwrap();
+
+        let dest = link_workflow_backup_in(&workflows, &source, "nikiv.dev.flow").unwrap();
+        assert_eq!(fs::read_link(&dest).unwrap(), source);
+        let backups: Vec<_> = fs::read_dir(workflows.join(".backups")).unwrap().collect();
+        assert_eq!(backups.len(), 1);
+
+        let restored = restore_workflow_backup_in(&workflows, "nikiv.dev.flow").unwrap();
+        assert!(!restored.is_symlink());
+        assert_eq!(fs::read_to_string(restored.join("info.plist")).unwrap(), "gallery");
+        assert!(restore_workflow_backup_in(&workflows, "nikiv.dev.flow").is_err());
+    }
+
+    #[test]
+    fn test_diff_plist_reports_changed_config_value() {
+        let plist = |keyword: &str, order_swapped: bool| {
+            let config = if order_swapped {
+                format!("<key>title</key><string>Code</string><key>keyword</key><string>{}</string>", keyword)
+            } else {
+                format!("<key>keyword</key><string>{}</string><key>title</key><string>Code</string>", keyword)
+            };
+            format!(
+                r#"<?xml version="1.0" encoding="UTF-8"?>
+<plist version="1.0"><dict>
+<key>bundleid</key><string>nikiv.dev.flow</string>
+<key>connections</key><dict><key>A</key><array><dict>
+<key>destinationuid</key><string>B</string><key>modifiers</key><integer>0</integer>
+</dict></array></dict>
+<key>objects</key><array><dict>
+<key>config</key><dict>{}</dict>
+<key>type</key><string>alfred.workflow.input.scriptfilter</string>
+<key>uid</key><string>A</string>
+</dict></array>
+</dict></plist>"#,
+                config
+            )
+        };
+
+        let dir = fixture_dir("diff-plist");
+        let installed = dir.join("info.plist");
+        fs::write(&installed, plist("c", true)).unwrap();
+
+        assert!(diff_plist(&plist("c", false), &installed).is_empty());
+        assert_eq!(
+            diff_plist(&plist("code", false), &installed),
+            ["object A (alfred.workflow.input.scriptfilter): config.keyword differs: \"code\" vs \"c\""]
+        );
+    }
+
+    #[test]
+    fn test_validate_workflow_fixture_is_clean() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/workflows/valid");
+        assert_eq!(validate_workflow(&dir), []);
+    }
+
+    #[test]
+    fn test_validate_workflow_reports_problems() {
+        let dir = fixture_dir("validate-workflow");
+        let diagnostics = validate_workflow(&dir);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert!(diagnostics[0].message.starts_with("Missing"));
+
+        let valid = include_str!("../tests/fixtures/workflows/valid/info.plist");
+        let broken = valid
+            .replacen("<string>SCRIPT</string>", "<string>GONE</string>", 1)
+            .replace("${code_root}", "${code_rot}");
+        fs::write(dir.join("info.plist"), broken).unwrap();
+        let messages: Vec<String> = validate_workflow(&dir).iter().map(|d| d.to_string()).collect();
+        assert_eq!(
+            messages,
+            [
+                "error: Connection KW -> GONE references unknown object GONE",
+                "warning: object SCRIPT: script uses undeclared variable code_rot",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scaffold_workflow_is_importable() {
+        let dir = fixture_dir("scaffold-workflow").join("Hello World");
+        let created = scaffold_workflow(&dir, "dev.example.hello", "Hello World").unwrap();
+        let names: Vec<_> = created.iter().map(|p| p.strip_prefix(&dir).unwrap()).collect();
+        assert_eq!(names, [Path::new("info.plist"), Path::new("icon.png"), Path::new("script.sh")]);
+        assert_eq!(validate_workflow(&dir), []);
+        assert!(fs::read(dir.join("icon.png")).unwrap().starts_with(b"\x89PNG"));
+        assert!(is_executable(&dir.join("script.sh")));
+
+        let root = plist::Value::from_file(dir.join("info.plist")).unwrap();
+        let root = root.as_dictionary().unwrap();
+        assert_eq!(root.get("bundleid").unwrap().as_string(), Some("dev.example.hello"));
+        assert_eq!(root.get("name").unwrap().as_string(), Some("Hello World"));
+        assert!(scaffold_workflow(&dir, "dev.example.hello", "Hello World").is_err());
+
+        let dir = dir.with_file_name("with-filter");
+        let created = scaffold_workflow_with(&dir, "dev.example.filter", "Hello World", true).unwrap();
+        assert!(created.contains(&dir.join("filter.sh")));
+        assert_eq!(validate_workflow(&dir), []);
+        let root = plist::Value::from_file(dir.join("info.plist")).unwrap();
+        let root = root.as_dictionary().unwrap();
+        let objects = root.get("objects").unwrap().as_array().unwrap();
+        let config = objects[0].as_dictionary().unwrap().get("config").unwrap();
+        let keyword = config.as_dictionary().unwrap().get("keyword").unwrap();
+        assert_eq!(keyword.as_string(), Some("helloworld"));
+        let connections = root.get("connections").unwrap().as_dictionary().unwrap();
+        assert!(connections.contains_key("FILTER"));
+    }
+
+    #[test]
+    fn test_pack_dry_run_lists_files_without_writing() {
+        let dir = fixture_dir("pack-dry-run");
+        fs::create_dir_all(dir.join("icons")).unwrap();
+        fs::write(dir.join("info.plist"), "").unwrap();
+        fs::write(dir.join("icons/code.png"), "").unwrap();
+        fs::write(dir.join(".env"), "").unwrap();
+
+        let files = pack_workflow_dry_run(&dir).unwrap();
+        assert_eq!(
+            files,
+            [PathBuf::from(".env"), PathBuf::from("icons/code.png"), PathBuf::from("info.plist")]
+        );
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 3);
+        assert!(pack_workflow_dry_run(&dir.join("missing")).is_err());
+    }
+
+    #[test]
+    fn test_editor_url_from_scheme() {
+        let path = Path::new("/Users/me/code/flow");
+        assert_eq!(editor_url("cursor", path), "cursor://file/Users/me/code/flow");
+        assert_eq!(editor_url("vscode://", path), "vscode://file/Users/me/code/flow");
+        assert_eq!(
+            editor_url("cursor", Path::new("/Users/me/My Notes/a#b.md")),
+            "cursor://file/Users/me/My%20Notes/a%23b.md"
+        );
+    }
+
+    #[test]
+    fn test_output_extend_and_merge() {
+        let output = Output::new(vec![Item::title_only("repo")])
+            .extend(vec![Item::title_only("session"), Item::title_only("bookmark")]);
+        let titles: Vec<&str> = output.items.iter().map(|i| i.title.as_str()).collect();
+        assert_eq!(titles, ["repo", "session", "bookmark"]);
+
+        let first = Output::new(vec![Item::title_only("a")])
+            .rerun(1.0)
+            .variable("source", "repos")
+            .variable("query", "fl");
+        let merged = first.merge(Output::new(vec![Item::title_only("b")]).variable("source", "sessions"));
+        assert_eq!(merged.items.len(), 2);
+        assert_eq!(merged.rerun, Some(1.0));
+        assert_eq!(merged.variables["source"], "sessions");
+        assert_eq!(merged.variables["query"], "fl");
+
+        let merged = merged.merge(Output::empty().rerun(2.0));
+        assert_eq!(merged.rerun, Some(2.0));
+        assert!(merged.to_json().contains(r#""variables":{"query":"fl","source":"sessions"}"#));
+    }
+
+    #[test]
+    fn test_output_dedup_by_uid() {
+        let output = Output::new(vec![
+            Item::title_only("repo flow").uid("/code/flow"),
+            Item::title_only("no uid"),
+            Item::title_only("session flow").uid("/code/flow"),
+            Item::title_only("no uid"),
+            Item::title_only("alfred").uid("/code/alfred"),
+        ])
+        .dedup_by_uid();
+        let titles: Vec<&str> = output.items.iter().map(|i| i.title.as_str()).collect();
+        assert_eq!(titles, ["repo flow", "no uid", "no uid", "alfred"]);
+    }
+
+    #[test]
+    fn test_items_from_tsv_lines() {
+        let input = "flow\t~/code/flow\t/Users/me/code/flow\n\n\
+                     alfred\talfred workflows\n\
+                     \t\tno title\n\
+                     bare\n\
+                     a\tb\tc\td\n";
+        let items = items_from_lines(input.as_bytes(), parse_tsv_item);
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0].title, "flow");
+        assert_eq!(items[0].subtitle.as_deref(), Some("~/code/flow"));
+        assert_eq!(items[0].arg.as_deref(), Some("/Users/me/code/flow"));
+        assert_eq!(items[1].arg.as_deref(), Some("alfred"));
+        assert_eq!(items[2].title, "bare");
+        assert_eq!(items[2].subtitle, None);
+    }
+
+    #[test]
+    fn test_repo_description_from_readme_and_manifests() {
+        let readme_repo =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/descriptions/readme");
+        assert_eq!(
+            repo_description(&readme_repo).as_deref(),
+            Some("Run project tasks and workflows from one place.")
+        );
+
+        let cargo_repo = fixture_dir("description-cargo");
+        fs::write(
+            cargo_repo.join("Cargo.toml"),
+            include_str!("../tests/fixtures/descriptions/cargo.toml"),
+        )
+        .unwrap();
+        fs::write(cargo_repo.join("README.md"), "# flow\n\nFrom the README\n").unwrap();
+        assert_eq!(
+            repo_description(&cargo_repo).as_deref(),
+            Some("Alfred workflow library with \"fuzzy\" search")
+        );
+
+        let node_repo = fixture_dir("description-node");
+        fs::write(
+            node_repo.join("package.json"),
+            "{\n  \"name\": \"site\",\n  \"description\": \"Personal site\",\n}",
+        )
+        .unwrap();
+        assert_eq!(repo_description(&node_repo).as_deref(), Some("Personal site"));
+        assert_eq!(repo_description(&fixture_dir("description-none")), None);
+    }
+
+    #[test]
+    fn test_print_capped_truncates_with_notice() {
+        let items: Vec<Item> = (0..5).map(|i| Item::title_only(format!("item {}", i))).collect();
+
+        let mut out = Vec::new();
+        Output::new(items.clone()).write_capped(&mut out, 2).unwrap();
+        let printed: Output = serde_json::from_slice(&out).unwrap();
+        let titles: Vec<&str> = printed.items.iter().map(|i| i.title.as_str()).collect();
+        assert_eq!(titles, ["item 0", "item 1", "… 3 more"]);
+        assert_eq!(printed.items[2].valid, Some(false));
+
+        let mut warning = Vec::new();
+        Output::new(items.clone()).write_size_warning(&mut warning).unwrap();
+        Output::new(items.clone()).warn_above(5).write_size_warning(&mut warning).unwrap();
+        assert!(warning.is_empty());
+        Output::new(items).warn_above(4).write_size_warning(&mut warning).unwrap();
+        let warning = String::from_utf8(warning).unwrap();
+        assert!(warning.contains("printing 5 items (more than 4)"), "{}", warning);
+    }
+
+    #[test]
+    fn test_output_top_n_by_score() {
+        let output = Output::new(vec![
+            Item::title_only("Error: no roots").valid(false),
+            Item::title_only("forks/flow-old"),
+            Item::title_only("alfred"),
+            Item::title_only("flow"),
+            Item::title_only("flow-alfred"),
+        ])
+        .top_n_by_score("flow", |item| item.title.as_str(), 2);
+        let titles: Vec<&str> = output.items.iter().map(|i| i.title.as_str()).collect();
+        assert_eq!(titles, ["flow", "flow-alfred"]);
+    }
+
+    #[test]
+    fn test_output_select_first_matching() {
+        let items = vec![
+            Item::title_only("flow-alfred"),
+            Item::title_only("flowy"),
+            Item::title_only("flow"),
+            Item::title_only("flow"),
+            Item::title_only("overflow"),
+        ];
+        let output = Output::new(items.clone()).select_first_matching(|item| item.title == "flow");
+        let titles: Vec<&str> = output.items.iter().map(|i| i.title.as_str()).collect();
+        assert_eq!(titles, ["flow", "flow-alfred", "flowy", "flow", "overflow"]);
+
+        let unchanged = Output::new(items.clone()).select_first_matching(|item| item.title == "x");
+        assert_eq!(unchanged.items, items);
+    }
+
+    #[test]
+    fn test_output_sort_items_by() {
+        let items = vec![
+            Item::title_only("flow-alfred"),
+            Item::title_only("flow"),
+            Item::title_only("alfred"),
+            Item::title_only("code"),
+        ];
+        let titles = |output: &Output| -> Vec<String> {
+            output.items.iter().map(|i| i.title.clone()).collect()
+        };
+
+        let by_len = Output::new(items.clone()).sort_items_by_key(|item| item.title.len());
+        assert_eq!(titles(&by_len), ["flow", "code", "alfred", "flow-alfred"]);
+
+        let longest_first = Output::new(items).sort_items_by(|a, b| {
+            b.title.len().cmp(&a.title.len()).then_with(|| a.title.cmp(&b.title))
+        });
+        assert_eq!(titles(&longest_first), ["flow-alfred", "alfred", "code", "flow"]);
+    }
+
+    #[test]
+    fn test_autocomplete_for_path_marks_directories() {
+        let dir = fixture_dir("autocomplete-for-path");
+        fs::create_dir_all(dir.join("flow")).unwrap();
+        fs::write(dir.join("notes.md"), "").unwrap();
+        assert_eq!(autocomplete_for_path("nikiv/flow", &dir.join("flow")), "nikiv/flow/");
+        assert_eq!(autocomplete_for_path("nikiv/flow/", &dir.join("flow")), "nikiv/flow/");
+        assert_eq!(autocomplete_for_path("notes.md", &dir.join("notes.md")), "notes.md");
+        assert_eq!(autocomplete_for_path("gone", &dir.join("gone")), "gone");
+    }
+
+    #[test]
+    fn test_truncate_middle_and_path() {
+        assert_eq!(truncate_middle("flow-alfred", 20), "flow-alfred");
+        assert_eq!(truncate_middle("flow-alfred", 7), "flo…red");
+        assert_eq!(truncate_middle("flow", 0), "");
+
+        let path = "~/code/org/team/project/flow";
+        assert_eq!(truncate_path(path, 40), path);
+        assert_eq!(truncate_path(path, 22), "~/code/…/project/flow");
+        assert_eq!(truncate_path(path, 16), "~/…/project/flow");
+        assert_eq!(truncate_path(path, 15), "…/project/flow");
+        assert_eq!(truncate_path(path, 8), "…/p…flow");
+        assert!(truncate_path(path, 8).chars().count() <= 8);
+    }
+
+    #[test]
+    fn test_dot_icons_cached_per_color() {
+        let dir = fixture_dir("dot-icons");
+        let red = dot_png_in(&dir, [255, 59, 48]).unwrap();
+        let green = dot_png_in(&dir, [52, 199, 89]).unwrap();
+        assert_ne!(red, green);
+        assert_eq!(red.file_name().unwrap(), "dot-ff3b30.png");
+
+        for path in [&red, &green] {
+            let bytes = fs::read(path).unwrap();
+            assert_eq!(&bytes[..8], b"\x89PNG\r\n\x1a\n");
+        }
+        assert_eq!(dot_png_in(&dir, [255, 59, 48]).unwrap(), red);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_item_from_path() {
+        let item = Item::from_path(Path::new("/Users/me/code/flow/readme.md"));
+        assert_eq!(item.title, "readme.md");
+        assert_eq!(item.subtitle.as_deref(), Some("/Users/me/code/flow"));
+        assert_eq!(item.arg.as_deref(), Some("/Users/me/code/flow/readme.md"));
+        assert_eq!(item.uid.as_deref(), Some("/Users/me/code/flow/readme.md"));
+        assert_eq!(item.item_type.as_deref(), Some("file"));
+        assert_eq!(item.icon, Some(Icon::filetype("net.daringfireball.markdown")));
+        assert_eq!(item.quicklookurl.as_deref(), Some("file:///Users/me/code/flow/readme.md"));
+    }
+
+    #[test]
+    fn test_script_filter_script_file() {
+        let inline = ScriptFilter::new("SF", "code").script("./flow-alfred code \"$1\"");
+        assert_eq!(inline.validate(), Ok(()));
+        assert!(inline.to_plist_object().contains("<key>type</key>\n        <integer>0</integer>"));
+
+        let filter = inline.script_file("scripts/code.sh");
+        assert_eq!(filter.validate(), Ok(()));
+        let plist = filter.to_plist_object();
+        assert!(plist.contains("<key>script</key>\n        <string></string>"));
+        assert!(plist.contains("<key>scriptfile</key>\n        <string>scripts/code.sh</string>"));
+        assert!(plist.contains("<key>type</key>\n        <integer>8</integer>"));
+
+        assert!(filter.script("echo").validate().is_err());
+        assert!(ScriptFilter::new("SF", "code").validate().is_err());
+    }
+
+    #[test]
+    fn test_script_filter_query_options() {
+        let key = |plist: &str, key: &str| -> String {
+            let start = plist.find(&format!("<key>{}</key>", key)).unwrap();
+            plist[start..].lines().nth(1).unwrap().trim().to_string()
+        };
+
+        let default = ScriptFilter::new("SF", "code").script("x").to_plist_object();
+        assert_eq!(key(&default, "escaping"), "<integer>102</integer>");
+        assert_eq!(key(&default, "argumenttrimmode"), "<integer>0</integer>");
+        assert_eq!(key(&default, "argumenttreatemptyqueryasnil"), "<false/>");
+
+        let custom = ScriptFilter::new("SF", "code")
+            .script("x")
+            .escaping(0)
+            .trim_mode(TrimMode::Off)
+            .empty_query_as_nil(true)
+            .to_plist_object();
+        assert_eq!(key(&custom, "escaping"), "<integer>0</integer>");
+        assert_eq!(key(&custom, "argumenttrimmode"), "<integer>1</integer>");
+        assert_eq!(key(&custom, "argumenttreatemptyqueryasnil"), "<true/>");
+    }
+
+    #[test]
+    fn test_workflow_builder_chain() {
+        let plist = WorkflowBuilder::new("nikiv.dev.test", "Test")
+            .object(Keyword::new("KW", "greet").title("Greet"))
+            .object(RunScript::new("RS", "if true; then\n    echo \"hello $1\"\nfi"))
+            .object(Notification::new("NT", "Done"))
+            .connect("KW", "RS")
+            .connect("RS", "NT")
+            .build()
+            .unwrap();
+
+        let value = plist::Value::from_reader(std::io::Cursor::new(plist.as_bytes())).unwrap();
+        let root = value.as_dictionary().unwrap();
+        let connections = root.get("connections").unwrap().as_dictionary().unwrap();
+        assert_eq!(connections.len(), 2);
+        let dest = |source: &str| -> String {
+            let links = connections.get(source).unwrap().as_array().unwrap();
+            assert_eq!(links.len(), 1);
+            let link = links[0].as_dictionary().unwrap();
+            link.get("destinationuid").unwrap().as_string().unwrap().to_string()
+        };
+        assert_eq!(dest("KW"), "RS");
+        assert_eq!(dest("RS"), "NT");
+        assert!(connections.get("NT").is_none());
+
+        let objects = root.get("objects").unwrap().as_array().unwrap();
+        assert_eq!(objects.len(), 3);
+        let script = objects[1].as_dictionary().unwrap().get("config").unwrap();
+        let script = script.as_dictionary().unwrap().get("script").unwrap();
+        assert_eq!(script.as_string(), Some("if true; then\n    echo \"hello $1\"\nfi"));
+
+        // Auto layout puts each step of the chain one column further right
+        let uidata = root.get("uidata").unwrap().as_dictionary().unwrap();
+        let x = |uid: &str| uidata.get(uid).unwrap().as_dictionary().unwrap().get("xpos").unwrap().as_real();
+        assert!(x("KW") < x("RS") && x("RS") < x("NT"));
+    }
+
+    #[test]
+    fn test_parse_workflow_objects() {
+        let path =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/workflows/objects/info.plist");
+        let objects = parse_workflow_objects(&path).unwrap();
+        let uids: Vec<&str> = objects.iter().map(ParsedObject::uid).collect();
+        assert_eq!(uids, ["CODE", "OPEN", "COPY"]);
+
+        let ParsedObject::ScriptFilter(filter) = &objects[0] else {
+            panic!("expected a Script Filter, got {:?}", objects[0]);
+        };
+        assert_eq!(filter.keyword, "code");
+        assert_eq!(filter.title, "Code");
+        assert_eq!(filter.subtitle, "Open a repo in the editor");
+        assert_eq!(filter.running_subtext, "Searching repos…");
+        assert_eq!(filter.script, "./flow-alfred code \"$1\"");
+        assert_eq!(filter.script_file, None);
+        assert_eq!(filter.argument_type, ArgumentType::Optional);
+        assert!(filter.with_space);
+        assert!(filter.validate().is_ok());
+
+        let ParsedObject::OpenFile(open) = &objects[1] else {
+            panic!("expected an Open File action, got {:?}", objects[1]);
+        };
+        assert_eq!(open.open_with.as_deref(), Some("com.todesktop.230313mzl4w4u92"));
+
+        let ParsedObject::Unknown {
+            object_type, config, ..
+        } = &objects[2]
+        else {
+            panic!("expected an unknown object, got {:?}", objects[2]);
+        };
+        assert_eq!(object_type, "alfred.workflow.output.clipboard");
+        assert_eq!(config.get("clipboardtext").unwrap().as_string(), Some("{query}"));
+
+        // Objects rendered by the builders read back the same
+        let hotkey = Hotkey::new("HK").shortcut("cmd+alt+k").unwrap();
+        let plist = WorkflowBuilder::new("nikiv.dev.test", "Test").object(hotkey).build().unwrap();
+        let dir = fixture_dir("parse-workflow-objects");
+        fs::write(dir.join("info.plist"), plist).unwrap();
+        let objects = parse_workflow_objects(&dir.join("info.plist")).unwrap();
+        let ParsedObject::Hotkey(hotkey) = &objects[0] else {
+            panic!("expected a Hotkey, got {:?}", objects[0]);
+        };
+        assert_eq!((hotkey.key_code, hotkey.key_label.as_str()), (40, "K"));
+        assert_eq!(hotkey.modifiers, Some(ModKey::CMD | ModKey::ALT));
+
+        assert!(matches!(
+            parse_workflow_objects(&dir.join("missing.plist")),
+            Err(Error::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_connection_modifier_subtext() {
+        let subtext = |connection: Connection| {
+            let xml = connection.to_plist_dict();
+            let value = plist::Value::from_reader(std::io::Cursor::new(xml.as_bytes())).unwrap();
+            let link = value.as_dictionary().unwrap();
+            link.get("modifiersubtext").unwrap().as_string().unwrap().to_string()
+        };
+        let reveal = Connection::new("SF", "RS")
+            .with_cmd()
+            .with_modifier_subtext("Reveal in Finder & copy");
+        assert_eq!(reveal.modifiers, 1048576);
+        assert_eq!(subtext(reveal), "Reveal in Finder & copy");
+        assert_eq!(subtext(Connection::new("SF", "RS")), "");
+    }
+
+    #[test]
+    fn test_transform_modes() {
+        let mode = |transform: Transform| {
+            let xml = transform.to_plist_object();
+            let value = plist::Value::from_reader(std::io::Cursor::new(xml.as_bytes())).unwrap();
+            let object = value.as_dictionary().unwrap();
+            assert_eq!(
+                object.get("type").unwrap().as_string(),
+                Some("alfred.workflow.utility.transform")
+            );
+            let config = object.get("config").unwrap().as_dictionary().unwrap();
+            config.get("type").unwrap().as_signed_integer().unwrap()
+        };
+        assert_eq!(mode(Transform::new("UP", TransformMode::Uppercase)), 2);
+        assert_eq!(mode(Transform::new("TRIM", TransformMode::TrimWhitespace)), 0);
+        assert_eq!(mode(Transform::new("ABC", TransformMode::StripNonAlphanumeric)), 5);
+    }
+
+    #[test]
+    fn test_conditional_branches_to_labeled_outputs() {
+        let conditional = Conditional::new("IF")
+            .condition(Condition::new("open", "Open"))
+            .condition(
+                Condition::new("^copy ", "Copy")
+                    .match_mode(MatchMode::Regex)
+                    .case_sensitive(true),
+            )
+            .else_label("Search");
+        let copy_output = conditional.output_uid("Copy").unwrap();
+        assert_eq!(conditional.output_uid("Missing"), None);
+
+        let plist = WorkflowBuilder::new("nikiv.dev.test", "Test")
+            .object(conditional)
+            .object(RunScript::new("OPEN", "open \"$1\""))
+            .object(RunScript::new("COPY", "printf %s \"$1\" | pbcopy"))
+            .object(Notification::new("ELSE", "No match"))
+            .connection(Connection::new("IF", "OPEN").from_output("IF-1"))
+            .connection(Connection::new("IF", "COPY").from_output(&copy_output))
+            .connect("IF", "ELSE")
+            .build()
+            .unwrap();
+
+        let value = plist::Value::from_reader(std::io::Cursor::new(plist.as_bytes())).unwrap();
+        let root = value.as_dictionary().unwrap();
+        let objects = root.get("objects").unwrap().as_array().unwrap();
+        let object = objects[0].as_dictionary().unwrap();
+        assert_eq!(
+            object.get("type").unwrap().as_string(),
+            Some("alfred.workflow.utility.conditional")
+        );
+        let config = object.get("config").unwrap().as_dictionary().unwrap();
+        assert_eq!(config.get("elselabel").unwrap().as_string(), Some("Search"));
+        let conditions = config.get("conditions").unwrap().as_array().unwrap();
+        assert_eq!(conditions.len(), 2);
+        let field = |i: usize, key: &str| {
+            conditions[i].as_dictionary().unwrap().get(key).cloned().unwrap()
+        };
+        assert_eq!(field(0, "inputstring").as_string(), Some("{query}"));
+        assert_eq!(field(0, "matchstring").as_string(), Some("open"));
+        assert_eq!(field(0, "outputlabel").as_string(), Some("Open"));
+        assert_eq!(field(0, "uid").as_string(), Some("IF-1"));
+        assert_eq!(field(1, "matchmode").as_signed_integer(), Some(4));
+        assert_eq!(field(1, "matchcasesensitive").as_boolean(), Some(true));
+        assert_eq!(field(1, "uid").as_string(), Some("IF-2"));
+
+        let links = root.get("connections").unwrap().as_dictionary().unwrap();
+        let links = links.get("IF").unwrap().as_array().unwrap();
+        let outputs: Vec<(&str, Option<&str>)> = links
+            .iter()
+            .map(|link| {
+                let link = link.as_dictionary().unwrap();
+                let dest = link.get("destinationuid").unwrap().as_string().unwrap();
+                (dest, link.get("sourceoutputuid").and_then(plist::Value::as_string))
+            })
+            .collect();
+        assert_eq!(outputs, [("OPEN", Some("IF-1")), ("COPY", Some("IF-2")), ("ELSE", None)]);
+
+        assert!(WorkflowBuilder::new("nikiv.dev.test", "Test")
+            .object(Conditional::new("IF"))
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn test_workflow_builder_metadata() {
+        let plist = WorkflowBuilder::new("nikiv.dev.flow", "Flow")
+            .version("1.2.0")
+            .description("Code & sessions <fast>")
+            .created_by("Nikita")
+            .web_address("https://github.com/nikivdev/alfred")
+            .readme("Line one\nLine \"two\"")
+            .variable("code_root", "~/code")
+            .variable("editor_scheme", "cursor")
+            .build()
+            .unwrap();
+        assert!(plist.contains("<string>Code &amp; sessions &lt;fast&gt;</string>"));
+
+        let value = plist::Value::from_reader(std::io::Cursor::new(plist.as_bytes())).unwrap();
+        let root = value.as_dictionary().unwrap();
+        let string = |key: &str| root.get(key).and_then(plist::Value::as_string);
+        assert_eq!(string("bundleid"), Some("nikiv.dev.flow"));
+        assert_eq!(string("name"), Some("Flow"));
+        assert_eq!(string("version"), Some("1.2.0"));
+        assert_eq!(string("description"), Some("Code & sessions <fast>"));
+        assert_eq!(string("createdby"), Some("Nikita"));
+        assert_eq!(string("webaddress"), Some("https://github.com/nikivdev/alfred"));
+        assert_eq!(string("readme"), Some("Line one\nLine \"two\""));
+
+        let variables = root.get("variables").unwrap().as_dictionary().unwrap();
+        assert_eq!(variables.len(), 2);
+        assert_eq!(variables.get("code_root").unwrap().as_string(), Some("~/code"));
+    }
+
+    #[test]
+    fn test_workflow_builder_dont_export() {
+        let plist = WorkflowBuilder::new("nikiv.dev.flow", "Flow")
+            .variable("api_key", "secret")
+            .variable("code_root", "~/code")
+            .dont_export("api_key")
+            .build()
+            .unwrap();
+
+        let value = plist::Value::from_reader(std::io::Cursor::new(plist.as_bytes())).unwrap();
+        let root = value.as_dictionary().unwrap();
+        let hidden: Vec<&str> = root
+            .get("variablesdontexport")
+            .unwrap()
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(plist::Value::as_string)
+            .collect();
+        assert_eq!(hidden, ["api_key"]);
+        let variables = root.get("variables").unwrap().as_dictionary().unwrap();
+        assert_eq!(variables.get("api_key").unwrap().as_string(), Some("secret"));
+
+        let err = WorkflowBuilder::new("nikiv.dev.flow", "Flow").dont_export("token").build();
+        assert_eq!(err.unwrap_err(), "Don't-export variable token is not defined");
+    }
+
+    #[test]
+    fn test_workflow_builder_rejects_unknown_ids() {
+        let err = WorkflowBuilder::new("nikiv.dev.test", "Test")
+            .object(Keyword::new("KW", "greet"))
+            .connect("KW", "MISSING")
+            .build()
+            .unwrap_err();
+        assert_eq!(err, "Connection KW -> MISSING references unknown object MISSING");
+
+        let err = WorkflowBuilder::new("nikiv.dev.test", "Test")
+            .object(ScriptFilter::new("SF", "code"))
+            .build()
+            .unwrap_err();
+        assert!(err.contains("no script"));
+    }
+
+    #[test]
+    fn test_output_json() {
+        let output = Output::new(vec![Item::new("Test", "Sub").arg("val")]);
+        let json = output.to_json();
+        assert!(json.contains("\"title\":\"Test\""));
+        assert!(json.contains("\"arg\":\"val\""));
+    }
+
+    #[test]
+    fn test_rerun_clamps_to_alfred_range() {
+        assert_eq!(Output::empty().rerun(0.5).rerun, Some(0.5));
+        assert_eq!(Output::empty().rerun(0.01).rerun, Some(RERUN_MIN));
+        assert_eq!(Output::empty().rerun(30.0).rerun, Some(RERUN_MAX));
+        assert_eq!(Output::empty().rerun(f64::NAN).rerun, Some(RERUN_MIN));
+    }
+
+    #[test]
+    fn test_rerun_until() {
+        assert_eq!(Output::empty().rerun_until(1.0, false).rerun, Some(1.0));
+        assert_eq!(Output::empty().rerun_until(1.0, true).rerun, None);
+        assert!(!Output::empty().rerun_until(1.0, true).to_json().contains("rerun"));
+    }
+
+    #[test]
+    fn test_output_progress() {
+        let output = Output::progress("Working…", "Fetching repos", 0.5);
+        assert_eq!(output.items.len(), 1);
+        assert_eq!(output.items[0].title, "Working…");
+        assert_eq!(output.items[0].valid, Some(false));
+        assert_eq!(output.rerun, Some(0.5));
+    }
+
+    #[test]
+    fn test_output_limit() {
+        let items: Vec<Item> = (0..5).map(|i| Item::title_only(format!("item {}", i))).collect();
+
+        let limited = Output::new(items.clone()).limit(3);
+        assert_eq!(limited.items.len(), 3);
+        assert_eq!(limited.items[2].title, "item 2");
+
+        let noticed = Output::new(items.clone()).limit_with_notice(3);
+        assert_eq!(noticed.items.len(), 4);
+        assert_eq!(noticed.items[3].title, "… 2 more");
+        assert_eq!(noticed.items[3].valid, Some(false));
+
+        // No notice when nothing was dropped
+        let untouched = Output::new(items.clone()).limit_with_notice(5);
+        assert_eq!(untouched.items.len(), 5);
+
+        // The same notice for items the caller dropped itself
+        let dropped = Output::new(items[..3].to_vec()).hidden_notice(2);
+        assert_eq!(dropped.items, noticed.items);
+        assert_eq!(Output::new(items).hidden_notice(0).items.len(), 5);
+    }
+
+    #[test]
+    fn test_discover_repos_cache_ttl() {
+        use std::time::{Duration, SystemTime};
+
+        let dir = fixture_dir("repo-cache");
+        let root = dir.join("code");
+        fs::create_dir_all(root.join("one/.git")).unwrap();
+        let cache_file = dir.join("cache/repos.json");
+        let config = Config::default();
+        let ttl = Duration::from_secs(60);
+        let t0 = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+
+        let first = discover_repos_cached_at(&root, ttl, &cache_file, t0, &config);
+        assert_eq!(first.len(), 1);
+
+        // A new repo appears, but the fresh cache is reused
+        fs::create_dir_all(root.join("two/.git")).unwrap();
+        let fresh = discover_repos_cached_at(&root, ttl, &cache_file, t0 + Duration::from_secs(30), &config);
+        assert_eq!(fresh, first);
+
+        // Once stale, the tree is walked again
+        let stale = discover_repos_cached_at(&root, ttl, &cache_file, t0 + Duration::from_secs(61), &config);
+        assert_eq!(stale.len(), 2);
+    }
+
+    #[test]
+    fn test_repo_cache_survives_concurrent_writers() {
+        use std::time::{Duration, SystemTime};
+
+        let dir = fixture_dir("repo-cache-concurrent");
+        let root = dir.join("code");
+        for name in ["one", "two", "three"] {
+            fs::create_dir_all(root.join(name).join(".git")).unwrap();
+        }
+        let cache_file = dir.join("cache/repos.json");
+
+        std::thread::scope(|scope| {
+            for writer in 0..8u64 {
+                let (root, cache_file) = (&root, &cache_file);
+                scope.spawn(move || {
+                    for i in 0..20 {
+                        // A zero TTL makes every call rediscover and rewrite
+                        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(writer * 100 + i);
+                        let config = Config::default();
+                        let repos =
+                            discover_repos_cached_at(root, Duration::ZERO, cache_file, now, &config);
+                        assert_eq!(repos.len(), 3);
+                        if let Ok(data) = fs::read_to_string(cache_file) {
+                            let parsed = serde_json::from_str::<RepoCache>(&data);
+                            assert!(parsed.is_ok(), "torn read: {}", data);
+                        }
+                    }
+                });
+            }
+        });
+
+        let data = fs::read_to_string(&cache_file).unwrap();
+        assert_eq!(serde_json::from_str::<RepoCache>(&data).unwrap().entries.len(), 3);
+        let leftovers: Vec<_> = fs::read_dir(dir.join("cache"))
+            .unwrap()
+            .flatten()
+            .map(|entry| entry.file_name())
+            .filter(|name| name != "repos.json" && name != "repos.lock")
+            .collect();
+        assert!(leftovers.is_empty(), "{:?}", leftovers);
+    }
+
+    #[test]
+    fn test_git_branch_from_head() {
+        let dir = fixture_dir("git-head");
+
+        let on_branch = dir.join("on-branch");
+        fs::create_dir_all(on_branch.join(".git")).unwrap();
+        fs::write(on_branch.join(".git/HEAD"), "ref: refs/heads/feature/cache\n").unwrap();
+        assert_eq!(git_branch(&on_branch), Some("feature/cache".to_string()));
+
+        let detached = dir.join("detached");
+        fs::create_dir_all(detached.join(".git")).unwrap();
+        fs::write(
+            detached.join(".git/HEAD"),
+            "3f1c2a9d8e7b6c5d4e3f2a1b0c9d8e7f6a5b4c3d\n",
+        )
+        .unwrap();
+        assert_eq!(git_branch(&detached), Some("3f1c2a9".to_string()));
+
+        // Worktrees point at their git dir through a `.git` file
+        let worktree = dir.join("worktree");
+        fs::create_dir_all(&worktree).unwrap();
+        fs::write(worktree.join(".git"), "gitdir: ../on-branch/.git\n").unwrap();
+        assert_eq!(git_branch(&worktree), Some("feature/cache".to_string()));
+    }
+
+    #[test]
+    fn test_remote_url_normalization() {
+        let expected = Some("https://github.com/nikivdev/alfred".to_string());
+        assert_eq!(browsable_remote("git@github.com:nikivdev/alfred.git"), expected);
+        assert_eq!(browsable_remote("ssh://git@github.com:22/nikivdev/alfred.git"), expected);
+        assert_eq!(browsable_remote("https://github.com/nikivdev/alfred.git"), expected);
+        assert_eq!(browsable_remote("https://token@github.com/nikivdev/alfred"), expected);
+        assert_eq!(
+            browsable_remote("git@gitlab.com:group/sub/project.git"),
+            Some("https://gitlab.com/group/sub/project".to_string())
+        );
+        assert_eq!(
+            browsable_remote("https://nikiv@bitbucket.org/team/repo.git"),
+            Some("https://bitbucket.org/team/repo".to_string())
+        );
+        assert_eq!(browsable_remote("/srv/git/alfred.git"), None);
+
+        let repo = fixture_dir("git-remote");
+        fs::create_dir_all(repo.join(".git")).unwrap();
+        fs::write(
+            repo.join(".git/config"),
+            "[core]\n\tbare = false\n[remote \"upstream\"]\n\turl = git@github.com:other/alfred.git\n\
+             [remote \"origin\"]\n\turl = git@github.com:nikivdev/alfred.git\n",
+        )
+        .unwrap();
+        assert_eq!(git_remote_url(&repo), expected);
+    }
+
+    #[test]
+    fn test_list_files_filters_and_caps() {
+        let dir = fixture_dir("list-files");
+        for file in [
+            "readme.md",
+            "src/lib.rs",
+            "src/main.RS",
+            "src/notes.txt",
+            "src/deep/mod.rs",
+            "target/debug/build.rs",
+            ".hidden.rs",
+        ] {
+            let path = dir.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+
+        let rust = list_files(&dir, &FileFilter::new().extensions(&["rs"]));
+        assert_eq!(
+            rust,
+            [dir.join("src/deep/mod.rs"), dir.join("src/lib.rs"), dir.join("src/main.RS")]
+        );
+
+        let capped = list_files(&dir, &FileFilter::new().extensions(&[".rs", "md"]).max_results(2));
+        assert_eq!(capped, [dir.join("readme.md"), dir.join("src/deep/mod.rs")]);
+
+        let shallow = list_files(&dir, &FileFilter::new().include_dirs(true).max_depth(1));
+        assert_eq!(shallow, [dir.join("readme.md"), dir.join("src")]);
+    }
+
+    #[test]
+    fn test_recent_files_by_mtime() {
+        use std::time::{Duration, SystemTime};
+
+        let dir = fixture_dir("recent-files");
+        let now = SystemTime::now();
+        let hour = Duration::from_secs(3600);
+        for (name, age) in [
+            ("old.md", 48 * hour),
+            ("new.rs", hour),
+            ("newest.rs", Duration::ZERO),
+            ("mid.txt", 5 * hour),
+        ] {
+            let file = fs::File::create(dir.join(name)).unwrap();
+            file.set_modified(now - age).unwrap();
+        }
+
+        let names = |files: Vec<(PathBuf, SystemTime)>| -> Vec<String> {
+            files
+                .iter()
+                .map(|(p, _)| p.file_name().unwrap().to_string_lossy().to_string())
+                .collect()
+        };
+        assert_eq!(names(recent_files(&dir, 3, None)), ["newest.rs", "new.rs", "mid.txt"]);
+        assert_eq!(names(recent_files(&dir, 10, Some(24 * hour))), ["newest.rs", "new.rs", "mid.txt"]);
+        assert_eq!(names(recent_files(&dir, 10, Some(2 * hour))), ["newest.rs", "new.rs"]);
+    }
+
+    #[test]
+    fn test_discover_repos_multi_disambiguates() {
+        let dir = fixture_dir("discover-multi");
+        let code = dir.join("code");
+        let work = dir.join("work");
+        fs::create_dir_all(code.join("alfred/.git")).unwrap();
+        fs::create_dir_all(code.join("flow/.git")).unwrap();
+        fs::create_dir_all(work.join("alfred/.git")).unwrap();
+
+        // The same root twice must not duplicate entries
+        let repos = discover_repos_multi(&[code.clone(), work.clone(), code.clone()]);
+        let displays: Vec<&str> = repos.iter().map(|r| r.display.as_str()).collect();
+        assert_eq!(displays, ["code/alfred", "flow", "work/alfred"]);
+        assert_eq!(repos[0].path, code.join("alfred"));
+        assert_eq!(repos[2].path, work.join("alfred"));
+    }
+
+    #[test]
+    fn test_sort_repos_by_time() {
+        use std::time::{Duration, SystemTime};
+
+        let root = fixture_dir("sort-repos");
+        let mut repos = Vec::new();
+        // beta's later checkout must not count as a commit
+        let fixtures = [
+            ("alpha", 300, 1_700_000_300, "commit (amend): work"),
+            ("beta", 100, 1_700_000_900, "checkout: moving from main to dev"),
+            ("gamma", 200, 1_700_000_200, "commit (merge): Merge dev"),
+        ];
+        for (name, age_secs, commit_unix, last_message) in fixtures {
+            let path = root.join(name);
+            fs::create_dir_all(path.join(".git/logs")).unwrap();
+            fs::write(
+                path.join(".git/logs/HEAD"),
+                format!(
+                    "0000000 1111111 Dev <dev@example.com> 1600000000 +0000\tclone\n\
+                     1111111 2222222 Dev <dev@example.com> 1600000050 +0000\tcommit: init\n\
+                     2222222 3333333 Dev <dev@example.com> {} +0100\t{}\n",
+                    commit_unix, last_message
+                ),
+            )
+            .unwrap();
+            let mtime = SystemTime::now() - Duration::from_secs(age_secs);
+            fs::File::open(&path).unwrap().set_modified(mtime).unwrap();
+            repos.push(CodeEntry {
+                display: name.to_string(),
+                path,
+                branch: None,
+                is_dirty: None,
+                description: None,
+            });
+        }
+        let names = |repos: &[CodeEntry]| {
+            repos.iter().map(|r| r.display.clone()).collect::<Vec<_>>()
+        };
+
+        sort_repos(&mut repos, SortOrder::RecentlyModified);
+        assert_eq!(names(&repos), ["beta", "gamma", "alpha"]);
+
+        assert_eq!(git_last_commit_time(&root.join("alpha")), Some(1_700_000_300));
+        assert_eq!(git_last_commit_time(&root.join("beta")), Some(1_600_000_050));
+        sort_repos(&mut repos, SortOrder::RecentlyCommitted);
+        assert_eq!(names(&repos), ["alpha", "gamma", "beta"]);
+
+        sort_repos(&mut repos, SortOrder::Alphabetical);
+        assert_eq!(names(&repos), ["alpha", "beta", "gamma"]);
+    }
+
+    #[test]
+    fn test_workflow_vars() {
+        std::env::set_var("FLOW_ALFRED_TEST_STR", "hello");
+        std::env::set_var("FLOW_ALFRED_TEST_BOOL_ON", "Yes");
+        std::env::set_var("FLOW_ALFRED_TEST_BOOL_OFF", "0");
+        std::env::set_var("FLOW_ALFRED_TEST_BOOL_BAD", "maybe");
+        std::env::set_var("FLOW_ALFRED_TEST_INT", " 42 ");
+        std::env::set_var("FLOW_ALFRED_TEST_PATH", "~/code");
+
+        assert_eq!(workflow_var("FLOW_ALFRED_TEST_STR"), Some("hello".to_string()));
+        assert_eq!(workflow_var("FLOW_ALFRED_TEST_UNSET"), None);
+        assert_eq!(workflow_var_bool("FLOW_ALFRED_TEST_BOOL_ON"), Some(true));
+        assert_eq!(workflow_var_bool("FLOW_ALFRED_TEST_BOOL_OFF"), Some(false));
+        assert_eq!(workflow_var_bool("FLOW_ALFRED_TEST_BOOL_BAD"), None);
+        assert_eq!(workflow_var_int("FLOW_ALFRED_TEST_INT"), Some(42));
+        assert_eq!(workflow_var_int("FLOW_ALFRED_TEST_STR"), None);
+        assert_eq!(workflow_var_path("FLOW_ALFRED_TEST_PATH"), Some(expand_path("~/code")));
+        assert!(!workflow_var_path("FLOW_ALFRED_TEST_PATH").unwrap().starts_with("~"));
+    }
+
+    #[test]
+    fn test_json_persistence_round_trip() {
+        let dir = fixture_dir("persist-round-trip").join("nested");
+        let value = HashMap::from([("picks".to_string(), vec![1u32, 2, 3])]);
+
+        save_json_in(&dir, "state", &value).unwrap();
+        let loaded: Option<HashMap<String, Vec<u32>>> = load_json_in(&dir, "state");
+        assert_eq!(loaded, Some(value));
+
+        let missing: Option<HashMap<String, Vec<u32>>> = load_json_in(&dir, "missing");
+        assert_eq!(missing, None);
+    }
+
+    #[test]
+    fn test_write_atomic_replaces_without_leftovers() {
+        let dir = fixture_dir("persist-atomic");
+        let path = dir.join("state.json");
+
+        write_atomic(&path, b"{\"version\":1}").unwrap();
+        write_atomic(&path, b"{\"version\":2}").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{\"version\":2}");
+
+        let leftovers: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .flatten()
+            .filter(|e| e.file_name() != "state.json")
+            .collect();
+        assert!(leftovers.is_empty());
+    }
+
+    #[test]
+    fn test_hotkey_cmd_shift_space() {
+        let hotkey = Hotkey::new("HK").shortcut("cmd+shift+space").unwrap();
+        assert_eq!(hotkey.key_code, 49);
+        assert_eq!(hotkey.modifiers.map(ModKey::event_flags), Some(1179648));
+
+        let xml = hotkey.argument(HotkeyArgument::Clipboard).to_plist_object();
+        let value = plist::Value::from_reader(std::io::Cursor::new(xml.as_bytes())).unwrap();
+        let object = value.as_dictionary().unwrap();
+        assert_eq!(
+            object.get("type").unwrap().as_string(),
+            Some("alfred.workflow.trigger.hotkey")
+        );
+        let config = object.get("config").unwrap().as_dictionary().unwrap();
+        let int = |key: &str| config.get(key).unwrap().as_signed_integer();
+        assert_eq!(int("hotkey"), Some(49));
+        assert_eq!(int("hotmod"), Some(1179648));
+        assert_eq!(int("argument"), Some(2));
+        assert_eq!(int("action"), Some(0));
+        assert_eq!(config.get("hotstring").unwrap().as_string(), Some("Space"));
+
+        assert_eq!((ModKey::CMD | ModKey::ALT | ModKey::CTRL).event_flags(), 1835008);
+        assert_eq!(Hotkey::new("HK").shortcut("ctrl+k").unwrap().key_label, "K");
+        assert!(Hotkey::new("HK").shortcut("hyper+k").is_err());
+        assert!(Hotkey::new("HK").shortcut("cmd+nope").is_err());
+    }
+
+    #[test]
+    fn test_notification_script_escaping() {
+        assert_eq!(
+            notification_script("Copied", r#"Path "~/code" copied"#, None),
+            r#"display notification "Path \"~/code\" copied" with title "Copied""#
+        );
+        assert_eq!(
+            notification_script(r"C:\dir", "done", Some("Flow")),
+            r#"display notification "done" with title "C:\\dir" subtitle "Flow""#
+        );
+    }
+
+    #[test]
+    fn test_applescript_argument_escaping() {
+        let bundle_id = "evil\" to quit\nline";
+        assert_eq!(applescript_string(bundle_id), r#""evil\" to quit\nline""#);
+        assert_eq!(
+            alfred_script(&format!("reload workflow {}", applescript_string(bundle_id))),
+            r#"tell application "Alfred" to reload workflow "evil\" to quit\nline""#
+        );
+        assert_eq!(applescript_string("a\\b\tc"), r#""a\\b\tc""#);
+    }
+
+    #[test]
+    fn test_trigger_script() {
+        assert_eq!(
+            alfred_script(&trigger_command("nikiv.dev.flow", "code", None)),
+            r#"tell application "Alfred" to run trigger "code" in workflow "nikiv.dev.flow""#
+        );
+        assert_eq!(
+            trigger_command("nikiv.dev.flow", "search", Some("say \"hi\"\n\\o/")),
+            r#"run trigger "search" in workflow "nikiv.dev.flow" with argument "say \"hi\"\n\\o/""#
+        );
+    }
+
+    #[test]
+    fn test_argument_type_from_str() {
+        for (name, expected) in [
+            ("required", ArgumentType::Required),
+            ("Optional", ArgumentType::Optional),
+            ("NONE", ArgumentType::None),
+        ] {
+            let parsed: ArgumentType = name.parse().unwrap();
+            assert_eq!(parsed, expected);
+            assert_eq!(parsed.to_string(), name.to_lowercase());
+        }
+        assert_eq!(
+            "maybe".parse::<ArgumentType>().unwrap_err(),
+            "Unknown argument type 'maybe' (expected required, optional or none)"
+        );
+    }
+
+    #[test]
+    fn test_expand_path_tilde_and_env_vars() {
+        let home = dirs_home().unwrap();
+        assert_eq!(expand_path("~"), home);
+        assert_eq!(expand_path("~/code"), home.join("code"));
+        assert_eq!(expand_path("$HOME/x"), home.join("x"));
+
+        std::env::set_var("FLOW_ALFRED_TEST_EXPAND", "/opt/work");
+        assert_eq!(expand_path("${FLOW_ALFRED_TEST_EXPAND}/repos"), Path::new("/opt/work/repos"));
+        assert_eq!(expand_path("$FLOW_ALFRED_TEST_EXPAND-old"), Path::new("/opt/work-old"));
+        std::env::remove_var("FLOW_ALFRED_TEST_EXPAND");
+
+        // Unset variables, `~user` and plain paths are left alone
+        assert_eq!(expand_path("$FLOW_ALFRED_TEST_UNSET/x"), Path::new("$FLOW_ALFRED_TEST_UNSET/x"));
+        assert_eq!(expand_path("~root/x"), Path::new("~root/x"));
+        assert_eq!(expand_path("/usr/${}/bin$"), Path::new("/usr/${}/bin$"));
+        assert_eq!(expand_path("relative/dir"), Path::new("relative/dir"));
+    }
+
+    #[test]
+    fn test_expand_path_checked_without_home() {
+        let missing = expand_path_checked_with("~/code", None);
+        assert_eq!(missing.unwrap_err().to_string(), "HOME is not set");
+        assert!(expand_path_checked_with("~", None).is_err());
+        assert_eq!(expand_path_checked_with("/opt/code", None).unwrap(), Path::new("/opt/code"));
+
+        let home = Some(PathBuf::from("/Users/me"));
+        assert_eq!(
+            expand_path_checked_with("~/code", home.clone()).unwrap(),
+            Path::new("/Users/me/code")
+        );
+        assert_eq!(expand_path_with("~", home), Path::new("/Users/me"));
+        assert_eq!(expand_path_checked("~/code").unwrap(), expand_path("~/code"));
+    }
+
+    #[test]
+    fn test_debug_only_logs_in_alfred_debugger() {
+        assert!(debug_enabled_from(Some("1")));
+        assert!(!debug_enabled_from(Some("0")));
+        assert!(!debug_enabled_from(Some("")));
+        assert!(!debug_enabled_from(None));
+
+        let mut out = Vec::new();
+        write_debug(&mut out, "hidden", false).unwrap();
+        assert!(out.is_empty());
+
+        write_debug(&mut out, "scanned 42 repos", true).unwrap();
+        let logged = String::from_utf8(out).unwrap();
+        assert!(logged.starts_with('['), "{}", logged);
+        assert!(logged.ends_with("] scanned 42 repos\n"), "{}", logged);
+    }
+
+    #[test]
+    fn test_missing_path_warnings_only_under_debug() {
+        let dir = fixture_dir("path-warnings");
+        let missing = dir.join("gone dir");
+        let output = Output::new(vec![
+            Item::title_only("exists")
+                .arg(dir.to_string_lossy())
+                .quicklook_file(dir.to_string_lossy()),
+            Item::title_only("missing")
+                .arg(missing.to_string_lossy())
+                .quicklook_file(missing.to_string_lossy()),
+            Item::title_only("not a path").arg("flow").quicklook("https://example.com"),
+        ]);
+
+        let mut out = Vec::new();
+        output.write_path_warnings(&mut out, false).unwrap();
+        assert!(out.is_empty());
+
+        output.write_path_warnings(&mut out, true).unwrap();
+        let logged = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = logged.lines().collect();
+        assert_eq!(lines.len(), 2, "{}", logged);
+        let expected = format!("warning: \"missing\" arg is missing: {}", missing.display());
+        assert!(lines[0].ends_with(&expected), "{}", lines[0]);
+        assert!(lines[1].contains("\"missing\" quicklookurl is missing"));
+        assert!(lines[1].ends_with("gone dir"), "{}", lines[1]);
+    }
+
+    #[test]
+    fn test_alfred_env_from_vars() {
+        let vars: HashMap<&str, &str> = [
+            ("alfred_version", "5.5"),
+            ("alfred_workflow_bundleid", "nikiv.dev.flow"),
+            ("alfred_workflow_name", "Flow"),
+            ("alfred_workflow_version", ""),
+            ("alfred_workflow_data", "/data/nikiv.dev.flow"),
+            ("alfred_workflow_cache", "/cache/nikiv.dev.flow"),
+            ("alfred_preferences", "/sync/Alfred.alfredpreferences"),
+            ("alfred_theme", "alfred.theme.yosemite"),
+            ("alfred_debug", "1"),
+        ]
+        .into_iter()
+        .collect();
+        let env = AlfredEnv::from_vars(|name| vars.get(name).map(|v| v.to_string()));
+
+        assert!(env.in_alfred());
+        assert_eq!(env.bundle_id.as_deref(), Some("nikiv.dev.flow"));
+        assert_eq!(env.workflow_name.as_deref(), Some("Flow"));
+        assert_eq!(env.workflow_version, None);
+        assert_eq!(env.data_dir, Some(PathBuf::from("/data/nikiv.dev.flow")));
+        assert_eq!(env.preferences, Some(PathBuf::from("/sync/Alfred.alfredpreferences")));
+        assert!(env.debug);
+
+        let outside = AlfredEnv::from_vars(|_| None);
+        assert_eq!(outside, AlfredEnv::default());
+        assert!(!outside.in_alfred());
+    }
+
+    #[test]
+    fn test_background_is_dark() {
+        assert_eq!(background_is_dark("rgba(30,30,32,0.95)"), Some(true));
+        assert_eq!(background_is_dark("rgba(255, 255, 255, 0.98)"), Some(false));
+        assert_eq!(background_is_dark("rgb(12,12,12)"), Some(true));
+        assert_eq!(background_is_dark("dark"), None);
+        assert_eq!(background_is_dark("rgba(1,2)"), None);
+    }
+
+    #[test]
+    fn test_run_command_captures_output_and_times_out() {
+        use std::time::{Duration, Instant};
+
+        let output = run_command("sh", ["-c", "echo out; echo err >&2; exit 2"], Duration::from_secs(10)).unwrap();
+        assert_eq!(output.stdout, b"out\n");
+        assert_eq!(output.stderr, b"err\n");
+        assert_eq!(output.status.code(), Some(2));
+
+        let started = Instant::now();
+        let err = run_command("sleep", ["5"], Duration::from_millis(100)).unwrap_err();
+        assert!(matches!(err, Error::TimedOut(_)));
+        assert_eq!(err.to_string(), "Command timed out after 100ms");
+        assert!(started.elapsed() < Duration::from_secs(4));
+    }
+
+    #[test]
+    fn test_combined_mod_keys() {
+        let item = Item::title_only("flow")
+            .cmd_mod("/a", "Reveal")
+            .mod_action(ModKey::CMD | ModKey::ALT, "/b", "Open both")
+            .mod_subtitle(ModKey::SHIFT | ModKey::CTRL, "Subtitle only");
+        let json = serde_json::to_string(&item).unwrap();
+        assert!(json.contains(
+            r#""mods":{"cmd":{"valid":true,"arg":"/a","subtitle":"Reveal"},"cmd+alt":{"valid":true,"arg":"/b","subtitle":"Open both"},"ctrl+shift":{"subtitle":"Subtitle only"}}"#
+        ));
+
+        let parsed: Item = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, item);
+        assert_eq!("shift+ctrl".parse::<ModKey>(), Ok(ModKey::CTRL | ModKey::SHIFT));
+        assert!("cmd+hyper".parse::<ModKey>().is_err());
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("/Users/me/code/flow"), "/Users/me/code/flow");
+        assert_eq!(shell_quote("/Users/me/My Code"), "'/Users/me/My Code'");
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+        assert_eq!(shell_quote(""), "''");
+        assert_eq!(shell_quote("$(rm -rf)"), "'$(rm -rf)'");
+    }
+
+    #[test]
+    fn test_format_relative_time() {
+        let now = 1_700_000_000;
+        let cases = [
+            (now, "just now"),
+            (now - 59, "just now"),
+            (now - 60, "1m ago"),
+            (now - 3_599, "59m ago"),
+            (now - 3_600, "1h ago"),
+            (now - 86_400, "1d ago"),
+            (now - 7 * 86_400, "1w ago"),
+            (now - 29 * 86_400, "4w ago"),
+            (now - 30 * 86_400, "1mo ago"),
+            (now - 400 * 86_400, "1y ago"),
+            (now + 30, "just now"),
+            (now + 300, "in 5m"),
+            (now + 2 * 86_400, "in 2d"),
+        ];
+        for (timestamp, expected) in cases {
+            assert_eq!(format_relative_time_at(timestamp, now), expected, "{}", now - timestamp);
+        }
+        assert_eq!(format_relative_duration(std::time::Duration::from_secs(45)), "45s");
+    }
+
+    #[test]
+    fn test_fuzzy_match() {
+        assert!(fuzzy_match("fc", "flow-code"));
+        assert!(fuzzy_match("fl", "flow"));
+        assert!(fuzzy_match("", "anything"));
+        assert!(!fuzzy_match("xyz", "abc"));
+    }
+
+    #[test]
+    fn test_fuzzy_tokens() {
+        assert!(!fuzzy_match("flow code", "flow-code"));
+        assert!(fuzzy_match_tokens("flow code", "flow-code"));
+        assert!(fuzzy_match_tokens("flow code", "code-flow"));
+        assert!(!fuzzy_match_tokens("flow zzz", "flow-code"));
+        assert!(fuzzy_match_tokens("", "anything"));
+
+        let in_order = fuzzy_score_tokens("flow code", "flow-code");
+        let reversed = fuzzy_score_tokens("flow code", "code-flow");
+        assert!(reversed > 0);
+        assert!(in_order > reversed);
+        assert_eq!(fuzzy_score_tokens("flow zzz", "flow-code"), -1);
+
+        // `b` and `c` first occur in `abc`, before the tokens actually match
+        let target = "abc-cat-bar";
+        let separate = fuzzy_score("cat", target) + fuzzy_score("bar", target);
+        assert_eq!(fuzzy_score_tokens("cat bar", target), separate + 25);
+        assert_eq!(fuzzy_score_tokens("bar cat", target), separate);
+    }
+
+    #[test]
+    fn test_fuzzy_score() {
+        // Exact prefix should score higher
+        let score_prefix = fuzzy_score("fl", "flow");
+        let score_middle = fuzzy_score("fl", "alfred");
+        assert!(score_prefix > score_middle);
+    }
+
+    #[test]
+    fn test_fuzzy_score_prefers_initialisms() {
+        assert!(is_initialism("fcc", "flow-code-cli"));
+        assert!(is_initialism("fc", "flow code"));
+        assert!(!is_initialism("fl", "flow-code"));
+
+        // Same score without the acronym bonus; the initialism now wins
+        assert!(fuzzy_score("fcc", "flow-code-cli") > fuzzy_score("fcc", "fccache"));
+        assert!(fuzzy_score("fc", "flow-code") > fuzzy_score("fc", "fcntl") + 30);
+    }
+
+    #[test]
+    fn test_fuzzy_score_normalized() {
+        let prefix = fuzzy_score_normalized("flow", "flow-code").unwrap();
+        assert!(prefix > 0.99, "{}", prefix);
+        let scattered = fuzzy_score_normalized("fwce", "flow-code").unwrap();
+        assert!(scattered < 0.6, "{}", scattered);
+        assert!(scattered > 0.0);
+        assert_eq!(fuzzy_score_normalized("xyz", "flow-code"), None);
+        assert_eq!(fuzzy_score_normalized("", "flow-code"), Some(1.0));
+    }
+
+    #[test]
+    fn test_fuzzy_score_fields_prefers_weighted_name_match() {
+        let name = |name| [(name, 1.0), ("CLI for Alfred workflows", 0.5)];
+        let by_name = fuzzy_score_fields("flow", &name("flow-code"));
+        let by_description = fuzzy_score_fields("alfred", &name("flow-code"));
+        assert!(by_description > 0);
+        assert!(by_name > by_description);
+
+        // The best field wins; the weight scales its score
+        let score = fuzzy_score("alfred", "CLI for Alfred workflows") as f32 * 0.5;
+        assert_eq!(by_description, score.round() as i32);
+        assert_eq!(fuzzy_score_fields("zzz", &name("flow-code")), -1);
+        assert_eq!(fuzzy_score_fields("", &name("flow-code")), 0);
+    }
+
+    #[test]
+    fn test_fuzzy_case_sensitive_matching() {
+        let exact = MatchOptions {
+            case_sensitive: true,
+            smart_case: false,
+        };
+        assert!(fuzzy_match_opts("API", "API docs", exact));
+        assert!(!fuzzy_match_opts("API", "api-docs", exact));
+        assert!(!fuzzy_match_opts("api", "API docs", exact));
+        assert_eq!(fuzzy_score_opts("api", "API docs", exact), -1);
+
+        // The existing functions still ignore case
+        assert!(fuzzy_match("API", "api-docs"));
+        assert_eq!(
+            fuzzy_score("API", "api-docs"),
+            fuzzy_score_opts("api", "api-docs", exact)
+        );
+    }
+
+    #[test]
+    fn test_fuzzy_smart_case_matching() {
+        let smart = MatchOptions::default();
+        assert!(smart.smart_case && !smart.case_sensitive);
+
+        // Lowercase query: case ignored
+        assert!(fuzzy_match_opts("api", "API docs", smart));
+        assert!(fuzzy_score_opts("api", "API docs", smart) > 0);
+        // Uppercase letter in the query: case matters
+        assert!(fuzzy_match_opts("API", "API docs", smart));
+        assert!(!fuzzy_match_opts("API", "api-docs", smart));
+        assert!(!fuzzy_match_opts("Api", "api-docs", smart));
+        assert_eq!(fuzzy_score_opts("API", "api-docs", smart), -1);
     }
 }
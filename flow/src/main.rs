@@ -1,7 +1,16 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-use clap::{Parser, Subcommand};
-use flow_alfred::{discover_repos, discover_repos_structured, expand_path, fuzzy_match, fuzzy_sort, reload_workflow, Icon, Item, Output};
+use clap::{CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
+use flow_alfred::sessions::{export_session_in, list_session_projects_in, list_sessions_in, projects_dir, resume_command, search_sessions_in, session_list_item, ExportFormat, TimeFormat};
+use flow_alfred::{autocomplete_for_path, discover_repos_cached, discover_repos_multi_with, discover_repos_structured, expand_path, expand_path_checked, fill_git_status, format_relative_time, fuzzy_filter_sort, fuzzy_match, fuzzy_sort, items_from_lines, parse_tsv_item, reload_workflow, repo_description, sort_repos, CodeEntry, Config, Icon, Item, Output, SortOrder, WorkflowInfo};
+
+/// Max items shown by code/repos search before the user has typed anything
+const EMPTY_QUERY_LIMIT: usize = 50;
+
+/// How long a code root's discovered repos are reused between keystrokes
+const REPO_CACHE_TTL: Duration = Duration::from_secs(60);
 
 #[derive(Parser)]
 #[command(name = "flow-alfred")]
@@ -9,6 +18,18 @@ use flow_alfred::{discover_repos, discover_repos_structured, expand_path, fuzzy_
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// Print management command results (link, pack, reload, ...) as JSON
+    #[arg(long, global = true)]
+    json: bool,
+
+    /// Indent Script Filter JSON for reading (Alfred ignores the whitespace)
+    #[arg(long, global = true)]
+    pretty: bool,
+
+    /// Claude config directory holding `projects/` (default: $CLAUDE_CONFIG_DIR or ~/.claude)
+    #[arg(long, global = true)]
+    sessions_root: Option<String>,
 }
 
 #[derive(Subcommand)]
@@ -19,9 +40,25 @@ enum Commands {
         #[arg(default_value = "")]
         query: String,
 
-        /// Root directory to scan
-        #[arg(long, default_value = "~/code")]
-        root: String,
+        /// Root directory to scan (repeatable; defaults to `code_roots` from config, else ~/code)
+        #[arg(long)]
+        root: Vec<String>,
+
+        /// Show the current branch in the subtitle
+        #[arg(long)]
+        status: bool,
+
+        /// Also mark repos with uncommitted changes (runs git per repo)
+        #[arg(long)]
+        dirty: bool,
+
+        /// Show a one-line description from each repo's manifest or README
+        #[arg(long)]
+        describe: bool,
+
+        /// Result order: alphabetical, modified or committed
+        #[arg(long, default_value = "alphabetical")]
+        sort: SortOrder,
     },
 
     /// Search git repositories under ~/repos (owner/repo structure)
@@ -30,9 +67,50 @@ enum Commands {
         #[arg(default_value = "")]
         query: String,
 
-        /// Root directory to scan
-        #[arg(long, default_value = "~/repos")]
-        root: String,
+        /// Root directory to scan (repeatable; defaults to `repos_roots` from config, else ~/repos)
+        #[arg(long)]
+        root: Vec<String>,
+
+        /// Show the current branch in the subtitle
+        #[arg(long)]
+        status: bool,
+
+        /// Also mark repos with uncommitted changes (runs git per repo)
+        #[arg(long)]
+        dirty: bool,
+
+        /// Show a one-line description from each repo's manifest or README
+        #[arg(long)]
+        describe: bool,
+
+        /// Result order: alphabetical, modified or committed
+        #[arg(long, default_value = "alphabetical")]
+        sort: SortOrder,
+    },
+
+    /// List installed workflows (a table, or Alfred JSON with --json)
+    List {
+        /// Query to filter workflows by name
+        #[arg(default_value = "")]
+        query: String,
+    },
+
+    /// Create a skeleton workflow directory, ready to `link`
+    Init {
+        /// Directory to create
+        workflow_dir: String,
+
+        /// Bundle ID
+        #[arg(long)]
+        bundle_id: String,
+
+        /// Workflow name shown in Alfred (defaults to the directory name)
+        #[arg(long)]
+        name: Option<String>,
+
+        /// Add a Script Filter connected to a Run Script
+        #[arg(long)]
+        with_script_filter: bool,
     },
 
     /// Link workflow to Alfred (for development)
@@ -44,6 +122,21 @@ enum Commands {
         /// Bundle ID
         #[arg(long, default_value = "nikiv.dev.flow")]
         bundle_id: String,
+
+        /// Show what would be linked without changing anything
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Move an installed (non-symlink) copy to workflows/.backups first
+        #[arg(long)]
+        backup: bool,
+    },
+
+    /// Restore the most recent backup made by `link --backup`
+    Restore {
+        /// Bundle ID
+        #[arg(long, default_value = "nikiv.dev.flow")]
+        bundle_id: String,
     },
 
     /// Unlink workflow from Alfred
@@ -53,6 +146,17 @@ enum Commands {
         bundle_id: String,
     },
 
+    /// Uninstall a workflow, moving an installed copy to the Trash
+    Remove {
+        /// Bundle ID
+        #[arg(long, default_value = "nikiv.dev.flow")]
+        bundle_id: String,
+
+        /// Also remove a real installed directory, not just a dev symlink
+        #[arg(long)]
+        force: bool,
+    },
+
     /// Pack workflow into .alfredworkflow file
     Pack {
         /// Path to workflow directory
@@ -62,6 +166,46 @@ enum Commands {
         /// Output file path
         #[arg(short, long)]
         output: Option<String>,
+
+        /// List the files that would be packed without writing the package
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Compare a workflow's info.plist with the copy installed in Alfred
+    Diff {
+        /// Path to workflow directory holding the generated info.plist
+        #[arg(default_value = "Flow.alfredworkflow")]
+        workflow_dir: String,
+
+        /// Bundle ID
+        #[arg(long, default_value = "nikiv.dev.flow")]
+        bundle_id: String,
+    },
+
+    /// Check a workflow directory for problems before packing
+    Validate {
+        /// Path to workflow directory
+        #[arg(default_value = "Flow.alfredworkflow")]
+        workflow_dir: String,
+    },
+
+    /// Open a repo's origin remote in the browser
+    Remote {
+        /// Path to the repository
+        #[arg(default_value = ".")]
+        path: String,
+
+        /// Print the URL instead of opening it
+        #[arg(long)]
+        print: bool,
+    },
+
+    /// Open a workflow's data and cache directories in Finder
+    Reveal {
+        /// Bundle ID
+        #[arg(long, default_value = "nikiv.dev.flow")]
+        bundle_id: String,
     },
 
     /// Install workflow (open .alfredworkflow file)
@@ -96,6 +240,21 @@ enum Commands {
         /// Project path
         #[arg(long)]
         path: String,
+
+        /// Match the query against the whole transcript, not just the first message
+        #[arg(long)]
+        search_content: bool,
+
+        /// Session times: relative, absolute or iso
+        #[arg(long, default_value = "relative")]
+        time_format: TimeFormat,
+    },
+
+    /// List projects that have AI sessions (Alfred JSON output)
+    Projects {
+        /// Query to filter projects
+        #[arg(default_value = "")]
+        query: String,
     },
 
     /// Get session content for clipboard
@@ -107,6 +266,43 @@ enum Commands {
         /// Project path
         #[arg(long)]
         path: String,
+
+        /// Output format: markdown, plain or json
+        #[arg(long, default_value = "markdown")]
+        format: ExportFormat,
+
+        /// Include message timestamps
+        #[arg(long)]
+        timestamps: bool,
+    },
+
+    /// Print the shell command that resumes a session in its project directory
+    ResumeSession {
+        /// Session ID
+        #[arg(long)]
+        id: String,
+
+        /// Project path
+        #[arg(long)]
+        path: String,
+    },
+
+    /// Turn `title<TAB>subtitle<TAB>arg` lines on stdin into items (Alfred JSON output)
+    FromLines {
+        /// Query to filter items by title
+        #[arg(default_value = "")]
+        query: String,
+    },
+
+    /// Fuzzy search a JSON catalog of items (Alfred JSON output)
+    Search {
+        /// Search query
+        #[arg(default_value = "")]
+        query: String,
+
+        /// JSON array of {title, subtitle?, arg, icon?, match?} objects
+        #[arg(long)]
+        file: String,
     },
 
     /// List windows of frontmost app (Alfred JSON output)
@@ -121,64 +317,175 @@ enum Commands {
         /// JSON with app name and window index
         arg: String,
     },
+
+    /// Print shell completions (bash, zsh, fish, ...)
+    #[command(hide = true)]
+    Completions {
+        /// Shell to generate completions for
+        shell: Shell,
+    },
 }
 
 fn main() {
     let cli = Cli::parse();
 
     match cli.command {
-        Commands::Code { query, root } => run_code_search(&query, &root),
-        Commands::Repos { query, root } => run_repos_search(&query, &root),
+        Commands::Code {
+            query,
+            root,
+            status,
+            dirty,
+            describe,
+            sort,
+        } => {
+            let roots = if root.is_empty() { Config::load().code_roots } else { root };
+            let editor_scheme = flow_alfred::workflow_var("editor_scheme").filter(|s| !s.trim().is_empty());
+            let output = run_code_search(&query, &roots, status, dirty, describe, sort, editor_scheme.as_deref());
+            emit(output, cli.pretty)
+        }
+        Commands::Repos {
+            query,
+            root,
+            status,
+            dirty,
+            describe,
+            sort,
+        } => {
+            let roots = if root.is_empty() { Config::load().repos_roots } else { root };
+            emit(run_repos_search(&query, &roots, status, dirty, describe, sort), cli.pretty)
+        }
+        Commands::List { query } => run_list(&query, cli.json, cli.pretty),
+        Commands::Init {
+            workflow_dir,
+            bundle_id,
+            name,
+            with_script_filter,
+        } => report(
+            cli.json,
+            "init",
+            run_init(&workflow_dir, &bundle_id, name.as_deref(), with_script_filter),
+        ),
         Commands::Link {
             workflow_dir,
             bundle_id,
-        } => run_link(&workflow_dir, &bundle_id),
-        Commands::Unlink { bundle_id } => run_unlink(&bundle_id),
+            dry_run,
+            backup,
+        } => report(cli.json, "link", run_link(&workflow_dir, &bundle_id, dry_run, backup)),
+        Commands::Restore { bundle_id } => report(cli.json, "restore", run_restore(&bundle_id)),
+        Commands::Unlink { bundle_id } => report(cli.json, "unlink", run_unlink(&bundle_id)),
+        Commands::Remove { bundle_id, force } => report(cli.json, "remove", run_remove(&bundle_id, force)),
         Commands::Pack {
             workflow_dir,
             output,
-        } => run_pack(&workflow_dir, output),
-        Commands::Install { workflow_file } => run_install(&workflow_file),
-        Commands::Reload { bundle_id } => run_reload(&bundle_id),
+            dry_run,
+        } => report(cli.json, "pack", run_pack(&workflow_dir, output, dry_run)),
+        Commands::Diff { workflow_dir, bundle_id } => report(cli.json, "diff", run_diff(&workflow_dir, &bundle_id)),
+        Commands::Validate { workflow_dir } => report(cli.json, "validate", run_validate(&workflow_dir)),
+        Commands::Remote { path, print } => report(cli.json, "remote", run_remote(&path, print)),
+        Commands::Reveal { bundle_id } => report(cli.json, "reveal", run_reveal(&bundle_id)),
+        Commands::Install { workflow_file } => report(cli.json, "install", run_install(&workflow_file)),
+        Commands::Reload { bundle_id } => report(cli.json, "reload", run_reload(&bundle_id)),
         Commands::Watch { workflow_dir, bundle_id } => run_watch(&workflow_dir, &bundle_id),
-        Commands::Sessions { query, path } => run_sessions(&query, &path),
-        Commands::SessionContent { id, path } => run_session_content(&id, &path),
+        Commands::Sessions {
+            query,
+            path,
+            search_content,
+            time_format,
+        } => {
+            let projects = sessions_projects(cli.sessions_root.as_deref());
+            let output = if search_content && !query.is_empty() {
+                run_sessions_search(&projects, &query, &path)
+            } else {
+                run_sessions(&projects, &query, &path, time_format)
+            };
+            emit(output, cli.pretty)
+        }
+        Commands::Projects { query } => {
+            let projects = sessions_projects(cli.sessions_root.as_deref());
+            emit(run_projects(&projects, &query), cli.pretty)
+        }
+        Commands::SessionContent {
+            id,
+            path,
+            format,
+            timestamps,
+        } => {
+            let projects = sessions_projects(cli.sessions_root.as_deref());
+            run_session_content(&projects, &id, &path, format, timestamps)
+        }
+        Commands::ResumeSession { id, path } => println!("{}", resume_command(&id, &path)),
+        Commands::FromLines { query } => emit(run_from_lines(&query), cli.pretty),
+        Commands::Search { query, file } => emit(run_search(&query, &file), cli.pretty),
         Commands::Windows { query } => run_windows(&query),
         Commands::RaiseWindow { arg } => run_raise_window(&arg),
+        Commands::Completions { shell } => {
+            print!("{}", completions(shell));
+        }
+    }
+}
+
+/// Print Script Filter JSON for Alfred, indented with `--pretty`
+fn emit(output: Output, pretty: bool) {
+    if pretty {
+        output.print_pretty();
+    } else {
+        output.print();
     }
 }
 
-fn run_code_search(query: &str, root: &str) {
-    let root_path = expand_path(root);
+fn run_code_search(
+    query: &str,
+    roots: &[String],
+    status: bool,
+    dirty: bool,
+    describe: bool,
+    sort: SortOrder,
+    editor_scheme: Option<&str>,
+) -> Output {
+    // A query completed with Tab ends in the directory's `/`
+    let query = query.trim_end_matches('/');
+    if let Err(e) = roots.iter().try_for_each(|root| expand_path_checked(root).map(drop)) {
+        return Output::new(vec![Item::new(e.to_string(), "Can't resolve ~ in the configured roots").valid(false)]);
+    }
+    let existing: Vec<&String> = roots.iter().filter(|root| expand_path(root).exists()).collect();
 
-    if !root_path.exists() {
-        Output::new(vec![Item::new(
-            format!("No directory found at {}", root),
+    if existing.is_empty() {
+        return Output::new(vec![Item::new(
+            format!("No directory found at {}", roots.join(", ")),
             "Check your code_root setting",
         )
         .valid(false)
         .icon(Icon::path(
             "/System/Library/CoreServices/CoreTypes.bundle/Contents/Resources/AlertStopIcon.icns",
-        ))])
-        .print();
-        return;
+        ))]);
     }
 
-    let repos = discover_repos(&root_path);
+    let root_paths: Vec<PathBuf> = existing.iter().map(|root| expand_path(root)).collect();
+    let mut repos = discover_repos_multi_with(&root_paths, |root| discover_repos_cached(root, REPO_CACHE_TTL));
+    sort_repos(&mut repos, sort);
     if repos.is_empty() {
-        Output::new(vec![Item::new("No git repositories found", format!("in {}", root))
+        return Output::new(vec![Item::new("No git repositories found", format!("in {}", roots.join(", ")))
             .valid(false)
-            .icon(Icon::path("/System/Library/CoreServices/CoreTypes.bundle/Contents/Resources/GenericFolderIcon.icns"))])
-            .print();
-        return;
+            .icon(Icon::path("/System/Library/CoreServices/CoreTypes.bundle/Contents/Resources/GenericFolderIcon.icns"))]);
     }
 
-    let mut items: Vec<Item> = repos
-        .iter()
-        .filter(|e| query.is_empty() || fuzzy_match(query, &e.display))
-        .map(|entry| {
+    // With no query every repo matches; only build items for those shown
+    let matched = fuzzy_filter_sort(repos, query, |e| &e.display);
+    let shown = if query.is_empty() { EMPTY_QUERY_LIMIT } else { matched.len() };
+    let hidden = matched.len().saturating_sub(shown);
+    let items: Vec<Item> = matched
+        .into_iter()
+        .take(shown)
+        .map(|(mut entry, _)| {
+            // Only shown repos pay for the reads (and `--dirty`'s `git status`)
+            if status || dirty {
+                fill_git_status(std::slice::from_mut(&mut entry), dirty);
+            }
+            if describe {
+                entry.description = repo_description(&entry.path);
+            }
             let path_str = entry.path.to_string_lossy().to_string();
-            let relative_path = format!("{}/{}", root, &entry.display);
+            let relative_path = relative_to_roots(&entry.path, &existing, &root_paths);
             // Condense when last two path segments are the same (e.g., "org/gitedit/gitedit" -> "org/gitedit")
             let parts: Vec<&str> = entry.display.rsplitn(2, '/').collect();
             let display = if parts.len() == 2 && parts[0] == parts[1].rsplit('/').next().unwrap_or("") {
@@ -186,58 +493,79 @@ fn run_code_search(query: &str, root: &str) {
             } else {
                 entry.display.clone()
             };
-            Item::title_only(&display)
+            let item = Item::title_only(&display)
+                .subtitle_opt(repo_subtitle(&entry))
                 .uid(&path_str)
                 .arg(&path_str)
                 .match_field(&entry.display)
-                .autocomplete(&entry.display)
-                .file_type()
-                .icon(Icon::fileicon(&path_str))
-                .quicklook(&path_str)
+                .autocomplete(autocomplete_for_path(&entry.display, &entry.path))
+                .file_type_skipcheck()
+                .icon(status_icon(&entry, &path_str))
+                .quicklook_file(&path_str)
                 .copy_text(&relative_path)
                 .cmd_mod(&relative_path, "Paste path")
-                .alt_mod(&path_str, "Browse sessions")
+                .alt_mod(&path_str, "Browse sessions");
+            let item = with_remote_mod(item, &entry.path);
+            let item = with_editor_mod(item, editor_scheme, &entry.path);
+            with_match_feedback(item, query, &entry.display)
         })
         .collect();
 
-    if !query.is_empty() {
-        fuzzy_sort(&mut items, query, |item| &item.title);
-    }
-
-    Output::new(items).print();
+    Output::new(items).hidden_notice(hidden)
 }
 
-fn run_repos_search(query: &str, root: &str) {
-    let root_path = expand_path(root);
+fn run_repos_search(
+    query: &str,
+    roots: &[String],
+    status: bool,
+    dirty: bool,
+    describe: bool,
+    sort: SortOrder,
+) -> Output {
+    // A query completed with Tab ends in the directory's `/`
+    let query = query.trim_end_matches('/');
+    if let Err(e) = roots.iter().try_for_each(|root| expand_path_checked(root).map(drop)) {
+        return Output::new(vec![Item::new(e.to_string(), "Can't resolve ~ in the configured roots").valid(false)]);
+    }
+    let existing: Vec<&String> = roots.iter().filter(|root| expand_path(root).exists()).collect();
 
-    if !root_path.exists() {
-        Output::new(vec![Item::new(
-            format!("No directory found at {}", root),
+    if existing.is_empty() {
+        return Output::new(vec![Item::new(
+            format!("No directory found at {}", roots.join(", ")),
             "Check your repos_root setting",
         )
         .valid(false)
         .icon(Icon::path(
             "/System/Library/CoreServices/CoreTypes.bundle/Contents/Resources/AlertStopIcon.icns",
-        ))])
-        .print();
-        return;
+        ))]);
     }
 
-    let repos = discover_repos_structured(&root_path);
+    let root_paths: Vec<PathBuf> = existing.iter().map(|root| expand_path(root)).collect();
+    let mut repos = discover_repos_multi_with(&root_paths, discover_repos_structured);
+    sort_repos(&mut repos, sort);
     if repos.is_empty() {
-        Output::new(vec![Item::new("No git repositories found", format!("in {}", root))
+        return Output::new(vec![Item::new("No git repositories found", format!("in {}", roots.join(", ")))
             .valid(false)
-            .icon(Icon::path("/System/Library/CoreServices/CoreTypes.bundle/Contents/Resources/GenericFolderIcon.icns"))])
-            .print();
-        return;
+            .icon(Icon::path("/System/Library/CoreServices/CoreTypes.bundle/Contents/Resources/GenericFolderIcon.icns"))]);
     }
 
-    let mut items: Vec<Item> = repos
-        .iter()
-        .filter(|e| query.is_empty() || fuzzy_match(query, &e.display))
-        .map(|entry| {
+    // With no query every repo matches; only build items for those shown
+    let matched = fuzzy_filter_sort(repos, query, |e| &e.display);
+    let shown = if query.is_empty() { EMPTY_QUERY_LIMIT } else { matched.len() };
+    let hidden = matched.len().saturating_sub(shown);
+    let items: Vec<Item> = matched
+        .into_iter()
+        .take(shown)
+        .map(|(mut entry, _)| {
+            // Only shown repos pay for the reads (and `--dirty`'s `git status`)
+            if status || dirty {
+                fill_git_status(std::slice::from_mut(&mut entry), dirty);
+            }
+            if describe {
+                entry.description = repo_description(&entry.path);
+            }
             let path_str = entry.path.to_string_lossy().to_string();
-            let relative_path = format!("{}/{}", root, &entry.display);
+            let relative_path = relative_to_roots(&entry.path, &existing, &root_paths);
             // Condense when last two path segments are the same (e.g., "org/gitedit/gitedit" -> "org/gitedit")
             let parts: Vec<&str> = entry.display.rsplitn(2, '/').collect();
             let display = if parts.len() == 2 && parts[0] == parts[1].rsplit('/').next().unwrap_or("") {
@@ -245,108 +573,427 @@ fn run_repos_search(query: &str, root: &str) {
             } else {
                 entry.display.clone()
             };
-            Item::title_only(&display)
+            let item = Item::title_only(&display)
+                .subtitle_opt(repo_subtitle(&entry))
                 .uid(&path_str)
                 .arg(&path_str)  // Full path for opening
                 .match_field(&entry.display)  // Keep full path for matching
-                .autocomplete(&entry.display)
-                .icon(Icon::fileicon(&path_str))
-                .quicklook(&path_str)
+                .autocomplete(autocomplete_for_path(&entry.display, &entry.path))
+                .icon(status_icon(&entry, &path_str))
+                .quicklook_file(&path_str)
                 .copy_text(&relative_path)  // Relative path for copy
                 .cmd_mod(&relative_path, "Paste path")
-                .alt_mod(&path_str, "Browse sessions")
+                .alt_mod(&path_str, "Browse sessions");
+            let item = with_remote_mod(item, &entry.path);
+            with_match_feedback(item, query, &entry.display)
         })
         .collect();
 
-    if !query.is_empty() {
-        fuzzy_sort(&mut items, query, |item| &item.title);
+    Output::new(items).hidden_notice(hidden)
+}
+
+/// Red/green dot once `--dirty` has checked the working tree, else the folder icon
+fn status_icon(entry: &CodeEntry, path_str: &str) -> Icon {
+    match entry.is_dirty {
+        Some(true) => Icon::dot([255, 59, 48]),
+        Some(false) => Icon::dot([52, 199, 89]),
+        None => Icon::fileicon(path_str),
     }
+}
 
-    Output::new(items).print();
+/// Shift+Return passes the repo's browsable `origin` URL, when it has one
+fn with_remote_mod(item: Item, path: &Path) -> Item {
+    match flow_alfred::git_remote_url(path) {
+        Some(url) => {
+            let subtitle = format!("Open {}", url);
+            item.shift_mod(url, subtitle)
+        }
+        None => item,
+    }
 }
 
-fn run_link(workflow_dir: &str, bundle_id: &str) {
-    let workflow_path = PathBuf::from(workflow_dir).canonicalize().unwrap_or_else(|_| {
-        let cwd = std::env::current_dir().unwrap_or_default();
-        cwd.join(workflow_dir)
+/// Ctrl+Return opens the repo via the `editor_scheme` workflow variable
+fn with_editor_mod(item: Item, editor_scheme: Option<&str>, path: &Path) -> Item {
+    match editor_scheme {
+        Some(scheme) => item.ctrl_mod(flow_alfred::editor_url(scheme, path), format!("Open in {}", scheme)),
+        None => item,
+    }
+}
+
+/// Show which characters of `display` matched the query in the subtitle
+/// (after any git status), and let Tab complete to the end of the segment
+fn with_match_feedback(mut item: Item, query: &str, display: &str) -> Item {
+    let Some(indices) = flow_alfred::fuzzy_match_indices(query, display).filter(|i| !i.is_empty()) else {
+        return item;
+    };
+    let highlighted = flow_alfred::highlight_matches(display, &indices);
+    item.subtitle = Some(match item.subtitle.take() {
+        Some(status) => format!("{} · {}", status, highlighted),
+        None => highlighted,
     });
+    let completed = flow_alfred::complete_to_boundary(display, &indices);
+    // Completing the whole path keeps a directory's trailing `/`
+    if item.autocomplete.as_deref().and_then(|a| a.strip_suffix('/')) == Some(completed.as_str()) {
+        return item;
+    }
+    item.autocomplete(completed)
+}
 
-    if !workflow_path.exists() {
-        eprintln!("Workflow directory not found: {:?}", workflow_path);
-        std::process::exit(1);
+/// Path spelled with the root as the user gave it, e.g. `~/code/alfred`
+fn relative_to_roots(path: &std::path::Path, roots: &[&String], root_paths: &[PathBuf]) -> String {
+    roots
+        .iter()
+        .zip(root_paths)
+        .find_map(|(root, root_path)| {
+            let rest = path.strip_prefix(root_path).ok()?;
+            Some(format!("{}/{}", root, rest.to_string_lossy()))
+        })
+        .unwrap_or_else(|| path.to_string_lossy().to_string())
+}
+
+/// Branch (and modified marker) and description, whichever were filled in
+fn repo_subtitle(entry: &CodeEntry) -> Option<String> {
+    let status = entry.branch.as_deref().map(|branch| match entry.is_dirty {
+        Some(true) => format!("{} · modified", branch),
+        _ => branch.to_string(),
+    });
+    let parts: Vec<&str> = status.as_deref().into_iter().chain(entry.description.as_deref()).collect();
+    (!parts.is_empty()).then(|| parts.join(" · "))
+}
+
+/// Outcome of a management command: prose for people, `fields` for `--json`
+struct Outcome {
+    lines: Vec<String>,
+    warnings: Vec<String>,
+    fields: serde_json::Map<String, serde_json::Value>,
+    exit_code: i32,
+}
+
+impl Outcome {
+    fn new(fields: serde_json::Value) -> Self {
+        Self {
+            lines: Vec::new(),
+            warnings: Vec::new(),
+            fields: match fields {
+                serde_json::Value::Object(map) => map,
+                _ => serde_json::Map::new(),
+            },
+            exit_code: 0,
+        }
     }
 
-    match flow_alfred::link_workflow(&workflow_path, bundle_id) {
-        Ok(dest) => {
-            println!("Linked {:?} -> {:?}", workflow_path, dest);
-            // Reload workflow in Alfred
-            if let Err(e) = reload_workflow(bundle_id) {
-                eprintln!("Warning: Failed to reload workflow: {}", e);
-            } else {
-                println!("Reloaded workflow in Alfred");
+    fn line(mut self, line: impl Into<String>) -> Self {
+        self.lines.push(line.into());
+        self
+    }
+
+    fn warning(mut self, warning: impl Into<String>) -> Self {
+        self.warnings.push(warning.into());
+        self
+    }
+}
+
+/// Print a management command's result, exiting nonzero on error
+///
+/// With `json`, both success and failure are a single JSON object on stdout:
+/// `{"action": ..., <fields>}` or `{"action": ..., "error": ...}`.
+fn report(json: bool, action: &str, result: Result<Outcome, String>) {
+    let exit_code = result.as_ref().map_or(1, |outcome| outcome.exit_code);
+    if json {
+        println!("{}", outcome_json(action, &result));
+    } else {
+        match &result {
+            Ok(outcome) => {
+                for line in &outcome.lines {
+                    println!("{}", line);
+                }
+                for warning in &outcome.warnings {
+                    eprintln!("Warning: {}", warning);
+                }
             }
+            Err(e) => eprintln!("{}", e),
         }
+    }
+    if exit_code != 0 {
+        std::process::exit(exit_code);
+    }
+}
+
+fn outcome_json(action: &str, result: &Result<Outcome, String>) -> serde_json::Value {
+    let mut object = serde_json::Map::new();
+    object.insert("action".to_string(), action.into());
+    match result {
+        Ok(outcome) => object.extend(outcome.fields.clone()),
         Err(e) => {
-            eprintln!("Failed to link: {}", e);
-            std::process::exit(1);
+            object.insert("error".to_string(), e.as_str().into());
         }
     }
+    serde_json::Value::Object(object)
 }
 
-fn run_unlink(bundle_id: &str) {
-    match flow_alfred::unlink_workflow(bundle_id) {
-        Ok(()) => println!("Unlinked {}", bundle_id),
-        Err(e) => {
-            eprintln!("Failed to unlink: {}", e);
-            std::process::exit(1);
+fn run_list(query: &str, json: bool, pretty: bool) {
+    let workflows = flow_alfred::list_installed_workflows();
+    let workflows: Vec<WorkflowInfo> = fuzzy_filter_sort(workflows, query, |w| w.name.as_str())
+        .into_iter()
+        .map(|(workflow, _)| workflow)
+        .collect();
+
+    if json {
+        emit(Output::new(workflows.iter().map(workflow_item).collect()), pretty);
+    } else {
+        print!("{}", workflow_table(&workflows));
+    }
+}
+
+fn workflow_item(workflow: &WorkflowInfo) -> Item {
+    let path_str = workflow.path.to_string_lossy().into_owned();
+    let mut details = vec![workflow.bundle_id.clone()];
+    if let Some(version) = &workflow.version {
+        details.push(format!("v{}", version));
+    }
+    if workflow.is_symlink {
+        details.push("dev symlink".to_string());
+    }
+    details.retain(|d| !d.is_empty());
+
+    let icon = workflow.path.join("icon.png");
+    let icon = if icon.exists() {
+        Icon::path(icon.to_string_lossy())
+    } else {
+        Icon::fileicon(&path_str)
+    };
+    Item::new(&workflow.name, details.join(" · "))
+        .uid(&path_str)
+        .arg(&path_str)
+        .icon(icon)
+        .file_type()
+}
+
+fn workflow_table(workflows: &[WorkflowInfo]) -> String {
+    let name_width = workflows.iter().map(|w| w.name.chars().count()).max().unwrap_or(0).max(4);
+    let id_width = workflows.iter().map(|w| w.bundle_id.chars().count()).max().unwrap_or(0).max(9);
+    let mut table = format!("{:name_width$}  {:id_width$}  VERSION  LINK\n", "NAME", "BUNDLE ID");
+    for w in workflows {
+        let version = w.version.as_deref().unwrap_or("-");
+        let link = if w.is_symlink { "symlink" } else { "" };
+        let row = format!("{:name_width$}  {:id_width$}  {:7}  {}", w.name, w.bundle_id, version, link);
+        table.push_str(row.trim_end());
+        table.push('\n');
+    }
+    table
+}
+
+fn run_init(
+    workflow_dir: &str,
+    bundle_id: &str,
+    name: Option<&str>,
+    with_script_filter: bool,
+) -> Result<Outcome, String> {
+    let dir = expand_path(workflow_dir);
+    let default_name = dir.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let name = name.unwrap_or(&default_name);
+    let created = flow_alfred::scaffold_workflow_with(&dir, bundle_id, name, with_script_filter)?;
+    let files: Vec<String> = created.iter().map(|f| f.display().to_string()).collect();
+    let mut outcome = Outcome::new(serde_json::json!({ "dir": dir.display().to_string(), "files": files }));
+    for file in &files {
+        outcome = outcome.line(format!("Created {}", file));
+    }
+    Ok(outcome.line(format!("Link it with: flow-alfred link {:?} --bundle-id {}", dir, bundle_id)))
+}
+
+fn run_link(workflow_dir: &str, bundle_id: &str, dry_run: bool, backup: bool) -> Result<Outcome, String> {
+    let workflow_path = PathBuf::from(workflow_dir).canonicalize().unwrap_or_else(|_| {
+        let cwd = std::env::current_dir().unwrap_or_default();
+        cwd.join(workflow_dir)
+    });
+
+    if !workflow_path.exists() {
+        return Err(format!("Workflow directory not found: {:?}", workflow_path));
+    }
+
+    if dry_run {
+        let plan = flow_alfred::link_workflow_dry_run(&workflow_path, bundle_id)
+            .map_err(|e| format!("Failed to link: {}", e))?;
+        let mut outcome = Outcome::new(serde_json::json!({
+            "source": plan.source.display().to_string(),
+            "dest": plan.dest.display().to_string(),
+            "overwrites": plan.overwrites,
+            "dry_run": true,
+        }))
+        .line(format!("Would link {:?} -> {:?}", plan.source, plan.dest));
+        if plan.overwrites {
+            outcome = outcome.line("Would replace the existing symlink");
         }
+        return Ok(outcome);
+    }
+
+    let linked = if backup {
+        flow_alfred::link_workflow_backup(&workflow_path, bundle_id)
+    } else {
+        flow_alfred::link_workflow(&workflow_path, bundle_id)
+    };
+    let dest = linked.map_err(|e| format!("Failed to link: {}", e))?;
+    Ok(link_outcome(&workflow_path, &dest, reload_workflow(bundle_id)))
+}
+
+fn link_outcome(source: &Path, dest: &Path, reloaded: Result<(), String>) -> Outcome {
+    let outcome = Outcome::new(serde_json::json!({
+        "source": source.display().to_string(),
+        "dest": dest.display().to_string(),
+        "reloaded": reloaded.is_ok(),
+    }))
+    .line(format!("Linked {:?} -> {:?}", source, dest));
+    match reloaded {
+        Ok(()) => outcome.line("Reloaded workflow in Alfred"),
+        Err(e) => outcome.warning(format!("Failed to reload workflow: {}", e)),
     }
 }
 
-fn run_pack(workflow_dir: &str, output: Option<String>) {
+fn run_restore(bundle_id: &str) -> Result<Outcome, String> {
+    let dest = flow_alfred::restore_workflow_backup(bundle_id)
+        .map_err(|e| format!("Failed to restore: {}", e))?;
+    let reloaded = reload_workflow(bundle_id);
+    let outcome = Outcome::new(serde_json::json!({
+        "dest": dest.display().to_string(),
+        "reloaded": reloaded.is_ok(),
+    }))
+    .line(format!("Restored {:?}", dest));
+    Ok(match reloaded {
+        Ok(()) => outcome,
+        Err(e) => outcome.warning(format!("Failed to reload workflow: {}", e)),
+    })
+}
+
+fn run_unlink(bundle_id: &str) -> Result<Outcome, String> {
+    flow_alfred::unlink_workflow(bundle_id).map_err(|e| format!("Failed to unlink: {}", e))?;
+    Ok(Outcome::new(serde_json::json!({ "bundle_id": bundle_id }))
+        .line(format!("Unlinked {}", bundle_id)))
+}
+
+fn run_remove(bundle_id: &str, force: bool) -> Result<Outcome, String> {
+    let dest = flow_alfred::remove_workflow(bundle_id, force)?;
+    Ok(Outcome::new(serde_json::json!({ "bundle_id": bundle_id, "path": dest }))
+        .line(format!("Removed {:?}", dest)))
+}
+
+fn run_pack(workflow_dir: &str, output: Option<String>, dry_run: bool) -> Result<Outcome, String> {
     let workflow_path = PathBuf::from(workflow_dir);
     if !workflow_path.exists() {
-        eprintln!("Workflow directory not found: {:?}", workflow_path);
-        std::process::exit(1);
+        return Err(format!("Workflow directory not found: {:?}", workflow_path));
     }
 
     let output_path = output
         .map(PathBuf::from)
         .unwrap_or_else(|| PathBuf::from("Flow-Workflow.alfredworkflow"));
 
-    match flow_alfred::pack_workflow(&workflow_path, &output_path) {
-        Ok(()) => println!("Created {:?}", output_path),
-        Err(e) => {
-            eprintln!("Failed to pack: {}", e);
-            std::process::exit(1);
+    if dry_run {
+        let files = flow_alfred::pack_workflow_dry_run(&workflow_path)
+            .map_err(|e| format!("Failed to pack: {}", e))?;
+        let names: Vec<String> = files.iter().map(|f| f.display().to_string()).collect();
+        let mut outcome = Outcome::new(serde_json::json!({
+            "output": output_path.display().to_string(),
+            "files": names,
+            "dry_run": true,
+        }));
+        for name in &names {
+            outcome = outcome.line(name.as_str());
         }
+        return Ok(outcome.line(format!("Would create {:?} with {} files", output_path, names.len())));
     }
+
+    flow_alfred::pack_workflow(&workflow_path, &output_path).map_err(|e| format!("Failed to pack: {}", e))?;
+    Ok(Outcome::new(serde_json::json!({ "output": output_path.display().to_string() }))
+        .line(format!("Created {:?}", output_path)))
 }
 
-fn run_install(workflow_file: &str) {
-    let path = PathBuf::from(workflow_file);
-    if !path.exists() {
-        eprintln!("Workflow file not found: {:?}", path);
-        std::process::exit(1);
+fn run_diff(workflow_dir: &str, bundle_id: &str) -> Result<Outcome, String> {
+    let generated_path = PathBuf::from(workflow_dir).join("info.plist");
+    let generated = std::fs::read_to_string(&generated_path)
+        .map_err(|e| format!("Failed to read {:?}: {}", generated_path, e))?;
+    let workflows = flow_alfred::workflows_dir().ok_or("Alfred workflows directory not found")?;
+    let installed = workflows.join(bundle_id).join("info.plist");
+
+    let diffs = flow_alfred::diff_plist(&generated, &installed);
+    let mut outcome = Outcome::new(serde_json::json!({ "differences": diffs }));
+    if diffs.is_empty() {
+        outcome = outcome.line("No differences");
+    } else {
+        for diff in &diffs {
+            outcome = outcome.line(diff.as_str());
+        }
+        outcome.exit_code = 1;
     }
+    Ok(outcome)
+}
 
-    match flow_alfred::install_workflow(&path) {
-        Ok(()) => println!("Opening {:?} for installation...", path),
-        Err(e) => {
-            eprintln!("Failed to install: {}", e);
-            std::process::exit(1);
+fn run_validate(workflow_dir: &str) -> Result<Outcome, String> {
+    let diagnostics = flow_alfred::validate_workflow(Path::new(workflow_dir));
+    let mut outcome = Outcome::new(serde_json::json!({ "diagnostics": diagnostics }));
+    if diagnostics.is_empty() {
+        outcome = outcome.line("No problems found");
+    }
+    for diagnostic in &diagnostics {
+        outcome = outcome.line(diagnostic.to_string());
+        if diagnostic.severity == flow_alfred::Severity::Error {
+            outcome.exit_code = 1;
         }
     }
+    Ok(outcome)
 }
 
-fn run_reload(bundle_id: &str) {
-    match reload_workflow(bundle_id) {
-        Ok(()) => println!("Reloaded workflow: {}", bundle_id),
-        Err(e) => {
-            eprintln!("Failed to reload: {}", e);
-            std::process::exit(1);
+fn completions(shell: Shell) -> String {
+    let mut buf = Vec::new();
+    clap_complete::generate(shell, &mut Cli::command(), "flow-alfred", &mut buf);
+    String::from_utf8_lossy(&buf).into_owned()
+}
+
+fn run_remote(path: &str, print: bool) -> Result<Outcome, String> {
+    let repo = expand_path(path);
+    let url = flow_alfred::git_remote_url(&repo).ok_or_else(|| format!("No origin remote found in {:?}", repo))?;
+    if print {
+        return Ok(Outcome::new(serde_json::json!({ "url": url, "opened": false })).line(url));
+    }
+
+    std::process::Command::new("open")
+        .arg(&url)
+        .status()
+        .map_err(|e| format!("Failed to open {}: {}", url, e))?;
+    Ok(Outcome::new(serde_json::json!({ "url": url, "opened": true })).line(format!("Opened {}", url)))
+}
+
+fn run_reveal(bundle_id: &str) -> Result<Outcome, String> {
+    let data = flow_alfred::workflow_data_dir(bundle_id).ok_or("HOME is not set")?;
+    let cache = flow_alfred::workflow_cache_dir(bundle_id).ok_or("HOME is not set")?;
+    let mut outcome = Outcome::new(serde_json::json!({ "data_dir": data, "cache_dir": cache }));
+    for dir in [&data, &cache] {
+        if !dir.exists() {
+            outcome = outcome.warning(format!("{:?} does not exist yet", dir));
+            continue;
         }
+        std::process::Command::new("open")
+            .arg(dir)
+            .status()
+            .map_err(|e| format!("Failed to open {:?}: {}", dir, e))?;
+        outcome = outcome.line(format!("Opened {:?}", dir));
     }
+    Ok(outcome)
+}
+
+fn run_install(workflow_file: &str) -> Result<Outcome, String> {
+    let path = PathBuf::from(workflow_file);
+    if !path.exists() {
+        return Err(format!("Workflow file not found: {:?}", path));
+    }
+
+    flow_alfred::install_workflow(&path).map_err(|e| format!("Failed to install: {}", e))?;
+    Ok(Outcome::new(serde_json::json!({ "path": path.display().to_string() }))
+        .line(format!("Opening {:?} for installation...", path)))
+}
+
+fn run_reload(bundle_id: &str) -> Result<Outcome, String> {
+    reload_workflow(bundle_id).map_err(|e| format!("Failed to reload: {}", e))?;
+    Ok(Outcome::new(serde_json::json!({ "bundle_id": bundle_id }))
+        .line(format!("Reloaded workflow: {}", bundle_id)))
 }
 
 fn run_watch(workflow_dir: &str, bundle_id: &str) {
@@ -386,179 +1033,116 @@ fn run_watch(workflow_dir: &str, bundle_id: &str) {
             println!("Reloaded");
         }
     }
-}
 
-fn run_sessions(query: &str, project_path: &str) {
-    use serde_json::Value;
-    use std::fs;
+    let _ = child.wait();
+}
 
-    let claude_dir = dirs::home_dir()
-        .map(|h| h.join(".claude").join("projects"))
-        .unwrap_or_default();
-
-    // Convert path to Claude's folder naming: /Users/nikiv/code/alfred -> -Users-nikiv-code-alfred
-    let project_folder = project_path.replace('/', "-");
-    let sessions_dir = claude_dir.join(&project_folder);
-
-    if !sessions_dir.exists() {
-        Output::new(vec![Item::new("No sessions found", &format!("for {}", project_path))
-            .valid(false)])
-            .print();
-        return;
-    }
-
-    // Find all .jsonl files
-    let mut sessions: Vec<(String, String, String, i64)> = Vec::new(); // (id, first_msg, timestamp_str, timestamp_unix)
-
-    if let Ok(entries) = fs::read_dir(&sessions_dir) {
-        for entry in entries.flatten() {
-            let path = entry.path();
-            if path.extension().map(|e| e == "jsonl").unwrap_or(false) {
-                if let Some(session_id) = path.file_stem().and_then(|s| s.to_str()) {
-                    // Read first user message and last timestamp
-                    if let Ok(content) = fs::read_to_string(&path) {
-                        let mut first_user_msg = String::new();
-                        let mut last_timestamp: i64 = 0;
-                        let mut last_timestamp_str = String::new();
-
-                        for line in content.lines() {
-                            if let Ok(json) = serde_json::from_str::<Value>(line) {
-                                // Get first user message
-                                if first_user_msg.is_empty() {
-                                    if json.get("type").and_then(|t| t.as_str()) == Some("user") {
-                                        if let Some(msg) = json.get("message")
-                                            .and_then(|m| m.get("content"))
-                                            .and_then(|c| c.as_str())
-                                        {
-                                            first_user_msg = msg.chars().take(80).collect();
-                                            first_user_msg = first_user_msg.lines().next().unwrap_or("").to_string();
-                                        }
-                                    }
-                                }
-
-                                // Track last timestamp
-                                if let Some(ts) = json.get("timestamp").and_then(|t| t.as_str()) {
-                                    if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(ts) {
-                                        let unix = dt.timestamp();
-                                        if unix > last_timestamp {
-                                            last_timestamp = unix;
-                                            last_timestamp_str = format_relative_time(unix);
-                                        }
-                                    }
-                                }
-                            }
-                        }
-
-                        if !first_user_msg.is_empty() && last_timestamp > 0 {
-                            sessions.push((
-                                session_id.to_string(),
-                                first_user_msg,
-                                last_timestamp_str,
-                                last_timestamp,
-                            ));
-                        }
-                    }
-                }
-            }
-        }
-    }
+/// Claude projects directory: `<root>/projects` for `--sessions-root`, else the default
+fn sessions_projects(root: Option<&str>) -> PathBuf {
+    root.map(|root| expand_path(root).join("projects"))
+        .unwrap_or_else(projects_dir)
+}
 
-    // Sort by timestamp descending (most recent first)
-    sessions.sort_by(|a, b| b.3.cmp(&a.3));
+fn run_sessions(projects: &Path, query: &str, project_path: &str, time_format: TimeFormat) -> Output {
+    let sessions = list_sessions_in(projects, project_path, query);
 
     if sessions.is_empty() {
-        Output::new(vec![Item::new("No sessions found", &format!("for {}", project_path))
-            .valid(false)])
-            .print();
-        return;
+        return Output::new(vec![Item::new("No sessions found", format!("for {}", project_path))
+            .valid(false)]);
     }
 
     let items: Vec<Item> = sessions
         .iter()
-        .filter(|(_, msg, _, _)| query.is_empty() || msg.to_lowercase().contains(&query.to_lowercase()))
-        .map(|(id, msg, time, _)| {
-            let arg = format!("{}|{}", id, project_path);
-            Item::new(msg, time)
-                .uid(id)
-                .arg(&arg)
-                .match_field(msg)
-        })
+        .map(|session| session_list_item(session, project_path, time_format))
         .collect();
 
-    Output::new(items).print();
+    Output::new(items)
 }
 
-fn format_relative_time(unix_timestamp: i64) -> String {
-    let now = chrono::Utc::now().timestamp();
-    let diff = now - unix_timestamp;
+fn run_sessions_search(projects: &Path, query: &str, project_path: &str) -> Output {
+    let matches = search_sessions_in(projects, project_path, query);
 
-    if diff < 60 {
-        "just now".to_string()
-    } else if diff < 3600 {
-        format!("{}m ago", diff / 60)
-    } else if diff < 86400 {
-        format!("{}h ago", diff / 3600)
-    } else if diff < 604800 {
-        format!("{}d ago", diff / 86400)
-    } else {
-        format!("{}w ago", diff / 604800)
+    if matches.is_empty() {
+        return Output::new(vec![Item::new("No matching sessions", format!("for \"{}\" in {}", query, project_path))
+            .valid(false)]);
     }
+
+    let items: Vec<Item> = matches
+        .iter()
+        .map(|m| {
+            let arg = format!("{}|{}", m.meta.id, project_path);
+            Item::new(&m.meta.first_message, &m.snippet)
+                .uid(&m.meta.id)
+                .arg(&arg)
+        })
+        .collect();
+
+    Output::new(items)
 }
 
-fn run_session_content(session_id: &str, project_path: &str) {
-    use serde_json::Value;
-    use std::fs;
+fn run_projects(projects_dir: &Path, query: &str) -> Output {
+    let projects = list_session_projects_in(projects_dir);
 
-    let claude_dir = dirs::home_dir()
-        .map(|h| h.join(".claude").join("projects"))
-        .unwrap_or_default();
+    if projects.is_empty() {
+        return Output::new(vec![Item::new("No projects with sessions", format!("in {}", projects_dir.display()))
+            .valid(false)]);
+    }
 
-    let project_folder = project_path.replace('/', "-");
-    let session_file = claude_dir.join(&project_folder).join(format!("{}.jsonl", session_id));
+    let mut items: Vec<Item> = projects
+        .iter()
+        .map(|project| (project, project.path.to_string_lossy().to_string()))
+        .filter(|(_, path_str)| query.is_empty() || fuzzy_match(query, path_str))
+        .map(|(project, path_str)| {
+            let subtitle = format!(
+                "{} sessions · {}",
+                project.session_count,
+                format_relative_time(project.last_activity)
+            );
+            Item::new(&path_str, subtitle)
+                .uid(&project.folder)
+                .arg(&path_str)
+                .autocomplete(&path_str)
+        })
+        .collect();
 
-    if !session_file.exists() {
-        eprintln!("Session file not found: {:?}", session_file);
-        return;
+    if !query.is_empty() {
+        fuzzy_sort(&mut items, query, |item| &item.title);
     }
 
-    let content = match fs::read_to_string(&session_file) {
-        Ok(c) => c,
-        Err(e) => {
-            eprintln!("Failed to read session: {}", e);
-            return;
-        }
-    };
+    Output::new(items)
+}
 
-    let mut output = String::new();
+fn run_session_content(projects: &Path, session_id: &str, project_path: &str, format: ExportFormat, timestamps: bool) {
+    match export_session_in(projects, session_id, project_path, format, timestamps) {
+        // Output the content (will be captured by Alfred for clipboard)
+        Ok(content) => print!("{}", content),
+        Err(e) => eprintln!("Failed to read session: {}", e),
+    }
+}
 
-    for line in content.lines() {
-        if let Ok(json) = serde_json::from_str::<Value>(line) {
-            let msg_type = json.get("type").and_then(|t| t.as_str()).unwrap_or("");
+fn run_from_lines(query: &str) -> Output {
+    let items = items_from_lines(std::io::stdin().lock(), parse_tsv_item);
+    let items = fuzzy_filter_sort(items, query, |item| item.title.as_str())
+        .into_iter()
+        .map(|(item, _)| item)
+        .collect();
+    Output::new(items)
+}
 
-            if msg_type == "user" {
-                if let Some(msg) = json.get("message").and_then(|m| m.get("content")).and_then(|c| c.as_str()) {
-                    output.push_str("\n## User\n\n");
-                    output.push_str(msg);
-                    output.push_str("\n");
-                }
-            } else if msg_type == "assistant" {
-                if let Some(content_arr) = json.get("message").and_then(|m| m.get("content")).and_then(|c| c.as_array()) {
-                    for item in content_arr {
-                        if item.get("type").and_then(|t| t.as_str()) == Some("text") {
-                            if let Some(text) = item.get("text").and_then(|t| t.as_str()) {
-                                output.push_str("\n## Assistant\n\n");
-                                output.push_str(text);
-                                output.push_str("\n");
-                            }
-                        }
-                    }
-                }
+fn run_search(query: &str, file: &str) -> Output {
+    match flow_alfred::catalog::load_catalog(&expand_path(file)) {
+        Ok(entries) => {
+            let items = flow_alfred::catalog::search_catalog(entries, query);
+            if items.is_empty() {
+                Output::new(vec![Item::new("No matches", format!("in {}", file)).valid(false)])
+            } else if query.is_empty() {
+                Output::new(items).limit_with_notice(EMPTY_QUERY_LIMIT)
+            } else {
+                Output::new(items)
             }
         }
+        Err(e) => Output::new(vec![Item::new("Failed to load catalog", e.to_string()).valid(false)]),
     }
-
-    // Output the content (will be captured by Alfred for clipboard)
-    print!("{}", output.trim());
 }
 
 fn run_windows(query: &str) {
@@ -709,3 +1293,109 @@ fn run_raise_window(arg: &str) {
         let _ = Command::new("osascript").args(["-l", "JavaScript", "-e", &jxa]).output();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flow_alfred::ModKey;
+
+    #[test]
+    fn test_editor_mod_uses_configured_scheme() {
+        let path = Path::new("/Users/me/code/flow");
+        let item = with_editor_mod(Item::title_only("flow"), Some("cursor"), path);
+        let mods = item.mods.unwrap();
+        let ctrl = mods.get(ModKey::CTRL).unwrap();
+        assert_eq!(ctrl.arg.as_deref(), Some("cursor://file/Users/me/code/flow"));
+        assert_eq!(ctrl.subtitle.as_deref(), Some("Open in cursor"));
+
+        let item = with_editor_mod(Item::title_only("flow"), None, path);
+        assert!(item.mods.is_none());
+    }
+
+    #[test]
+    fn test_match_feedback_on_repo_item() {
+        let item = Item::title_only("nikiv/flow-code").autocomplete("nikiv/flow-code");
+        let item = with_match_feedback(item, "nfl", "nikiv/flow-code");
+        assert_eq!(item.subtitle.as_deref(), Some("[n]ikiv/[fl]ow-code"));
+        assert_eq!(item.autocomplete.as_deref(), Some("nikiv/flow"));
+
+        // A repo directory's trailing `/` survives completing its whole path
+        let item = Item::title_only("nikiv/flow-code").autocomplete("nikiv/flow-code/");
+        let item = with_match_feedback(item, "nfc", "nikiv/flow-code");
+        assert_eq!(item.autocomplete.as_deref(), Some("nikiv/flow-code/"));
+
+        let item = Item::title_only("alfred").subtitle("main");
+        let item = with_match_feedback(item, "al", "alfred");
+        assert_eq!(item.subtitle.as_deref(), Some("main · [al]fred"));
+
+        let item = with_match_feedback(Item::title_only("alfred"), "", "alfred");
+        assert_eq!(item.subtitle, None);
+    }
+
+    #[test]
+    fn test_workflow_table_and_item() {
+        let workflows = [
+            WorkflowInfo {
+                name: "Flow".to_string(),
+                bundle_id: "nikiv.dev.flow".to_string(),
+                version: None,
+                path: PathBuf::from("/w/nikiv.dev.flow"),
+                is_symlink: true,
+            },
+            WorkflowInfo {
+                name: "Clipboard".to_string(),
+                bundle_id: String::new(),
+                version: Some("2.1".to_string()),
+                path: PathBuf::from("/w/user.workflow.1234"),
+                is_symlink: false,
+            },
+        ];
+        assert_eq!(
+            workflow_table(&workflows),
+            "NAME       BUNDLE ID       VERSION  LINK\n\
+             Flow       nikiv.dev.flow  -        symlink\n\
+             Clipboard                  2.1\n"
+        );
+
+        let item = workflow_item(&workflows[0]);
+        assert_eq!(item.subtitle.as_deref(), Some("nikiv.dev.flow · dev symlink"));
+        assert_eq!(item.arg.as_deref(), Some("/w/nikiv.dev.flow"));
+        assert_eq!(workflow_item(&workflows[1]).subtitle.as_deref(), Some("v2.1"));
+    }
+
+    #[test]
+    fn test_link_json_shape() {
+        let outcome = link_outcome(
+            Path::new("/dev/Flow.alfredworkflow"),
+            Path::new("/workflows/nikiv.dev.flow"),
+            Ok(()),
+        );
+        assert_eq!(
+            outcome_json("link", &Ok(outcome)),
+            serde_json::json!({
+                "action": "link",
+                "source": "/dev/Flow.alfredworkflow",
+                "dest": "/workflows/nikiv.dev.flow",
+                "reloaded": true,
+            })
+        );
+
+        let missing = std::env::temp_dir().join("flow-alfred-missing-workflow");
+        let result = run_link(missing.to_str().unwrap(), "nikiv.dev.flow", false, false);
+        let json = outcome_json("link", &result);
+        assert_eq!(json["action"], "link");
+        assert!(json["error"].as_str().unwrap().starts_with("Workflow directory not found"));
+        assert!(json.get("dest").is_none());
+    }
+
+    #[test]
+    fn test_zsh_completions_list_subcommands() {
+        let script = completions(Shell::Zsh);
+        assert!(!script.is_empty());
+        for sub in Cli::command().get_subcommands() {
+            if !sub.is_hide_set() {
+                assert!(script.contains(sub.get_name()), "missing {}", sub.get_name());
+            }
+        }
+    }
+}
@@ -0,0 +1,101 @@
+//! Minimal PNG encoder for generated icons
+//!
+//! Writes 8-bit RGBA images with uncompressed (stored) deflate blocks, which
+//! is plenty for the tiny badges Alfred shows and needs no zlib dependency.
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+/// Largest payload of a single stored deflate block
+const MAX_STORED_BLOCK: usize = 65_535;
+
+/// Encode `pixels` (row-major RGBA, `width * height * 4` bytes) as a PNG
+pub(crate) fn encode_rgba(width: u32, height: u32, pixels: &[u8]) -> Vec<u8> {
+    let row_len = width as usize * 4;
+    assert_eq!(pixels.len(), row_len * height as usize, "pixel buffer size");
+
+    // Each scanline is prefixed with filter type 0 (none)
+    let mut raw = Vec::with_capacity((row_len + 1) * height as usize);
+    for row in pixels.chunks(row_len) {
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&width.to_be_bytes());
+    ihdr.extend_from_slice(&height.to_be_bytes());
+    // Bit depth 8, color type 6 (RGBA), default compression/filter/interlace
+    ihdr.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+    let mut png = SIGNATURE.to_vec();
+    write_chunk(&mut png, b"IHDR", &ihdr);
+    write_chunk(&mut png, b"IDAT", &zlib_stored(&raw));
+    write_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Wrap `data` in a zlib stream made of stored deflate blocks
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01];
+    let mut blocks = data.chunks(MAX_STORED_BLOCK).peekable();
+    if blocks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let len = block.len() as u16;
+        out.push(u8::from(last));
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for &byte in data {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + u32::from(byte)) % 65_521;
+        b = (b + a) % 65_521;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checksums_match_known_values() {
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+
+        let png = encode_rgba(1, 1, &[255, 0, 0, 255]);
+        assert_eq!(&png[..8], &SIGNATURE);
+        assert_eq!(
+            &png[png.len() - 8..],
+            &[b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82]
+        );
+    }
+}
@@ -0,0 +1,172 @@
+//! Validate Script Filter JSON against Alfred's documented format
+//!
+//! The schema ships embedded from `schema/alfred-script-filter.json`. Only the
+//! JSON Schema keywords it uses are interpreted: `type`, `required`,
+//! `properties`, `additionalProperties`, `items`, `enum` and local `$ref`s.
+
+use serde_json::Value;
+
+const SCHEMA: &str = include_str!("../schema/alfred-script-filter.json");
+
+/// Check Script Filter JSON (e.g. from [`Output::to_json`](crate::Output::to_json))
+///
+/// Returns every problem found, each prefixed with a JSON path such as
+/// `$.items[0]`, so a misspelled key reads as
+/// `$.items[0]: unknown key "subtitel"`.
+pub fn validate_against_alfred_schema(json: &str) -> Result<(), Vec<String>> {
+    let schema: Value = serde_json::from_str(SCHEMA).expect("embedded schema is valid JSON");
+    let value: Value =
+        serde_json::from_str(json).map_err(|e| vec![format!("Invalid JSON: {}", e)])?;
+
+    let mut errors = Vec::new();
+    validate(&value, &schema, &schema, "$", &mut errors);
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+fn validate(value: &Value, schema: &Value, root: &Value, path: &str, errors: &mut Vec<String>) {
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        match resolve_ref(root, reference) {
+            Some(target) => validate(value, target, root, path, errors),
+            None => errors.push(format!(
+                "{}: unresolvable schema reference {}",
+                path, reference
+            )),
+        }
+        return;
+    }
+
+    if let Some(expected) = schema.get("type") {
+        let allowed: Vec<&str> = match expected {
+            Value::String(t) => vec![t.as_str()],
+            Value::Array(ts) => ts.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !allowed.iter().any(|t| type_matches(value, t)) {
+            errors.push(format!(
+                "{}: expected {}, found {}",
+                path,
+                allowed.join(" or "),
+                type_name(value)
+            ));
+            return;
+        }
+    }
+
+    if let Some(options) = schema.get("enum").and_then(Value::as_array) {
+        if !options.contains(value) {
+            let options: Vec<String> = options.iter().map(Value::to_string).collect();
+            errors.push(format!(
+                "{}: {} is not one of {}",
+                path,
+                value,
+                options.join(", ")
+            ));
+        }
+    }
+
+    if let Value::Object(map) = value {
+        if let Some(required) = schema.get("required").and_then(Value::as_array) {
+            for key in required.iter().filter_map(Value::as_str) {
+                if !map.contains_key(key) {
+                    errors.push(format!("{}: missing required key \"{}\"", path, key));
+                }
+            }
+        }
+
+        let properties = schema.get("properties").and_then(Value::as_object);
+        for (key, child) in map {
+            let child_path = format!("{}.{}", path, key);
+            match properties.and_then(|p| p.get(key)) {
+                Some(child_schema) => validate(child, child_schema, root, &child_path, errors),
+                None => match schema.get("additionalProperties") {
+                    Some(Value::Bool(false)) => {
+                        errors.push(format!("{}: unknown key \"{}\"", path, key));
+                    }
+                    Some(extra @ Value::Object(_)) => {
+                        validate(child, extra, root, &child_path, errors);
+                    }
+                    _ => {}
+                },
+            }
+        }
+    }
+
+    if let (Value::Array(elements), Some(item_schema)) = (value, schema.get("items")) {
+        for (i, element) in elements.iter().enumerate() {
+            validate(
+                element,
+                item_schema,
+                root,
+                &format!("{}[{}]", path, i),
+                errors,
+            );
+        }
+    }
+}
+
+fn resolve_ref<'a>(root: &'a Value, reference: &str) -> Option<&'a Value> {
+    root.pointer(reference.strip_prefix('#')?)
+}
+
+fn type_matches(value: &Value, expected: &str) -> bool {
+    match expected {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "boolean" => value.is_boolean(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "null" => value.is_null(),
+        _ => false,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Icon, Item, Output};
+
+    #[test]
+    fn test_valid_output_passes() {
+        let output = Output::new(vec![
+            Item::new("Title", "Subtitle")
+                .uid("1")
+                .arg("/path")
+                .icon(Icon::fileicon("/path"))
+                .file_type()
+                .copy_text("copy")
+                .largetype("large")
+                .cmd_mod("/other", "Paste path"),
+            Item::title_only("Bare"),
+        ])
+        .rerun(0.5);
+
+        assert_eq!(validate_against_alfred_schema(&output.to_json()), Ok(()));
+    }
+
+    #[test]
+    fn test_misspelled_key_is_reported() {
+        let json = r#"{"items":[{"title":"Test","subtitel":"typo","valid":"yes"}]}"#;
+        let errors = validate_against_alfred_schema(json).unwrap_err();
+        assert!(errors.contains(&"$.items[0]: unknown key \"subtitel\"".to_string()));
+        assert!(errors.contains(&"$.items[0].valid: expected boolean, found string".to_string()));
+
+        let errors = validate_against_alfred_schema(r#"{"itemz":[]}"#).unwrap_err();
+        assert!(errors.contains(&"$: missing required key \"items\"".to_string()));
+    }
+}
@@ -0,0 +1,1243 @@
+//! Claude Code session transcripts
+//!
+//! Sessions are stored as JSONL files under `~/.claude/projects/<project-folder>/`,
+//! one file per session, where the folder name is the project path with every
+//! character but ASCII letters and digits replaced by `-`. `CLAUDE_CONFIG_DIR`
+//! relocates `~/.claude`.
+//!
+//! Each lookup has an `_in` variant taking the projects directory explicitly.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+use crate::{expand_path, shell_quote, Error, Item};
+
+/// Summary of a single session transcript
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionMeta {
+    /// Session ID (the JSONL file stem)
+    pub id: String,
+    /// First line of the first user message, capped to 80 chars
+    pub first_message: String,
+    /// Latest timestamp seen in the transcript (unix seconds)
+    pub last_timestamp: i64,
+    /// Number of user and assistant lines
+    pub message_count: usize,
+    /// Rough token count of the message text (characters / 4)
+    pub approx_tokens: usize,
+}
+
+impl SessionMeta {
+    /// `last_timestamp` rendered for a subtitle
+    pub fn format_time(&self, format: TimeFormat) -> String {
+        format_timestamp(self.last_timestamp, format)
+    }
+
+    /// Subtitle summarizing the session, e.g. `3h ago · 42 msgs · ~12k tokens`
+    pub fn subtitle(&self, format: TimeFormat) -> String {
+        let tokens = if self.approx_tokens < 1000 {
+            self.approx_tokens.to_string()
+        } else {
+            format!("{}k", self.approx_tokens / 1000)
+        };
+        format!(
+            "{} · {} msgs · ~{} tokens",
+            self.format_time(format),
+            self.message_count,
+            tokens
+        )
+    }
+}
+
+/// How session times are shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimeFormat {
+    /// Age such as `3h ago`
+    #[default]
+    Relative,
+    /// Local date and time, `2024-01-02 15:04`
+    Absolute,
+    /// RFC 3339 in UTC, `2024-01-02T15:04:05Z`
+    Iso,
+}
+
+impl std::str::FromStr for TimeFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "relative" => Ok(TimeFormat::Relative),
+            "absolute" => Ok(TimeFormat::Absolute),
+            "iso" => Ok(TimeFormat::Iso),
+            other => Err(format!(
+                "Unknown time format '{}' (expected relative, absolute or iso)",
+                other
+            )),
+        }
+    }
+}
+
+/// Render a unix timestamp in the given format (absolute times use the local timezone)
+pub fn format_timestamp(unix: i64, format: TimeFormat) -> String {
+    format_timestamp_in(unix, format, &chrono::Local)
+}
+
+fn format_timestamp_in<Tz: chrono::TimeZone>(unix: i64, format: TimeFormat, tz: &Tz) -> String
+where
+    Tz::Offset: std::fmt::Display,
+{
+    let Some(utc) = chrono::DateTime::from_timestamp(unix, 0) else {
+        return String::new();
+    };
+    match format {
+        TimeFormat::Relative => crate::format_relative_time(unix),
+        TimeFormat::Absolute => utc.with_timezone(tz).format("%Y-%m-%d %H:%M").to_string(),
+        TimeFormat::Iso => utc.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+    }
+}
+
+/// A session whose transcript matched a full-text search
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionMatch {
+    pub meta: SessionMeta,
+    /// Single-line excerpt around the first match
+    pub snippet: String,
+}
+
+/// A project folder under `~/.claude/projects` that holds sessions
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProjectMeta {
+    /// Encoded folder name, e.g. `-Users-nikiv-code-alfred`
+    pub folder: String,
+    /// Best-effort decoded project path
+    pub path: PathBuf,
+    /// Number of session files
+    pub session_count: usize,
+    /// Most recent session file modification time (unix seconds)
+    pub last_activity: i64,
+}
+
+/// One line of a transcript JSONL file
+#[derive(Debug, Deserialize)]
+struct TranscriptLine {
+    #[serde(rename = "type", default)]
+    kind: String,
+    #[serde(default)]
+    timestamp: Option<Timestamp>,
+    #[serde(default)]
+    message: Option<Message>,
+}
+
+/// RFC 3339 string, or integer epoch seconds or milliseconds
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Timestamp {
+    Text(String),
+    Epoch(i64),
+}
+
+/// Epochs above this are taken as milliseconds (in seconds it's the year 5138)
+const EPOCH_MILLIS_THRESHOLD: i64 = 100_000_000_000;
+
+#[derive(Debug, Deserialize)]
+struct Message {
+    #[serde(default)]
+    content: Option<Content>,
+}
+
+/// Message content is either a plain string or a list of content blocks
+/// (`text`, `tool_use`, `tool_result`, `image`, ...)
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Content {
+    Text(String),
+    Blocks(Vec<ContentBlock>),
+}
+
+#[derive(Debug, Deserialize)]
+struct ContentBlock {
+    #[serde(rename = "type", default)]
+    kind: String,
+    #[serde(default)]
+    text: Option<String>,
+}
+
+impl TranscriptLine {
+    fn parse(line: &str) -> Option<Self> {
+        serde_json::from_str(line).ok()
+    }
+
+    fn content(&self) -> Option<&Content> {
+        self.message.as_ref().and_then(|m| m.content.as_ref())
+    }
+
+    /// Text of a user line, in either the string or content-block form
+    ///
+    /// Non-text blocks (tool results, images) are ignored, so a line made up
+    /// only of tool results yields `None` rather than an empty string.
+    fn user_text(&self) -> Option<String> {
+        let text = match self.content()? {
+            Content::Text(text) => text.clone(),
+            Content::Blocks(blocks) => blocks
+                .iter()
+                .filter(|b| b.kind == "text")
+                .filter_map(|b| b.text.as_deref())
+                .collect::<Vec<_>>()
+                .join("\n"),
+        };
+        if text.trim().is_empty() {
+            None
+        } else {
+            Some(text)
+        }
+    }
+
+    /// Text blocks of an assistant line
+    fn assistant_texts(&self) -> Vec<&str> {
+        match self.content() {
+            Some(Content::Blocks(blocks)) => blocks
+                .iter()
+                .filter(|b| b.kind == "text")
+                .filter_map(|b| b.text.as_deref())
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Message texts of a user or assistant line, in order (none for other kinds)
+    fn texts(&self) -> Vec<String> {
+        match self.kind.as_str() {
+            "user" => self.user_text().into_iter().collect(),
+            "assistant" => self
+                .assistant_texts()
+                .into_iter()
+                .map(str::to_string)
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// The timestamp as written when it's a date string, else as RFC 3339
+    fn timestamp_text(&self) -> Option<String> {
+        match self.timestamp.as_ref()? {
+            Timestamp::Text(text) if text.trim().parse::<i64>().is_err() => Some(text.clone()),
+            _ => chrono::DateTime::from_timestamp(self.unix_timestamp()?, 0)
+                .map(|dt| dt.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)),
+        }
+    }
+
+    fn unix_timestamp(&self) -> Option<i64> {
+        let epoch = match self.timestamp.as_ref()? {
+            Timestamp::Epoch(epoch) => *epoch,
+            Timestamp::Text(text) => match text.trim().parse::<i64>() {
+                Ok(epoch) => epoch,
+                Err(_) => {
+                    return chrono::DateTime::parse_from_rfc3339(text)
+                        .ok()
+                        .map(|dt| dt.timestamp())
+                }
+            },
+        };
+        if epoch > EPOCH_MILLIS_THRESHOLD {
+            Some(epoch / 1000)
+        } else {
+            Some(epoch)
+        }
+    }
+}
+
+/// Claude's config directory: `$CLAUDE_CONFIG_DIR` when set, else `~/.claude`
+pub fn claude_config_dir() -> PathBuf {
+    claude_config_dir_from(std::env::var("CLAUDE_CONFIG_DIR").ok(), dirs::home_dir())
+}
+
+/// [`claude_config_dir`] for a given `CLAUDE_CONFIG_DIR` value and home
+fn claude_config_dir_from(value: Option<String>, home: Option<PathBuf>) -> PathBuf {
+    match value {
+        Some(dir) if !dir.is_empty() => expand_path(&dir),
+        _ => home.map(|h| h.join(".claude")).unwrap_or_default(),
+    }
+}
+
+/// Directory holding Claude's per-project session folders
+pub fn projects_dir() -> PathBuf {
+    claude_config_dir().join("projects")
+}
+
+/// Convert a project path to Claude's folder naming, where every character
+/// other than an ASCII letter or digit becomes `-`:
+/// `/Users/nikiv/code/flow.rs/` -> `-Users-nikiv-code-flow-rs`
+///
+/// A trailing slash is dropped first, since Claude names folders after the
+/// working directory, which never has one.
+pub fn encode_project_folder(project_path: &str) -> String {
+    let trimmed = project_path.trim_end_matches('/');
+    let path = if trimmed.is_empty() {
+        project_path
+    } else {
+        trimmed
+    };
+    path.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect()
+}
+
+/// Decode a Claude project folder name back to a filesystem path
+///
+/// The encoding is lossy: a `-` may have been a `/`, a literal dash, or a
+/// `.`, `_`, space or other character in a path segment. Decoding resolves
+/// this against the filesystem, greedily matching dash-separated tokens to
+/// the longest run that some existing directory entry encodes to. Segments
+/// that don't exist (e.g. deleted projects) fall back to treating every `-`
+/// as `/`, so `/code/my-app` would decode as `/code/my/app` once `my-app` is
+/// gone.
+pub fn decode_project_folder(folder: &str) -> PathBuf {
+    let tokens: Vec<&str> = folder
+        .strip_prefix('-')
+        .unwrap_or(folder)
+        .split('-')
+        .collect();
+    let mut path = PathBuf::from("/");
+    let mut i = 0;
+
+    while i < tokens.len() {
+        let names: Vec<String> = fs::read_dir(&path)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                    .collect()
+            })
+            .unwrap_or_default();
+        // Longest run of tokens that an existing entry encodes to
+        let matched = (i + 1..=tokens.len()).rev().find_map(|end| {
+            let encoded = tokens[i..end].join("-");
+            names
+                .iter()
+                .find(|name| **name == encoded || encode_project_folder(name) == encoded)
+                .map(|name| (name.clone(), end))
+        });
+        let (segment, end) = matched.unwrap_or_else(|| (tokens[i].to_string(), i + 1));
+        path.push(segment);
+        i = end;
+    }
+
+    path
+}
+
+/// Folder under `projects` holding the sessions of `project_path`
+///
+/// Tries the encoded path, then the encoded canonical path (resolving
+/// symlinks, `.` and `..`), then any existing folder that decodes to the same
+/// canonical path, covering folders Claude named from a different spelling.
+/// Returns the encoded path, which may not exist, when nothing matches.
+fn project_sessions_dir(projects: &Path, project_path: &str) -> PathBuf {
+    let exact = projects.join(encode_project_folder(project_path));
+    if exact.is_dir() {
+        return exact;
+    }
+    let Ok(canonical) = expand_path(project_path).canonicalize() else {
+        return exact;
+    };
+    let by_canonical = projects.join(encode_project_folder(&canonical.to_string_lossy()));
+    if by_canonical.is_dir() {
+        return by_canonical;
+    }
+
+    let Ok(entries) = fs::read_dir(projects) else {
+        return exact;
+    };
+    entries
+        .flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|ft| ft.is_dir()))
+        .find(|entry| {
+            let decoded = decode_project_folder(&entry.file_name().to_string_lossy());
+            decoded.canonicalize().is_ok_and(|path| path == canonical)
+        })
+        .map(|entry| entry.path())
+        .unwrap_or(exact)
+}
+
+/// List every project that has sessions, most recently active first
+pub fn list_session_projects() -> Vec<ProjectMeta> {
+    list_session_projects_in(&projects_dir())
+}
+
+/// [`list_session_projects`] under a specific projects directory
+pub fn list_session_projects_in(projects: &Path) -> Vec<ProjectMeta> {
+    let entries = match fs::read_dir(projects) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut result: Vec<ProjectMeta> = entries
+        .flatten()
+        .filter(|entry| entry.file_type().map(|ft| ft.is_dir()).unwrap_or(false))
+        .filter_map(|entry| {
+            let folder = entry.file_name().to_string_lossy().to_string();
+            let mut session_count = 0;
+            let mut last_activity = 0;
+
+            for session in fs::read_dir(entry.path()).ok()?.flatten() {
+                let path = session.path();
+                if path.extension().map(|e| e != "jsonl").unwrap_or(true) {
+                    continue;
+                }
+                session_count += 1;
+                let modified = session
+                    .metadata()
+                    .and_then(|m| m.modified())
+                    .ok()
+                    .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                    .map(|d| d.as_secs() as i64)
+                    .unwrap_or(0);
+                last_activity = last_activity.max(modified);
+            }
+
+            if session_count == 0 {
+                return None;
+            }
+
+            Some(ProjectMeta {
+                path: decode_project_folder(&folder),
+                folder,
+                session_count,
+                last_activity,
+            })
+        })
+        .collect();
+
+    result.sort_by_key(|p| std::cmp::Reverse(p.last_activity));
+    result
+}
+
+/// List sessions for a project, most recent first, filtered by first message
+pub fn list_sessions(project_path: &str, query: &str) -> Vec<SessionMeta> {
+    list_sessions_in(&projects_dir(), project_path, query)
+}
+
+/// [`list_sessions`] under a specific projects directory
+pub fn list_sessions_in(projects: &Path, project_path: &str, query: &str) -> Vec<SessionMeta> {
+    let sessions_dir = project_sessions_dir(projects, project_path);
+    let entries = match fs::read_dir(&sessions_dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let query = query.to_lowercase();
+    let mut sessions: Vec<SessionMeta> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().map(|e| e == "jsonl").unwrap_or(false))
+        .filter_map(|path| read_session_meta(&path))
+        .filter(|s| query.is_empty() || s.first_message.to_lowercase().contains(&query))
+        .collect();
+
+    // Most recent first
+    sessions.sort_by_key(|s| std::cmp::Reverse(s.last_timestamp));
+    sessions
+}
+
+fn read_session_meta(path: &Path) -> Option<SessionMeta> {
+    let id = path.file_stem()?.to_str()?.to_string();
+    let lines = transcript_lines(path).ok()?;
+
+    let mut first_message = String::new();
+    let mut last_timestamp: i64 = 0;
+    let mut message_count = 0;
+    let mut text_chars = 0;
+
+    for line in lines {
+        if line.kind == "user" || line.kind == "assistant" {
+            message_count += 1;
+        }
+        match line.kind.as_str() {
+            "user" => text_chars += line.user_text().map_or(0, |t| t.chars().count()),
+            "assistant" => {
+                text_chars += line
+                    .assistant_texts()
+                    .iter()
+                    .map(|t| t.chars().count())
+                    .sum::<usize>()
+            }
+            _ => {}
+        }
+
+        if first_message.is_empty() && line.kind == "user" {
+            if let Some(text) = line.user_text() {
+                let first_line = text.trim_start().lines().next().unwrap_or("");
+                first_message = first_line.chars().take(80).collect();
+            }
+        }
+
+        if let Some(unix) = line.unix_timestamp() {
+            last_timestamp = last_timestamp.max(unix);
+        }
+    }
+
+    if first_message.is_empty() {
+        return None;
+    }
+    if last_timestamp <= 0 {
+        // No parseable timestamps: the file's mtime is the next best thing
+        last_timestamp = fs::metadata(path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+    }
+
+    Some(SessionMeta {
+        id,
+        first_message,
+        last_timestamp,
+        message_count,
+        approx_tokens: text_chars / 4,
+    })
+}
+
+/// Search the full transcript of every session for a project
+///
+/// Matching is case-insensitive against user and assistant text. Each file is
+/// scanned only until its first match; non-matching files are never parsed
+/// for metadata.
+pub fn search_sessions(project_path: &str, query: &str) -> Vec<SessionMatch> {
+    search_sessions_in(&projects_dir(), project_path, query)
+}
+
+/// [`search_sessions`] under a specific projects directory
+pub fn search_sessions_in(projects: &Path, project_path: &str, query: &str) -> Vec<SessionMatch> {
+    let sessions_dir = project_sessions_dir(projects, project_path);
+    let entries = match fs::read_dir(&sessions_dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let needle: Vec<char> = query.chars().map(lower_char).collect();
+    if needle.is_empty() {
+        return Vec::new();
+    }
+
+    let mut matches: Vec<SessionMatch> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().map(|e| e == "jsonl").unwrap_or(false))
+        .filter_map(|path| {
+            let snippet = find_in_transcript(&path, &needle)?;
+            let meta = read_session_meta(&path)?;
+            Some(SessionMatch { meta, snippet })
+        })
+        .collect();
+
+    matches.sort_by_key(|m| std::cmp::Reverse(m.meta.last_timestamp));
+    matches
+}
+
+fn find_in_transcript(path: &Path, needle: &[char]) -> Option<String> {
+    for line in transcript_lines(path).ok()? {
+        for text in line.texts() {
+            if let Some(snippet) = snippet_around(&text, needle) {
+                return Some(snippet);
+            }
+        }
+    }
+    None
+}
+
+/// Lowercase a single char, keeping a 1:1 char mapping so match offsets line up
+fn lower_char(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+fn snippet_around(text: &str, needle: &[char]) -> Option<String> {
+    const CONTEXT: usize = 30;
+
+    // Collapse whitespace so the snippet fits on one subtitle line
+    let chars: Vec<char> = text
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .collect();
+    let lowered: Vec<char> = chars.iter().copied().map(lower_char).collect();
+    let pos = lowered.windows(needle.len()).position(|w| w == needle)?;
+
+    let start = pos.saturating_sub(CONTEXT);
+    let end = (pos + needle.len() + CONTEXT).min(chars.len());
+    let mut snippet: String = chars[start..end].iter().collect();
+    if start > 0 {
+        snippet.insert(0, '…');
+    }
+    if end < chars.len() {
+        snippet.push('…');
+    }
+    Some(snippet)
+}
+
+/// Output format for an exported transcript
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExportFormat {
+    /// `## User` / `## Assistant` headings
+    #[default]
+    Markdown,
+    /// `User:` / `Assistant:` labels without heading markup
+    Plain,
+    /// JSON array of `{role, text}` objects
+    Json,
+}
+
+impl std::str::FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "markdown" | "md" => Ok(ExportFormat::Markdown),
+            "plain" | "text" => Ok(ExportFormat::Plain),
+            "json" => Ok(ExportFormat::Json),
+            other => Err(format!(
+                "Unknown format '{}' (expected markdown, plain or json)",
+                other
+            )),
+        }
+    }
+}
+
+/// A single user or assistant message in a transcript
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TranscriptMessage {
+    /// `user` or `assistant`
+    pub role: String,
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<String>,
+}
+
+/// Shell command resuming a session from its project directory:
+/// `cd <project_path> && claude --resume <id>`, with both values quoted
+pub fn resume_command(id: &str, project_path: &str) -> String {
+    format!(
+        "cd {} && claude --resume {}",
+        shell_quote(project_path),
+        shell_quote(id)
+    )
+}
+
+/// Large Type beyond this many characters shrinks to unreadable, so the
+/// transcript shown there is cut off (the copied text is not)
+const LARGETYPE_MAX_CHARS: usize = 1500;
+
+/// Alfred item for a session whose copy and Large Type carry the transcript
+///
+/// Copying (Cmd+C) puts the whole Markdown transcript on the clipboard; Large
+/// Type (Cmd+L) shows its first [`LARGETYPE_MAX_CHARS`] characters. The arg
+/// is `<id>|<project_path>` and Cmd resumes the session in a terminal.
+pub fn session_item(id: &str, project_path: &str) -> Item {
+    session_item_in(&projects_dir(), id, project_path)
+}
+
+/// [`session_item`] under a specific projects directory
+pub fn session_item_in(projects: &Path, id: &str, project_path: &str) -> Item {
+    let transcript =
+        match export_session_in(projects, id, project_path, ExportFormat::Markdown, false) {
+            Ok(transcript) => transcript,
+            Err(e) => return Item::new(e.to_string(), format!("Session {}", id)).valid(false),
+        };
+    let session_file = project_sessions_dir(projects, project_path).join(format!("{}.jsonl", id));
+    let (title, subtitle) = match read_session_meta(&session_file) {
+        Some(meta) => (
+            meta.first_message.clone(),
+            meta.subtitle(TimeFormat::default()),
+        ),
+        None => (id.to_string(), project_path.to_string()),
+    };
+
+    let largetype = if transcript.chars().count() > LARGETYPE_MAX_CHARS {
+        let mut cut: String = transcript.chars().take(LARGETYPE_MAX_CHARS - 1).collect();
+        cut.push('…');
+        cut
+    } else {
+        transcript.clone()
+    };
+
+    Item::new(&title, subtitle)
+        .uid(id)
+        .arg(format!("{}|{}", id, project_path))
+        .match_field(&title)
+        .cmd_mod(resume_command(id, project_path), "Resume in terminal")
+        .copy_text(transcript)
+        .largetype(largetype)
+}
+
+/// Alfred item for a listed session, built from its [`SessionMeta`] alone
+///
+/// Unlike [`session_item`], nothing is read from disk: the arg is
+/// `<id>|<project_path>`, which the workflow passes to `flow-alfred
+/// session-content` to export the transcript only when the item is actioned.
+pub fn session_list_item(meta: &SessionMeta, project_path: &str, time_format: TimeFormat) -> Item {
+    Item::new(&meta.first_message, meta.subtitle(time_format))
+        .uid(&meta.id)
+        .arg(format!("{}|{}", meta.id, project_path))
+        .match_field(&meta.first_message)
+        .cmd_mod(resume_command(&meta.id, project_path), "Resume in terminal")
+}
+
+/// Render a session transcript as Markdown with `## User` / `## Assistant` headings
+pub fn session_content(id: &str, project_path: &str) -> Result<String, Error> {
+    export_session(id, project_path, ExportFormat::Markdown, false)
+}
+
+/// Render a session transcript in the given format, optionally with message timestamps
+pub fn export_session(
+    id: &str,
+    project_path: &str,
+    format: ExportFormat,
+    timestamps: bool,
+) -> Result<String, Error> {
+    export_session_in(&projects_dir(), id, project_path, format, timestamps)
+}
+
+/// [`export_session`] under a specific projects directory
+pub fn export_session_in(
+    projects: &Path,
+    id: &str,
+    project_path: &str,
+    format: ExportFormat,
+    timestamps: bool,
+) -> Result<String, Error> {
+    let session_file = project_sessions_dir(projects, project_path).join(format!("{}.jsonl", id));
+    if !session_file.exists() {
+        return Err(Error::NotFound(session_file));
+    }
+
+    // Text formats are appended message by message; JSON needs the whole array
+    let mut output = String::new();
+    let mut json_messages = Vec::new();
+    for line in transcript_lines(&session_file)? {
+        for text in line.texts() {
+            let message = TranscriptMessage {
+                role: line.kind.clone(),
+                text,
+                timestamp: if timestamps {
+                    line.timestamp_text()
+                } else {
+                    None
+                },
+            };
+            match format {
+                ExportFormat::Json => json_messages.push(message),
+                _ => push_message(&mut output, &message, format),
+            }
+        }
+    }
+
+    if format == ExportFormat::Json {
+        return Ok(serde_json::to_string_pretty(&json_messages).unwrap_or_default());
+    }
+    Ok(output.trim().to_string())
+}
+
+/// Parsed lines of a transcript, read one at a time so memory stays bounded
+/// by the longest line rather than the file size
+fn transcript_lines(path: &Path) -> Result<impl Iterator<Item = TranscriptLine>, Error> {
+    let reader = BufReader::new(fs::File::open(path)?);
+    Ok(reader
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| TranscriptLine::parse(&line)))
+}
+
+/// Append one message as a `## Role` (Markdown) or `Role:` (plain) block
+fn push_message(output: &mut String, message: &TranscriptMessage, format: ExportFormat) {
+    let role = if message.role == "user" {
+        "User"
+    } else {
+        "Assistant"
+    };
+    let label = match &message.timestamp {
+        Some(ts) => format!("{} ({})", role, ts),
+        None => role.to_string(),
+    };
+    match format {
+        ExportFormat::Markdown => output.push_str(&format!("\n## {}\n\n", label)),
+        _ => output.push_str(&format!("\n{}:\n", label)),
+    }
+    output.push_str(&message.text);
+    output.push('\n');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixture_dir;
+
+    const PROJECT: &str = "/Users/test/code/alfred";
+
+    fn projects_fixture(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let projects = fixture_dir(name);
+        let folder = projects.join(encode_project_folder(PROJECT));
+        fs::create_dir_all(&folder).unwrap();
+        for (file, content) in files {
+            fs::write(folder.join(file), content).unwrap();
+        }
+        projects
+    }
+
+    #[test]
+    fn test_list_sessions_meta() {
+        let projects = projects_fixture(
+            "sessions-list",
+            &[
+                (
+                    "abc.jsonl",
+                    include_str!("../tests/fixtures/sessions/basic.jsonl"),
+                ),
+                (
+                    "def.jsonl",
+                    include_str!("../tests/fixtures/sessions/older.jsonl"),
+                ),
+            ],
+        );
+
+        let sessions = list_sessions_in(&projects, PROJECT, "");
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].id, "abc");
+        assert_eq!(
+            sessions[0].first_message,
+            "Fix the symlink bug in link_workflow"
+        );
+        // Two user and two assistant lines; the summary line isn't a message
+        assert_eq!(sessions[0].message_count, 4);
+        assert_eq!(sessions[0].approx_tokens, 98 / 4);
+        assert!(sessions[0]
+            .subtitle(TimeFormat::Iso)
+            .ends_with(" · 4 msgs · ~24 tokens"));
+        assert_eq!(sessions[1].id, "def");
+
+        let filtered = list_sessions_in(&projects, PROJECT, "CACHING");
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].id, "def");
+    }
+
+    #[test]
+    fn test_last_timestamp_is_latest_not_last_line() {
+        let projects = projects_fixture(
+            "sessions-timestamp",
+            &[(
+                "abc.jsonl",
+                include_str!("../tests/fixtures/sessions/basic.jsonl"),
+            )],
+        );
+
+        let sessions = list_sessions_in(&projects, PROJECT, "");
+        let expected = chrono::DateTime::parse_from_rfc3339("2025-01-02T10:02:00Z")
+            .unwrap()
+            .timestamp();
+        assert_eq!(sessions[0].last_timestamp, expected);
+    }
+
+    #[test]
+    fn test_epoch_and_missing_timestamps() {
+        let projects = projects_fixture(
+            "sessions-epoch",
+            &[
+                (
+                    "millis.jsonl",
+                    include_str!("../tests/fixtures/sessions/epoch_millis.jsonl"),
+                ),
+                (
+                    "untimed.jsonl",
+                    include_str!("../tests/fixtures/sessions/no_timestamp.jsonl"),
+                ),
+            ],
+        );
+
+        let sessions = list_sessions_in(&projects, PROJECT, "");
+        assert_eq!(sessions.len(), 2);
+        // The untimed session falls back to its (just written) mtime
+        assert_eq!(sessions[0].id, "untimed");
+        assert_eq!(sessions[0].first_message, "Explain the plist diff output");
+        let now = chrono::Utc::now().timestamp();
+        assert!((now - sessions[0].last_timestamp).abs() < 60);
+        // Millis and integer seconds are both read as unix seconds
+        assert_eq!(sessions[1].id, "millis");
+        assert_eq!(sessions[1].last_timestamp, 1_735_603_200);
+    }
+
+    #[test]
+    fn test_session_content_extracts_user_and_assistant() {
+        let projects = projects_fixture(
+            "sessions-content",
+            &[(
+                "abc.jsonl",
+                include_str!("../tests/fixtures/sessions/basic.jsonl"),
+            )],
+        );
+
+        let content =
+            export_session_in(&projects, "abc", PROJECT, ExportFormat::Markdown, false).unwrap();
+        assert!(content.starts_with("## User\n\nFix the symlink bug"));
+        assert!(content.contains("## Assistant\n\nLooking at link_workflow now."));
+        assert!(content.ends_with("## Assistant\n\nDone."));
+        assert!(!content.contains("tool_use"));
+
+        assert!(matches!(
+            export_session_in(&projects, "missing", PROJECT, ExportFormat::Markdown, false),
+            Err(Error::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_session_item_copies_and_shows_transcript() {
+        let long = format!(
+            r#"{{"type":"user","message":{{"content":"{}"}},"timestamp":"2024-12-31T00:00:00Z"}}"#,
+            "word ".repeat(600)
+        );
+        let projects = projects_fixture(
+            "sessions-item-text",
+            &[
+                (
+                    "abc.jsonl",
+                    include_str!("../tests/fixtures/sessions/basic.jsonl"),
+                ),
+                ("long.jsonl", &long),
+            ],
+        );
+
+        let item = session_item_in(&projects, "abc", PROJECT);
+        assert_eq!(item.title, "Fix the symlink bug in link_workflow");
+        let text = item.text.unwrap();
+        let copy = text.copy.unwrap();
+        assert!(copy.starts_with("## User\n\nFix the symlink bug"));
+        assert_eq!(text.largetype.as_deref(), Some(copy.as_str()));
+
+        let text = session_item_in(&projects, "long", PROJECT).text.unwrap();
+        assert!(text.copy.unwrap().chars().count() > LARGETYPE_MAX_CHARS);
+        let largetype = text.largetype.unwrap();
+        assert_eq!(largetype.chars().count(), LARGETYPE_MAX_CHARS);
+        assert!(largetype.ends_with('…'));
+
+        let missing = session_item_in(&projects, "missing", PROJECT);
+        assert_eq!(missing.valid, Some(false));
+    }
+
+    #[test]
+    fn test_session_list_item_from_meta() {
+        let projects = projects_fixture(
+            "sessions-item",
+            &[(
+                "abc.jsonl",
+                include_str!("../tests/fixtures/sessions/basic.jsonl"),
+            )],
+        );
+        let sessions = list_sessions_in(&projects, PROJECT, "");
+        let item = session_list_item(&sessions[0], PROJECT, TimeFormat::Iso);
+        assert_eq!(item.title, "Fix the symlink bug in link_workflow");
+        assert_eq!(item.subtitle, Some(sessions[0].subtitle(TimeFormat::Iso)));
+        assert_eq!(item.arg, Some(format!("abc|{}", PROJECT)));
+        // The transcript is exported on demand, not embedded in the item
+        assert_eq!(item.text, None);
+        let cmd = item.mods.unwrap();
+        assert_eq!(
+            cmd.get(crate::ModKey::CMD).unwrap().arg,
+            Some(resume_command("abc", PROJECT))
+        );
+    }
+
+    #[test]
+    fn test_export_streams_large_transcript() {
+        use std::io::Write;
+
+        let projects = projects_fixture("sessions-large", &[]);
+        let path = projects
+            .join(encode_project_folder(PROJECT))
+            .join("big.jsonl");
+        let mut file = std::io::BufWriter::new(fs::File::create(&path).unwrap());
+        let padding = "x".repeat(200);
+        for i in 0..10_000 {
+            writeln!(
+                file,
+                r#"{{"type":"user","timestamp":"2025-01-02T10:00:00Z","message":{{"content":"question {} {}"}}}}"#,
+                i, padding
+            )
+            .unwrap();
+            writeln!(file, r#"{{"type":"progress","data":{{}}}}"#).unwrap();
+            writeln!(
+                file,
+                r#"{{"type":"assistant","message":{{"content":[{{"type":"text","text":"answer {}"}}]}}}}"#,
+                i
+            )
+            .unwrap();
+        }
+        drop(file);
+
+        let content =
+            export_session_in(&projects, "big", PROJECT, ExportFormat::Plain, false).unwrap();
+        assert_eq!(content.matches("\nUser:\n").count() + 1, 10_000);
+        assert_eq!(content.matches("Assistant:\n").count(), 10_000);
+        assert!(content.starts_with("User:\nquestion 0 x"));
+        assert!(content.ends_with("Assistant:\nanswer 9999"));
+
+        let meta = read_session_meta(&path).unwrap();
+        assert_eq!(meta.message_count, 20_000);
+        assert_eq!(
+            meta.first_message,
+            format!("question 0 {}", padding)
+                .chars()
+                .take(80)
+                .collect::<String>()
+        );
+    }
+
+    #[test]
+    fn test_preview_from_string_content() {
+        let projects = projects_fixture(
+            "sessions-string-preview",
+            &[(
+                "abc.jsonl",
+                include_str!("../tests/fixtures/sessions/basic.jsonl"),
+            )],
+        );
+
+        let sessions = list_sessions_in(&projects, PROJECT, "");
+        assert_eq!(
+            sessions[0].first_message,
+            "Fix the symlink bug in link_workflow"
+        );
+    }
+
+    #[test]
+    fn test_preview_from_array_content_skips_tool_results() {
+        let projects = projects_fixture(
+            "sessions-array-preview",
+            &[(
+                "arr.jsonl",
+                include_str!("../tests/fixtures/sessions/array_content.jsonl"),
+            )],
+        );
+
+        let sessions = list_sessions_in(&projects, PROJECT, "");
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].first_message, "Why does the window switcher");
+
+        let content =
+            export_session_in(&projects, "arr", PROJECT, ExportFormat::Markdown, false).unwrap();
+        assert!(
+            content.starts_with("## User\n\nWhy does the window switcher\nmiss full-screen apps?")
+        );
+        assert!(!content.contains("tool_result"));
+    }
+
+    #[test]
+    fn test_search_matches_assistant_only_term() {
+        let projects = projects_fixture(
+            "sessions-search",
+            &[
+                (
+                    "abc.jsonl",
+                    include_str!("../tests/fixtures/sessions/basic.jsonl"),
+                ),
+                (
+                    "arr.jsonl",
+                    include_str!("../tests/fixtures/sessions/array_content.jsonl"),
+                ),
+            ],
+        );
+
+        // "own space" only appears in an assistant reply
+        let matches = search_sessions_in(&projects, PROJECT, "own SPACE");
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].meta.id, "arr");
+        assert_eq!(
+            matches[0].snippet,
+            "…-screen windows live on their own Space."
+        );
+
+        assert!(search_sessions_in(&projects, PROJECT, "nonexistent term").is_empty());
+    }
+
+    #[test]
+    fn test_export_formats() {
+        let projects = projects_fixture(
+            "sessions-export",
+            &[(
+                "old.jsonl",
+                include_str!("../tests/fixtures/sessions/older.jsonl"),
+            )],
+        );
+        let export = |format, timestamps| {
+            export_session_in(&projects, "old", PROJECT, format, timestamps).unwrap()
+        };
+
+        assert_eq!(
+            export(ExportFormat::Markdown, false),
+            "## User\n\nAdd repo caching\n\n## Assistant\n\nSure."
+        );
+        assert_eq!(
+            export(ExportFormat::Plain, false),
+            "User:\nAdd repo caching\n\nAssistant:\nSure."
+        );
+        assert_eq!(
+            export(ExportFormat::Markdown, true),
+            "## User (2024-12-30T08:00:00Z)\n\nAdd repo caching\n\n## Assistant (2024-12-30T08:00:10Z)\n\nSure."
+        );
+
+        let json: serde_json::Value =
+            serde_json::from_str(&export(ExportFormat::Json, false)).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!([
+                {"role": "user", "text": "Add repo caching"},
+                {"role": "assistant", "text": "Sure."}
+            ])
+        );
+        let json: serde_json::Value =
+            serde_json::from_str(&export(ExportFormat::Json, true)).unwrap();
+        assert_eq!(json[1]["timestamp"], "2024-12-30T08:00:10Z");
+    }
+
+    #[test]
+    fn test_export_format_from_str() {
+        assert_eq!("Markdown".parse(), Ok(ExportFormat::Markdown));
+        assert_eq!("plain".parse(), Ok(ExportFormat::Plain));
+        assert_eq!("json".parse(), Ok(ExportFormat::Json));
+        assert!("html".parse::<ExportFormat>().is_err());
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        // 2024-01-02 15:04:05 UTC
+        let unix = 1_704_207_845;
+        let tz = chrono::FixedOffset::east_opt(2 * 3600).unwrap();
+        assert_eq!(
+            format_timestamp_in(unix, TimeFormat::Absolute, &tz),
+            "2024-01-02 17:04"
+        );
+        assert_eq!(
+            format_timestamp_in(unix, TimeFormat::Iso, &tz),
+            "2024-01-02T15:04:05Z"
+        );
+        assert_eq!(
+            format_timestamp_in(unix, TimeFormat::Relative, &tz),
+            crate::format_relative_time(unix)
+        );
+        assert!(format_timestamp(unix, TimeFormat::Relative).ends_with(" ago"));
+
+        assert_eq!("ISO".parse(), Ok(TimeFormat::Iso));
+        assert_eq!("absolute".parse(), Ok(TimeFormat::Absolute));
+        assert!("epoch".parse::<TimeFormat>().is_err());
+    }
+
+    #[test]
+    fn test_claude_config_dir_env_override() {
+        let home = || Some(PathBuf::from("/Users/me"));
+        assert_eq!(
+            claude_config_dir_from(Some("/tmp/flow-alfred-claude-config".into()), home()),
+            PathBuf::from("/tmp/flow-alfred-claude-config")
+        );
+        assert_eq!(
+            claude_config_dir_from(Some(String::new()), home()),
+            PathBuf::from("/Users/me/.claude")
+        );
+        assert_eq!(
+            claude_config_dir_from(None, home()),
+            PathBuf::from("/Users/me/.claude")
+        );
+        assert_eq!(claude_config_dir_from(None, None), PathBuf::new());
+    }
+
+    #[test]
+    fn test_resume_command_quotes_path() {
+        assert_eq!(
+            resume_command("4f1c-9a", "/Users/me/My Projects/alfred"),
+            "cd '/Users/me/My Projects/alfred' && claude --resume 4f1c-9a"
+        );
+    }
+
+    #[test]
+    fn test_decode_project_folder() {
+        // Unknown paths decode every dash as a separator
+        assert_eq!(
+            decode_project_folder("-Users-nobody-code-alfred"),
+            PathBuf::from("/Users/nobody/code/alfred")
+        );
+
+        // Existing segments with dashes are preserved
+        let root = fixture_dir("sessions-decode");
+        let project = root.join("my-app");
+        fs::create_dir_all(&project).unwrap();
+        let encoded = encode_project_folder(&project.to_string_lossy());
+        assert_eq!(decode_project_folder(&encoded), project);
+    }
+
+    #[test]
+    fn test_encode_project_folder_matches_claude() {
+        assert_eq!(
+            encode_project_folder("/Users/nikiv/code/alfred"),
+            "-Users-nikiv-code-alfred"
+        );
+        assert_eq!(
+            encode_project_folder("/Users/nikiv/code/flow.rs"),
+            "-Users-nikiv-code-flow-rs"
+        );
+        assert_eq!(
+            encode_project_folder("/Users/nikiv/.config/my_app/"),
+            "-Users-nikiv--config-my-app"
+        );
+        assert_eq!(encode_project_folder("/"), "-");
+    }
+
+    #[test]
+    fn test_sessions_found_for_differently_encoded_folder() {
+        let root = fixture_dir("sessions-encoded");
+        let project = root.join("code").join("site.io");
+        fs::create_dir_all(&project).unwrap();
+        assert_eq!(
+            decode_project_folder(&encode_project_folder(&project.to_string_lossy())),
+            project
+        );
+
+        // Folder named with the old `/` -> `-` scheme, which keeps the dot
+        let projects = root.join("projects");
+        let folder = projects.join(project.to_string_lossy().replace('/', "-"));
+        fs::create_dir_all(&folder).unwrap();
+        fs::write(
+            folder.join("abc.jsonl"),
+            include_str!("../tests/fixtures/sessions/basic.jsonl"),
+        )
+        .unwrap();
+
+        let with_slash = format!("{}/", project.display());
+        let sessions = list_sessions_in(&projects, &with_slash, "");
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].id, "abc");
+        assert!(
+            export_session_in(&projects, "abc", &with_slash, ExportFormat::Plain, false).is_ok()
+        );
+    }
+
+    #[test]
+    fn test_list_session_projects_counts() {
+        let projects = fixture_dir("sessions-projects");
+        let alfred = projects.join("-Users-nobody-code-alfred");
+        let flow = projects.join("-Users-nobody-code-flow");
+        let empty = projects.join("-Users-nobody-code-empty");
+        for dir in [&alfred, &flow, &empty] {
+            fs::create_dir_all(dir).unwrap();
+        }
+        fs::write(alfred.join("a.jsonl"), "").unwrap();
+        fs::write(alfred.join("b.jsonl"), "").unwrap();
+        fs::write(alfred.join("notes.txt"), "").unwrap();
+        fs::write(flow.join("c.jsonl"), "").unwrap();
+
+        let mut found = list_session_projects_in(&projects);
+        found.sort_by(|a, b| a.folder.cmp(&b.folder));
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].path, PathBuf::from("/Users/nobody/code/alfred"));
+        assert_eq!(found[0].session_count, 2);
+        assert_eq!(found[1].session_count, 1);
+        assert!(found[0].last_activity > 0);
+    }
+}